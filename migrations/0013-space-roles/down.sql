@@ -0,0 +1,4 @@
+DROP TABLE channel_role_overrides;
+DROP TABLE space_member_roles;
+DROP TABLE space_roles;
+DROP TYPE space_role_kind;
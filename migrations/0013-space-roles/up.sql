@@ -0,0 +1,50 @@
+CREATE TYPE space_role_kind AS ENUM (
+    'Custom',
+    'Everyone',
+    'Admin',
+    'Master'
+    );
+
+CREATE TABLE space_roles
+(
+    "id"          uuid            NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
+    "space_id"    uuid            NOT NULL
+        CONSTRAINT "role_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "name"        text            NOT NULL,
+    "kind"        space_role_kind NOT NULL DEFAULT 'Custom',
+    "permissions" bigint          NOT NULL DEFAULT 0,
+    "created"     timestamp       NOT NULL DEFAULT (now() at time zone 'utc'),
+    CONSTRAINT "unique_role_name_in_space" UNIQUE (space_id, name)
+);
+
+CREATE UNIQUE INDEX "unique_builtin_role" ON space_roles (space_id, kind) WHERE kind <> 'Custom';
+
+CREATE TABLE space_member_roles
+(
+    "user_id"  uuid NOT NULL,
+    "space_id" uuid NOT NULL,
+    "role_id"  uuid NOT NULL
+        CONSTRAINT "member_role_role" REFERENCES space_roles (id) ON DELETE CASCADE,
+    CONSTRAINT "member_role_member" FOREIGN KEY (user_id, space_id)
+        REFERENCES space_members (user_id, space_id) ON DELETE CASCADE,
+    CONSTRAINT "member_role_pair" PRIMARY KEY (user_id, space_id, role_id)
+);
+
+CREATE TABLE channel_role_overrides
+(
+    "channel_id" uuid   NOT NULL
+        CONSTRAINT "override_channel" REFERENCES channels (id) ON DELETE CASCADE,
+    "role_id"    uuid   NOT NULL
+        CONSTRAINT "override_role" REFERENCES space_roles (id) ON DELETE CASCADE,
+    "allow"      bigint NOT NULL DEFAULT 0,
+    "deny"       bigint NOT NULL DEFAULT 0,
+    CONSTRAINT "channel_role_pair" PRIMARY KEY (channel_id, role_id)
+);
+
+-- The admin and master flags keep marking membership of the built-in roles.
+INSERT INTO space_roles (space_id, name, kind, permissions)
+SELECT spaces.id, builtin.name, builtin.kind, builtin.permissions
+FROM spaces,
+     (VALUES ('Everyone', 'Everyone'::space_role_kind, 960::bigint),
+             ('Admin', 'Admin'::space_role_kind, 1::bigint),
+             ('Master', 'Master'::space_role_kind, 1024::bigint)) AS builtin (name, kind, permissions);
@@ -0,0 +1,17 @@
+CREATE TABLE space_invites
+(
+    "id"          uuid      NOT NULL DEFAULT uuid_generate_v4() PRIMARY KEY,
+    "space_id"    uuid      NOT NULL
+        CONSTRAINT "invite_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "creator_id"  uuid      NOT NULL
+        CONSTRAINT "invite_creator" REFERENCES users (id) ON DELETE CASCADE,
+    "label"       text      NOT NULL DEFAULT '',
+    "max_uses"    integer            DEFAULT NULL,
+    "uses"        integer   NOT NULL DEFAULT 0,
+    "channel_ids" uuid[]    NOT NULL DEFAULT '{}',
+    "expires_at"  timestamp          DEFAULT NULL,
+    "revoked"     boolean   NOT NULL DEFAULT false,
+    "created"     timestamp NOT NULL DEFAULT (now() at time zone 'utc')
+);
+
+CREATE INDEX "space_invites_space" ON space_invites (space_id);
@@ -0,0 +1,4 @@
+-- The hashes can't be reversed, clear them instead.
+UPDATE spaces
+SET password = ''
+WHERE password <> '';
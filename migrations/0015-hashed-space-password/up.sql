@@ -0,0 +1,4 @@
+-- Hash the plain text passwords in place, so current passwords keep working.
+UPDATE spaces
+SET password = crypt(password, gen_salt('bf'))
+WHERE password <> '';
@@ -0,0 +1,10 @@
+CREATE TABLE space_ownership_transfers
+(
+    "space_id"     uuid      NOT NULL PRIMARY KEY
+        CONSTRAINT "transfer_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "from_user_id" uuid      NOT NULL
+        CONSTRAINT "transfer_from_user" REFERENCES users (id) ON DELETE CASCADE,
+    "to_user_id"   uuid      NOT NULL
+        CONSTRAINT "transfer_to_user" REFERENCES users (id) ON DELETE CASCADE,
+    "created"      timestamp NOT NULL DEFAULT (now() at time zone 'utc')
+);
@@ -0,0 +1,16 @@
+-- A template instantiates the current state of its source space.
+CREATE TABLE space_templates
+(
+    "id"                uuid      NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
+    "space_id"          uuid      NOT NULL
+        CONSTRAINT "template_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "creator_id"        uuid      NOT NULL
+        CONSTRAINT "template_creator" REFERENCES users (id) ON DELETE CASCADE,
+    "name"              text      NOT NULL,
+    "description"       text      NOT NULL DEFAULT '',
+    "is_public"         boolean   NOT NULL DEFAULT false,
+    "include_documents" boolean   NOT NULL DEFAULT true,
+    "created"           timestamp NOT NULL DEFAULT (now() at time zone 'utc')
+);
+
+CREATE INDEX "space_templates_creator" ON space_templates (creator_id);
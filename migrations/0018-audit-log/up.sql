@@ -0,0 +1,41 @@
+CREATE TYPE audit_action AS ENUM (
+    'SpaceEdited',
+    'InviteTokenRefreshed',
+    'InviteCreated',
+    'InviteRevoked',
+    'MemberKicked',
+    'AdminGranted',
+    'AdminRevoked',
+    'OwnershipTransferred',
+    'RoleCreated',
+    'RoleEdited',
+    'RoleDeleted',
+    'RoleAssigned',
+    'RoleUnassigned',
+    'ChannelCreated',
+    'ChannelEdited',
+    'ChannelDeleted',
+    'MasterGranted',
+    'MasterRevoked',
+    'RoleOverrideSet',
+    'RoleOverrideRemoved',
+    'MessageDeleted',
+    'MessageFolded'
+    );
+
+-- The channel and target are not foreign keys, the log outlives them.
+CREATE TABLE audit_logs
+(
+    "id"         uuid         NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
+    "space_id"   uuid         NOT NULL
+        CONSTRAINT "audit_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "actor_id"   uuid                  DEFAULT NULL
+        CONSTRAINT "audit_actor" REFERENCES users (id) ON DELETE SET NULL,
+    "action"     audit_action NOT NULL,
+    "channel_id" uuid                  DEFAULT NULL,
+    "target_id"  uuid                  DEFAULT NULL,
+    "payload"    jsonb        NOT NULL DEFAULT '{}',
+    "created"    timestamp    NOT NULL DEFAULT (now() at time zone 'utc')
+);
+
+CREATE INDEX "audit_logs_space_created" ON audit_logs (space_id, created DESC);
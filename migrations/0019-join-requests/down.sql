@@ -0,0 +1,3 @@
+-- The values added to `audit_action` can't be dropped.
+DROP TABLE IF EXISTS space_join_requests;
+DROP TYPE IF EXISTS join_request_status;
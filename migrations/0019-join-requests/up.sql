@@ -0,0 +1,25 @@
+CREATE TYPE join_request_status AS ENUM (
+    'Pending',
+    'Approved',
+    'Rejected'
+    );
+
+CREATE TABLE space_join_requests
+(
+    "id"         uuid                NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
+    "space_id"   uuid                NOT NULL
+        CONSTRAINT "join_request_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "user_id"    uuid                NOT NULL
+        CONSTRAINT "join_request_user" REFERENCES users (id) ON DELETE CASCADE,
+    "message"    text                NOT NULL DEFAULT '',
+    "status"     join_request_status NOT NULL DEFAULT 'Pending',
+    "decided_by" uuid                         DEFAULT NULL
+        CONSTRAINT "join_request_decider" REFERENCES users (id) ON DELETE SET NULL,
+    "created"    timestamp           NOT NULL DEFAULT (now() at time zone 'utc'),
+    "decided"    timestamp                    DEFAULT NULL
+);
+
+CREATE UNIQUE INDEX "unique_pending_join_request" ON space_join_requests (space_id, user_id) WHERE status = 'Pending';
+
+ALTER TYPE audit_action ADD VALUE 'JoinRequestApproved';
+ALTER TYPE audit_action ADD VALUE 'JoinRequestRejected';
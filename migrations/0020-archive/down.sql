@@ -0,0 +1,5 @@
+-- The values added to `audit_action` can't be dropped.
+ALTER TABLE channels
+    DROP COLUMN IF EXISTS "archived";
+ALTER TABLE spaces
+    DROP COLUMN IF EXISTS "archived";
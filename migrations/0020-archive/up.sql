@@ -0,0 +1,9 @@
+ALTER TABLE spaces
+    ADD COLUMN "archived" boolean NOT NULL DEFAULT false;
+ALTER TABLE channels
+    ADD COLUMN "archived" boolean NOT NULL DEFAULT false;
+
+ALTER TYPE audit_action ADD VALUE 'SpaceArchived';
+ALTER TYPE audit_action ADD VALUE 'SpaceUnarchived';
+ALTER TYPE audit_action ADD VALUE 'ChannelArchived';
+ALTER TYPE audit_action ADD VALUE 'ChannelUnarchived';
@@ -0,0 +1,4 @@
+ALTER TABLE channels
+    DROP COLUMN IF EXISTS "category_id",
+    DROP COLUMN IF EXISTS "position";
+DROP TABLE IF EXISTS channel_categories;
@@ -0,0 +1,16 @@
+CREATE TABLE channel_categories
+(
+    "id"       uuid      NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
+    "space_id" uuid      NOT NULL
+        CONSTRAINT "category_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "name"     text      NOT NULL,
+    "position" integer   NOT NULL DEFAULT 0,
+    "created"  timestamp NOT NULL DEFAULT (now() at time zone 'utc')
+);
+
+CREATE INDEX "channel_categories_space" ON channel_categories (space_id);
+
+ALTER TABLE channels
+    ADD COLUMN "category_id" uuid DEFAULT NULL
+        CONSTRAINT "channel_category" REFERENCES channel_categories (id) ON DELETE SET NULL,
+    ADD COLUMN "position"    integer NOT NULL DEFAULT 0;
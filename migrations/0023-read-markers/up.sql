@@ -0,0 +1,15 @@
+CREATE TABLE channel_read_markers
+(
+    "user_id"       uuid      NOT NULL
+        CONSTRAINT "read_marker_user" REFERENCES users (id) ON DELETE CASCADE,
+    "channel_id"    uuid      NOT NULL
+        CONSTRAINT "read_marker_channel" REFERENCES channels (id) ON DELETE CASCADE,
+    "pos"           float     NOT NULL DEFAULT 0.0,
+    -- Counters maintained on new messages, recounted when the marker advances.
+    "unread_count"  integer   NOT NULL DEFAULT 0,
+    "mention_count" integer   NOT NULL DEFAULT 0,
+    "updated"       timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    PRIMARY KEY (user_id, channel_id)
+);
+
+CREATE INDEX "read_markers_channel" ON channel_read_markers (channel_id);
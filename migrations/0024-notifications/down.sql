@@ -0,0 +1,5 @@
+-- The values added to `event_type` can't be dropped.
+DROP INDEX IF EXISTS "events_receiver";
+ALTER TABLE events
+    ALTER COLUMN "id" DROP DEFAULT,
+    DROP COLUMN IF EXISTS "read";
@@ -0,0 +1,11 @@
+ALTER TYPE event_type ADD VALUE 'Mentioned';
+ALTER TYPE event_type ADD VALUE 'Replied';
+ALTER TYPE event_type ADD VALUE 'Invited';
+ALTER TYPE event_type ADD VALUE 'JoinRequested';
+ALTER TYPE event_type ADD VALUE 'JoinRequestDecided';
+
+ALTER TABLE events
+    ALTER COLUMN "id" SET DEFAULT uuid_generate_v1mc(),
+    ADD COLUMN "read" boolean NOT NULL DEFAULT false;
+
+CREATE INDEX "events_receiver" ON events (receiver_id, created DESC);
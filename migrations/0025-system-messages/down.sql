@@ -0,0 +1,5 @@
+ALTER TABLE channels
+    DROP COLUMN IF EXISTS "system_events";
+ALTER TABLE messages
+    DROP COLUMN IF EXISTS "system_event";
+DROP TYPE IF EXISTS system_event;
@@ -0,0 +1,12 @@
+CREATE TYPE system_event AS ENUM (
+    'Joined',
+    'Left',
+    'BecameMaster',
+    'Renamed'
+);
+
+ALTER TABLE messages
+    ADD COLUMN "system_event" system_event DEFAULT NULL;
+
+ALTER TABLE channels
+    ADD COLUMN "system_events" system_event[] NOT NULL DEFAULT '{Joined,Left,BecameMaster,Renamed}';
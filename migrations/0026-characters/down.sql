@@ -0,0 +1,3 @@
+ALTER TABLE messages
+    DROP COLUMN IF EXISTS "character_id";
+DROP TABLE IF EXISTS channel_characters;
@@ -0,0 +1,19 @@
+CREATE TABLE channel_characters
+(
+    "id"          uuid      NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
+    "channel_id"  uuid      NOT NULL
+        CONSTRAINT "character_channel" REFERENCES channels (id) ON DELETE CASCADE,
+    "user_id"     uuid      NOT NULL
+        CONSTRAINT "character_user" REFERENCES users (id) ON DELETE CASCADE,
+    "name"        text      NOT NULL,
+    "text_color"  text               DEFAULT NULL,
+    "avatar_id"   uuid               DEFAULT NULL
+        CONSTRAINT "character_avatar" REFERENCES media (id) ON DELETE SET NULL,
+    "description" text      NOT NULL DEFAULT '',
+    "created"     timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    CONSTRAINT "unique_character_name" UNIQUE (channel_id, user_id, name)
+);
+
+ALTER TABLE messages
+    ADD COLUMN "character_id" uuid DEFAULT NULL
+        CONSTRAINT "message_character" REFERENCES channel_characters (id) ON DELETE SET NULL;
@@ -0,0 +1,3 @@
+ALTER TABLE channels
+    DROP COLUMN IF EXISTS "slow_mode_seconds",
+    DROP COLUMN IF EXISTS "preview_rate_limit";
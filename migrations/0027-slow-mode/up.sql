@@ -0,0 +1,3 @@
+ALTER TABLE channels
+    ADD COLUMN "slow_mode_seconds"  integer NOT NULL DEFAULT 0,
+    ADD COLUMN "preview_rate_limit" integer NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+-- The values added to `audit_action` can't be dropped.
@@ -0,0 +1,10 @@
+CREATE TABLE message_reactions
+(
+    "message_id" uuid      NOT NULL
+        CONSTRAINT "reaction_message" REFERENCES messages (id) ON DELETE CASCADE,
+    "user_id"    uuid      NOT NULL
+        CONSTRAINT "reaction_user" REFERENCES users (id) ON DELETE CASCADE,
+    "emoji"      text      NOT NULL,
+    "created"    timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    CONSTRAINT "message_reactions_pkey" PRIMARY KEY ("message_id", "user_id", "emoji")
+);
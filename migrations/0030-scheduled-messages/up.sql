@@ -0,0 +1,25 @@
+CREATE TABLE scheduled_messages
+(
+    "id"               uuid      NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
+    "channel_id"       uuid      NOT NULL
+        CONSTRAINT "scheduled_message_channel" REFERENCES channels (id) ON DELETE CASCADE,
+    "sender_id"        uuid      NOT NULL
+        CONSTRAINT "scheduled_message_sender" REFERENCES users (id) ON DELETE CASCADE,
+    "name"             text      NOT NULL,
+    "text"             text      NOT NULL,
+    "entities"         jsonb     NOT NULL DEFAULT '[]',
+    "in_game"          boolean   NOT NULL DEFAULT false,
+    "is_action"        boolean   NOT NULL DEFAULT false,
+    "media_id"         uuid               DEFAULT null
+        CONSTRAINT "scheduled_message_media" REFERENCES media (id) ON DELETE SET NULL,
+    "whisper_to_users" uuid[]             DEFAULT null,
+    "character_id"     uuid               DEFAULT null
+        CONSTRAINT "scheduled_message_character" REFERENCES channel_characters (id) ON DELETE SET NULL,
+    "tags"             text[]    NOT NULL DEFAULT '{}',
+    "send_at"          timestamp NOT NULL,
+    "created"          timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    "modified"         timestamp NOT NULL DEFAULT (now() at time zone 'utc')
+);
+
+CREATE INDEX "scheduled_messages_send_at" ON scheduled_messages (send_at);
+CREATE INDEX "scheduled_messages_channel_sender" ON scheduled_messages (channel_id, sender_id);
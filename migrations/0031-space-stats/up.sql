@@ -0,0 +1,42 @@
+-- Counters of explorable spaces, refreshed periodically instead of counted on every page.
+CREATE TABLE space_stats
+(
+    "space_id"      uuid      NOT NULL PRIMARY KEY
+        CONSTRAINT "space_stats_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "member_count"  bigint    NOT NULL DEFAULT 0,
+    "channel_count" bigint    NOT NULL DEFAULT 0,
+    "last_message"  timestamp          DEFAULT null,
+    -- Milliseconds since epoch of the last message, or of the creation of the space.
+    "activity"      bigint    NOT NULL DEFAULT 0,
+    "refreshed"     timestamp NOT NULL DEFAULT (now() at time zone 'utc')
+);
+
+CREATE INDEX "space_stats_members" ON space_stats (member_count DESC, space_id DESC);
+CREATE INDEX "space_stats_activity" ON space_stats (activity DESC, space_id DESC);
+
+INSERT INTO space_stats (space_id, member_count, channel_count, last_message, activity, refreshed)
+SELECT s.id,
+       COALESCE(members.count, 0),
+       COALESCE(channels.count, 0),
+       messages.last_message,
+       (extract(EPOCH FROM COALESCE(messages.last_message, s.created)) * 1000)::bigint,
+       (now() at time zone 'utc')
+FROM spaces s
+         LEFT JOIN (SELECT space_id, count(*) AS count FROM space_members GROUP BY space_id) members
+                   ON members.space_id = s.id
+         LEFT JOIN (SELECT space_id, count(*) AS count FROM channels WHERE deleted = false GROUP BY space_id) channels
+                   ON channels.space_id = s.id
+         LEFT JOIN (SELECT c.space_id, max(m.created) AS last_message
+                    FROM messages m
+                             INNER JOIN channels c ON m.channel_id = c.id
+                    WHERE m.deleted = false
+                    GROUP BY c.space_id) messages
+                   ON messages.space_id = s.id
+WHERE s.deleted = false
+  AND s.explorable = true
+  AND s.is_public = true
+ON CONFLICT (space_id) DO UPDATE SET member_count  = excluded.member_count,
+                                     channel_count = excluded.channel_count,
+                                     last_message  = excluded.last_message,
+                                     activity      = excluded.activity,
+                                     refreshed     = excluded.refreshed;
@@ -0,0 +1,3 @@
+ALTER TABLE scheduled_messages
+    DROP COLUMN IF EXISTS "attempts",
+    DROP COLUMN IF EXISTS "failed_at";
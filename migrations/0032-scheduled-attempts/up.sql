@@ -0,0 +1,3 @@
+ALTER TABLE scheduled_messages
+    ADD COLUMN "attempts" integer NOT NULL DEFAULT 0,
+    ADD COLUMN "failed_at" timestamp DEFAULT null;
@@ -0,0 +1,13 @@
+-- Whether the entities of a message mention a member, by user id or by the name of their character.
+CREATE OR REPLACE FUNCTION mentions(entities jsonb, user_id uuid, character_name text) RETURNS boolean
+    LANGUAGE sql
+    IMMUTABLE
+AS
+$$
+SELECT EXISTS(SELECT 1
+              FROM jsonb_array_elements(entities) e
+              WHERE e ->> 'type' = 'Mention'
+                AND (e ->> 'userId' = user_id::text
+                  OR (character_name <> ''
+                      AND btrim(regexp_replace(e ->> 'characterName', '\s+', ' ', 'g')) = character_name)))
+$$;
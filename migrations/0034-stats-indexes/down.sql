@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS "space_members_space";
+DROP INDEX IF EXISTS "message_channel_public_created";
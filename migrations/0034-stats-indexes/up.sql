@@ -0,0 +1,4 @@
+CREATE INDEX IF NOT EXISTS "space_members_space" ON space_members (space_id);
+-- The last visible message of each channel, see `refresh_stats.sql`.
+CREATE INDEX IF NOT EXISTS "message_channel_public_created" ON messages (channel_id, created)
+    WHERE deleted = false AND whisper_to_users IS NULL;
@@ -53,12 +53,13 @@ CREATE TABLE spaces
         CONSTRAINT "space_owner" REFERENCES users (id) ON DELETE RESTRICT,
     "is_public"         boolean   NOT NULL DEFAULT true,
     "deleted"           boolean   NOT NULL DEFAULT false,
-    "password"          text      NOT NULL DEFAULT '',    -- plain text
+    "password"          text      NOT NULL DEFAULT '',    -- bcrypt hash, empty for no password
     "language"          text      NOT NULL DEFAULT '',    -- ISO 639-1
     "default_dice_type" text      NOT NULL DEFAULT 'd20', -- d20, d100, FATE ...
     "invite_token"      uuid      NOT NULL DEFAULT gen_random_uuid(),
     "explorable"        boolean   NOT NULL DEFAULT false,
-    "allow_spectator"   boolean   NOT NULL DEFAULT true
+    "allow_spectator"   boolean   NOT NULL DEFAULT true,
+    "archived"          boolean   NOT NULL DEFAULT false
 );
 
 CREATE TABLE space_members
@@ -72,6 +73,32 @@ CREATE TABLE space_members
     CONSTRAINT "user_space_id_pair" PRIMARY KEY ("user_id", "space_id")
 );
 
+CREATE INDEX "space_members_space" ON space_members (space_id);
+
+-- Counters of explorable spaces, refreshed periodically instead of counted on every page.
+CREATE TABLE space_stats
+(
+    "space_id"      uuid      NOT NULL PRIMARY KEY
+        CONSTRAINT "space_stats_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "member_count"  bigint    NOT NULL DEFAULT 0,
+    "channel_count" bigint    NOT NULL DEFAULT 0,
+    "last_message"  timestamp          DEFAULT null,
+    -- Milliseconds since epoch of the last message, or of the creation of the space.
+    "activity"      bigint    NOT NULL DEFAULT 0,
+    "refreshed"     timestamp NOT NULL DEFAULT (now() at time zone 'utc')
+);
+
+CREATE INDEX "space_stats_members" ON space_stats (member_count DESC, space_id DESC);
+CREATE INDEX "space_stats_activity" ON space_stats (activity DESC, space_id DESC);
+
+-- Membership and role changes recorded into the channel timeline.
+CREATE TYPE system_event AS ENUM (
+    'Joined',
+    'Left',
+    'BecameMaster',
+    'Renamed'
+);
+
 CREATE TABLE channels
 (
     "id"                   uuid      NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
@@ -86,6 +113,15 @@ CREATE TABLE channels
     "default_roll_command" text      NOT NULL DEFAULT 'd',
     "is_document"          bool      NOT NULL DEFAULT false,
     "old_name"             text      NOT NULL DEFAULT '',
+    "archived"             boolean   NOT NULL DEFAULT false,
+    "category_id"          uuid               DEFAULT NULL,
+    "position"             integer   NOT NULL DEFAULT 0,
+    -- Kinds of system messages posted into the channel.
+    "system_events"        system_event[] NOT NULL DEFAULT '{Joined,Left,BecameMaster,Renamed}',
+    -- Minimum interval between messages of a non-master member, 0 to disable.
+    "slow_mode_seconds"    integer   NOT NULL DEFAULT 0,
+    -- Maximum previews a member broadcasts per second, 0 for no limit.
+    "preview_rate_limit"   integer   NOT NULL DEFAULT 0,
     CONSTRAINT "unique_channel_name_in_space" UNIQUE (space_id, name)
 );
 
@@ -103,6 +139,23 @@ CREATE TABLE channel_members
     CONSTRAINT "user_channel_id_pair" PRIMARY KEY ("user_id", "channel_id")
 );
 
+-- The roster of characters a member is able to speak as.
+CREATE TABLE channel_characters
+(
+    "id"          uuid      NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
+    "channel_id"  uuid      NOT NULL
+        CONSTRAINT "character_channel" REFERENCES channels (id) ON DELETE CASCADE,
+    "user_id"     uuid      NOT NULL
+        CONSTRAINT "character_user" REFERENCES users (id) ON DELETE CASCADE,
+    "name"        text      NOT NULL,
+    "text_color"  text               DEFAULT NULL,
+    "avatar_id"   uuid               DEFAULT NULL
+        CONSTRAINT "character_avatar" REFERENCES media (id) ON DELETE SET NULL,
+    "description" text      NOT NULL DEFAULT '',
+    "created"     timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    CONSTRAINT "unique_character_name" UNIQUE (channel_id, user_id, name)
+);
+
 CREATE TABLE messages
 (
     "id"                uuid      NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
@@ -134,7 +187,11 @@ CREATE TABLE messages
     "modified"          timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
     "order_date"        timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
     "order_offset"      integer   NOT NULL DEFAULT 0,
-    "pos"               float     NOT NULL DEFAULT 0.0
+    "pos"               float     NOT NULL DEFAULT 0.0,
+    -- Not null for messages posted by the server, see `channels.system_events`.
+    "system_event"      system_event       DEFAULT null,
+    "character_id"      uuid               DEFAULT null
+        CONSTRAINT "message_character" REFERENCES channel_characters (id) ON DELETE SET NULL
 );
 
 ALTER TABLE messages
@@ -143,6 +200,63 @@ ALTER TABLE messages
 CREATE INDEX "message_pos" ON messages (pos);
 CREATE INDEX "message_tags" ON messages USING GIN (tags);
 CREATE INDEX "message_channel" ON messages USING btree (channel_id);
+-- The last visible message of each channel, see `refresh_stats.sql`.
+CREATE INDEX "message_channel_public_created" ON messages (channel_id, created)
+    WHERE deleted = false AND whisper_to_users IS NULL;
+
+-- Whether the entities of a message mention a member, by user id or by the name of their character.
+CREATE OR REPLACE FUNCTION mentions(entities jsonb, user_id uuid, character_name text) RETURNS boolean
+    LANGUAGE sql
+    IMMUTABLE
+AS
+$$
+SELECT EXISTS(SELECT 1
+              FROM jsonb_array_elements(entities) e
+              WHERE e ->> 'type' = 'Mention'
+                AND (e ->> 'userId' = user_id::text
+                  OR (character_name <> ''
+                      AND btrim(regexp_replace(e ->> 'characterName', '\s+', ' ', 'g')) = character_name)))
+$$;
+
+CREATE TABLE message_reactions
+(
+    "message_id" uuid      NOT NULL
+        CONSTRAINT "reaction_message" REFERENCES messages (id) ON DELETE CASCADE,
+    "user_id"    uuid      NOT NULL
+        CONSTRAINT "reaction_user" REFERENCES users (id) ON DELETE CASCADE,
+    "emoji"      text      NOT NULL,
+    "created"    timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    CONSTRAINT "message_reactions_pkey" PRIMARY KEY ("message_id", "user_id", "emoji")
+);
+
+CREATE TABLE scheduled_messages
+(
+    "id"               uuid      NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
+    "channel_id"       uuid      NOT NULL
+        CONSTRAINT "scheduled_message_channel" REFERENCES channels (id) ON DELETE CASCADE,
+    "sender_id"        uuid      NOT NULL
+        CONSTRAINT "scheduled_message_sender" REFERENCES users (id) ON DELETE CASCADE,
+    "name"             text      NOT NULL,
+    "text"             text      NOT NULL,
+    "entities"         jsonb     NOT NULL DEFAULT '[]',
+    "in_game"          boolean   NOT NULL DEFAULT false,
+    "is_action"        boolean   NOT NULL DEFAULT false,
+    "media_id"         uuid               DEFAULT null
+        CONSTRAINT "scheduled_message_media" REFERENCES media (id) ON DELETE SET NULL,
+    "whisper_to_users" uuid[]             DEFAULT null,
+    "character_id"     uuid               DEFAULT null
+        CONSTRAINT "scheduled_message_character" REFERENCES channel_characters (id) ON DELETE SET NULL,
+    "tags"             text[]    NOT NULL DEFAULT '{}',
+    "send_at"          timestamp NOT NULL,
+    "created"          timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    "modified"         timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    "attempts"         integer   NOT NULL DEFAULT 0,
+    "failed_at"        timestamp          DEFAULT null
+);
+
+-- Sent and removed by a background task once `send_at` is reached.
+CREATE INDEX "scheduled_messages_send_at" ON scheduled_messages (send_at);
+CREATE INDEX "scheduled_messages_channel_sender" ON scheduled_messages (channel_id, sender_id);
 
 CREATE TABLE restrained_members
 (
@@ -162,12 +276,17 @@ CREATE TYPE event_type AS ENUM (
     'Joined',
     'Left',
     'NewMaster',
-    'NewAdmin'
+    'NewAdmin',
+    'Mentioned',
+    'Replied',
+    'Invited',
+    'JoinRequested',
+    'JoinRequestDecided'
     );
 
 CREATE TABLE events
 (
-    "id"          uuid       NOT NULL PRIMARY KEY,
+    "id"          uuid       NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
     "type"        event_type NOT NULL,
     "channel_id"  uuid                DEFAULT NULL
         CONSTRAINT "event_channel" REFERENCES channels (id) ON DELETE CASCADE,
@@ -176,5 +295,201 @@ CREATE TABLE events
     "receiver_id" uuid
         CONSTRAINT "event_receiver" REFERENCES users (id) ON DELETE CASCADE,
     "payload"     jsonb      NOT NULL DEFAULT '{}',
-    "created"     timestamp  NOT NULL default (now() at time zone 'utc')
+    "created"     timestamp  NOT NULL default (now() at time zone 'utc'),
+    "read"        boolean    NOT NULL DEFAULT false
+);
+
+CREATE INDEX "events_receiver" ON events (receiver_id, created DESC);
+
+CREATE TYPE space_role_kind AS ENUM (
+    'Custom',
+    'Everyone',
+    'Admin',
+    'Master'
+    );
+
+CREATE TABLE space_roles
+(
+    "id"          uuid            NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
+    "space_id"    uuid            NOT NULL
+        CONSTRAINT "role_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "name"        text            NOT NULL,
+    "kind"        space_role_kind NOT NULL DEFAULT 'Custom',
+    "permissions" bigint          NOT NULL DEFAULT 0,
+    "created"     timestamp       NOT NULL DEFAULT (now() at time zone 'utc'),
+    CONSTRAINT "unique_role_name_in_space" UNIQUE (space_id, name)
+);
+
+CREATE UNIQUE INDEX "unique_builtin_role" ON space_roles (space_id, kind) WHERE kind <> 'Custom';
+
+CREATE TABLE space_member_roles
+(
+    "user_id"  uuid NOT NULL,
+    "space_id" uuid NOT NULL,
+    "role_id"  uuid NOT NULL
+        CONSTRAINT "member_role_role" REFERENCES space_roles (id) ON DELETE CASCADE,
+    CONSTRAINT "member_role_member" FOREIGN KEY (user_id, space_id)
+        REFERENCES space_members (user_id, space_id) ON DELETE CASCADE,
+    CONSTRAINT "member_role_pair" PRIMARY KEY (user_id, space_id, role_id)
 );
+
+CREATE TABLE channel_role_overrides
+(
+    "channel_id" uuid   NOT NULL
+        CONSTRAINT "override_channel" REFERENCES channels (id) ON DELETE CASCADE,
+    "role_id"    uuid   NOT NULL
+        CONSTRAINT "override_role" REFERENCES space_roles (id) ON DELETE CASCADE,
+    "allow"      bigint NOT NULL DEFAULT 0,
+    "deny"       bigint NOT NULL DEFAULT 0,
+    CONSTRAINT "channel_role_pair" PRIMARY KEY (channel_id, role_id)
+);
+
+CREATE TABLE space_invites
+(
+    "id"          uuid      NOT NULL DEFAULT uuid_generate_v4() PRIMARY KEY,
+    "space_id"    uuid      NOT NULL
+        CONSTRAINT "invite_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "creator_id"  uuid      NOT NULL
+        CONSTRAINT "invite_creator" REFERENCES users (id) ON DELETE CASCADE,
+    "label"       text      NOT NULL DEFAULT '',
+    "max_uses"    integer            DEFAULT NULL,
+    "uses"        integer   NOT NULL DEFAULT 0,
+    "channel_ids" uuid[]    NOT NULL DEFAULT '{}',
+    "expires_at"  timestamp          DEFAULT NULL,
+    "revoked"     boolean   NOT NULL DEFAULT false,
+    "created"     timestamp NOT NULL DEFAULT (now() at time zone 'utc')
+);
+
+CREATE INDEX "space_invites_space" ON space_invites (space_id);
+
+CREATE TABLE space_ownership_transfers
+(
+    "space_id"     uuid      NOT NULL PRIMARY KEY
+        CONSTRAINT "transfer_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "from_user_id" uuid      NOT NULL
+        CONSTRAINT "transfer_from_user" REFERENCES users (id) ON DELETE CASCADE,
+    "to_user_id"   uuid      NOT NULL
+        CONSTRAINT "transfer_to_user" REFERENCES users (id) ON DELETE CASCADE,
+    "created"      timestamp NOT NULL DEFAULT (now() at time zone 'utc')
+);
+
+-- A template instantiates the current state of its source space.
+CREATE TABLE space_templates
+(
+    "id"                uuid      NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
+    "space_id"          uuid      NOT NULL
+        CONSTRAINT "template_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "creator_id"        uuid      NOT NULL
+        CONSTRAINT "template_creator" REFERENCES users (id) ON DELETE CASCADE,
+    "name"              text      NOT NULL,
+    "description"       text      NOT NULL DEFAULT '',
+    "is_public"         boolean   NOT NULL DEFAULT false,
+    "include_documents" boolean   NOT NULL DEFAULT true,
+    "created"           timestamp NOT NULL DEFAULT (now() at time zone 'utc')
+);
+
+CREATE INDEX "space_templates_creator" ON space_templates (creator_id);
+
+CREATE TYPE audit_action AS ENUM (
+    'SpaceEdited',
+    'InviteTokenRefreshed',
+    'InviteCreated',
+    'InviteRevoked',
+    'MemberKicked',
+    'AdminGranted',
+    'AdminRevoked',
+    'OwnershipTransferred',
+    'RoleCreated',
+    'RoleEdited',
+    'RoleDeleted',
+    'RoleAssigned',
+    'RoleUnassigned',
+    'ChannelCreated',
+    'ChannelEdited',
+    'ChannelDeleted',
+    'MasterGranted',
+    'MasterRevoked',
+    'RoleOverrideSet',
+    'RoleOverrideRemoved',
+    'MessageDeleted',
+    'MessageFolded',
+    'JoinRequestApproved',
+    'JoinRequestRejected',
+    'SpaceArchived',
+    'SpaceUnarchived',
+    'ChannelArchived',
+    'ChannelUnarchived',
+    'ChannelMemberKicked',
+    'ChannelsMerged'
+    );
+
+-- The channel and target are not foreign keys, the log outlives them.
+CREATE TABLE audit_logs
+(
+    "id"         uuid         NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
+    "space_id"   uuid         NOT NULL
+        CONSTRAINT "audit_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "actor_id"   uuid                  DEFAULT NULL
+        CONSTRAINT "audit_actor" REFERENCES users (id) ON DELETE SET NULL,
+    "action"     audit_action NOT NULL,
+    "channel_id" uuid                  DEFAULT NULL,
+    "target_id"  uuid                  DEFAULT NULL,
+    "payload"    jsonb        NOT NULL DEFAULT '{}',
+    "created"    timestamp    NOT NULL DEFAULT (now() at time zone 'utc')
+);
+
+CREATE INDEX "audit_logs_space_created" ON audit_logs (space_id, created DESC);
+
+CREATE TYPE join_request_status AS ENUM (
+    'Pending',
+    'Approved',
+    'Rejected'
+    );
+
+CREATE TABLE space_join_requests
+(
+    "id"         uuid                NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
+    "space_id"   uuid                NOT NULL
+        CONSTRAINT "join_request_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "user_id"    uuid                NOT NULL
+        CONSTRAINT "join_request_user" REFERENCES users (id) ON DELETE CASCADE,
+    "message"    text                NOT NULL DEFAULT '',
+    "status"     join_request_status NOT NULL DEFAULT 'Pending',
+    "decided_by" uuid                         DEFAULT NULL
+        CONSTRAINT "join_request_decider" REFERENCES users (id) ON DELETE SET NULL,
+    "created"    timestamp           NOT NULL DEFAULT (now() at time zone 'utc'),
+    "decided"    timestamp                    DEFAULT NULL
+);
+
+CREATE UNIQUE INDEX "unique_pending_join_request" ON space_join_requests (space_id, user_id) WHERE status = 'Pending';
+
+CREATE TABLE channel_categories
+(
+    "id"       uuid      NOT NULL DEFAULT uuid_generate_v1mc() PRIMARY KEY,
+    "space_id" uuid      NOT NULL
+        CONSTRAINT "category_space" REFERENCES spaces (id) ON DELETE CASCADE,
+    "name"     text      NOT NULL,
+    "position" integer   NOT NULL DEFAULT 0,
+    "created"  timestamp NOT NULL DEFAULT (now() at time zone 'utc')
+);
+
+CREATE INDEX "channel_categories_space" ON channel_categories (space_id);
+
+ALTER TABLE channels
+    ADD CONSTRAINT "channel_category" FOREIGN KEY (category_id) REFERENCES channel_categories (id) ON DELETE SET NULL;
+
+CREATE TABLE channel_read_markers
+(
+    "user_id"       uuid      NOT NULL
+        CONSTRAINT "read_marker_user" REFERENCES users (id) ON DELETE CASCADE,
+    "channel_id"    uuid      NOT NULL
+        CONSTRAINT "read_marker_channel" REFERENCES channels (id) ON DELETE CASCADE,
+    "pos"           float     NOT NULL DEFAULT 0.0,
+    -- Counters maintained on new messages, recounted when the marker advances.
+    "unread_count"  integer   NOT NULL DEFAULT 0,
+    "mention_count" integer   NOT NULL DEFAULT 0,
+    "updated"       timestamp NOT NULL DEFAULT (now() at time zone 'utc'),
+    PRIMARY KEY (user_id, channel_id)
+);
+
+CREATE INDEX "read_markers_channel" ON channel_read_markers (channel_id);
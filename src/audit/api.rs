@@ -0,0 +1,21 @@
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::AuditAction;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BySpace {
+    pub space_id: Uuid,
+    pub action: Option<AuditAction>,
+    pub actor_id: Option<Uuid>,
+    pub channel_id: Option<Uuid>,
+    pub target_id: Option<Uuid>,
+    /// The `created` of the last entry of the previous page.
+    #[serde(default, with = "crate::date_format::option")]
+    pub before: Option<NaiveDateTime>,
+    /// The `id` of the last entry of the previous page, to page through entries created at the same time.
+    pub before_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
@@ -0,0 +1,49 @@
+use super::api::BySpace;
+use super::AuditLog;
+use crate::csrf::authenticate;
+use crate::database;
+use crate::error::AppError;
+use crate::interface::{missing, ok_response, parse_query, Response};
+use crate::spaces::permissions::space_permission;
+use crate::spaces::Permissions;
+use hyper::{Body, Request};
+
+async fn by_space(req: Request<Body>) -> Result<Vec<AuditLog>, AppError> {
+    let session = authenticate(&req).await?;
+    let BySpace {
+        space_id,
+        action,
+        actor_id,
+        channel_id,
+        target_id,
+        before,
+        before_id,
+        limit,
+    } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    space_permission(db, &session.user_id, &space_id, Permissions::VIEW_AUDIT_LOG).await?;
+    let limit = limit.unwrap_or(64).clamp(1, 256);
+    AuditLog::get_by_space(
+        db,
+        &space_id,
+        action,
+        actor_id.as_ref(),
+        channel_id.as_ref(),
+        target_id.as_ref(),
+        before,
+        before_id.as_ref(),
+        limit,
+    )
+    .await
+    .map_err(Into::into)
+}
+
+pub async fn router(req: Request<Body>, path: &str) -> Result<Response, AppError> {
+    use hyper::Method;
+
+    match (path, req.method().clone()) {
+        ("/by_space", Method::GET) => by_space(req).await.map(ok_response),
+        _ => missing(),
+    }
+}
@@ -0,0 +1,164 @@
+use chrono::NaiveDateTime;
+use postgres_types::{FromSql, ToSql};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use uuid::Uuid;
+
+use crate::database::Querist;
+use crate::error::DbError;
+
+#[derive(Debug, Serialize, Deserialize, FromSql, ToSql, Clone, Copy, PartialEq, Eq)]
+#[postgres(name = "audit_action")]
+pub enum AuditAction {
+    SpaceEdited,
+    InviteTokenRefreshed,
+    InviteCreated,
+    InviteRevoked,
+    MemberKicked,
+    AdminGranted,
+    AdminRevoked,
+    OwnershipTransferred,
+    RoleCreated,
+    RoleEdited,
+    RoleDeleted,
+    RoleAssigned,
+    RoleUnassigned,
+    ChannelCreated,
+    ChannelEdited,
+    ChannelDeleted,
+    MasterGranted,
+    MasterRevoked,
+    RoleOverrideSet,
+    RoleOverrideRemoved,
+    /// A message deleted by someone other than its sender.
+    MessageDeleted,
+    /// A message folded by someone other than its sender.
+    MessageFolded,
+    JoinRequestApproved,
+    JoinRequestRejected,
+    SpaceArchived,
+    SpaceUnarchived,
+    ChannelArchived,
+    ChannelUnarchived,
+    ChannelMemberKicked,
+    ChannelsMerged,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromSql, Clone)]
+#[serde(rename_all = "camelCase")]
+#[postgres(name = "audit_logs")]
+pub struct AuditLog {
+    pub id: Uuid,
+    pub space_id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub action: AuditAction,
+    pub channel_id: Option<Uuid>,
+    /// The user, role, invite or message acted on, depending on the action.
+    pub target_id: Option<Uuid>,
+    pub payload: JsonValue,
+    #[serde(with = "crate::date_format")]
+    pub created: NaiveDateTime,
+}
+
+impl AuditLog {
+    pub async fn record<T: Querist>(
+        db: &mut T,
+        space_id: &Uuid,
+        actor_id: &Uuid,
+        action: AuditAction,
+        channel_id: Option<&Uuid>,
+        target_id: Option<&Uuid>,
+        payload: JsonValue,
+    ) -> Result<AuditLog, DbError> {
+        let row = db
+            .query_exactly_one(
+                include_str!("sql/create.sql"),
+                &[space_id, actor_id, &action, &channel_id, &target_id, &payload],
+            )
+            .await?;
+        row.try_get(0)
+    }
+
+    pub async fn get_by_space<T: Querist>(
+        db: &mut T,
+        space_id: &Uuid,
+        action: Option<AuditAction>,
+        actor_id: Option<&Uuid>,
+        channel_id: Option<&Uuid>,
+        target_id: Option<&Uuid>,
+        before: Option<NaiveDateTime>,
+        before_id: Option<&Uuid>,
+        limit: i64,
+    ) -> Result<Vec<AuditLog>, DbError> {
+        let rows = db
+            .query(
+                include_str!("sql/by_space.sql"),
+                &[
+                    space_id,
+                    &action,
+                    &actor_id,
+                    &channel_id,
+                    &target_id,
+                    &before,
+                    &before_id,
+                    &limit,
+                ],
+            )
+            .await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+}
+
+/// The fields changed between two serialized states, as `{ field: { before, after } }`.
+pub fn diff<T: Serialize>(before: &T, after: &T) -> JsonValue {
+    let before = serde_json::to_value(before).unwrap_or(JsonValue::Null);
+    let after = serde_json::to_value(after).unwrap_or(JsonValue::Null);
+    let mut changed = Map::new();
+    if let (JsonValue::Object(before), JsonValue::Object(mut after)) = (before, after) {
+        for (key, old) in before.into_iter() {
+            let new = after.remove(&key).unwrap_or(JsonValue::Null);
+            if old != new {
+                changed.insert(key, serde_json::json!({ "before": old, "after": new }));
+            }
+        }
+    }
+    JsonValue::Object(changed)
+}
+
+#[test]
+fn diff_test() {
+    use serde_json::json;
+    let before = json!({ "name": "Mythal", "topic": "", "isPublic": true });
+    let after = json!({ "name": "Pure Illusion", "topic": "", "isPublic": false });
+    let changed = diff(&before, &after);
+    assert_eq!(
+        changed,
+        json!({
+            "name": { "before": "Mythal", "after": "Pure Illusion" },
+            "isPublic": { "before": true, "after": false },
+        })
+    );
+    assert_eq!(diff(&before, &before), json!({}));
+}
+
+#[tokio::test]
+async fn audit_log_test() -> Result<(), crate::error::AppError> {
+    use crate::database::Client;
+    use crate::spaces::Space;
+    use crate::users::User;
+    let mut client = Client::new().await?;
+    let mut trans = client.transaction().await?;
+    let db = &mut trans;
+    let user = User::register(db, "test-audit@mythal.net", "audit_test_user", "Audit", "no password")
+        .await
+        .unwrap();
+    let space = Space::create(db, "Audit".to_string(), &user.id, String::new(), None, None).await?;
+    let payload = serde_json::json!({});
+    let log = AuditLog::record(db, &space.id, &user.id, AuditAction::SpaceEdited, None, None, payload).await?;
+    let logs = AuditLog::get_by_space(db, &space.id, None, None, None, None, None, None, 10).await?;
+    assert_eq!(logs[0].id, log.id);
+    let action = Some(AuditAction::ChannelDeleted);
+    let logs = AuditLog::get_by_space(db, &space.id, action, None, None, None, None, None, 10).await?;
+    assert!(logs.is_empty());
+    Ok(())
+}
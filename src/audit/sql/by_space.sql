@@ -0,0 +1,10 @@
+SELECT audit_logs
+FROM audit_logs
+WHERE space_id = $1
+  AND ($2::audit_action IS NULL OR action = $2)
+  AND ($3::uuid IS NULL OR actor_id = $3)
+  AND ($4::uuid IS NULL OR channel_id = $4)
+  AND ($5::uuid IS NULL OR target_id = $5)
+  AND ($6::timestamp IS NULL OR created < $6 OR (created = $6 AND id < $7))
+ORDER BY created DESC, id DESC
+LIMIT $8;
@@ -0,0 +1,3 @@
+INSERT INTO audit_logs (space_id, actor_id, action, channel_id, target_id, payload)
+VALUES ($1, $2, $3, $4, $5, $6)
+RETURNING audit_logs;
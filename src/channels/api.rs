@@ -1,6 +1,7 @@
-use super::models::{Channel, ChannelMember};
+use super::models::{Channel, ChannelCharacter, ChannelMember, ReadMarker};
 use crate::channels::models::Member;
-use crate::spaces::Space;
+use crate::messages::{Message, SystemEvent};
+use crate::spaces::{Permissions, Space};
 use crate::users::User;
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
@@ -37,6 +38,9 @@ pub struct Edit {
     pub remove_masters: Vec<Uuid>,
     pub is_public: Option<bool>,
     pub is_document: Option<bool>,
+    pub system_events: Option<Vec<SystemEvent>>,
+    pub slow_mode_seconds: Option<i32>,
+    pub preview_rate_limit: Option<i32>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -63,6 +67,9 @@ pub struct ChannelWithRelated {
     pub color_list: HashMap<Uuid, String>,
     pub heartbeat_map: HashMap<Uuid, i64>,
     pub encoded_events: Vec<String>,
+    pub read_marker: Option<ReadMarker>,
+    pub characters: Vec<ChannelCharacter>,
+    pub pinned: Vec<Message>,
 }
 
 #[derive(Serialize, Debug)]
@@ -70,6 +77,7 @@ pub struct ChannelWithRelated {
 pub struct ChannelWithMember {
     pub channel: Channel,
     pub member: ChannelMember,
+    pub read_marker: Option<ReadMarker>,
 }
 
 #[derive(Serialize, Debug)]
@@ -96,6 +104,57 @@ pub struct AddMember {
     pub character_name: String,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCategory {
+    pub space_id: Uuid,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EditCategory {
+    pub category_id: Uuid,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderCategories {
+    pub space_id: Uuid,
+    pub category_ids: Vec<Uuid>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelPosition {
+    pub channel_id: Uuid,
+    #[serde(default)]
+    pub category_id: Option<Uuid>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderChannels {
+    pub space_id: Uuid,
+    /// Channels in their new order, each with the category it now belongs to.
+    pub channels: Vec<ChannelPosition>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkRead {
+    pub channel_id: Uuid,
+    pub pos: f64,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct KickFromChannel {
+    pub channel_id: Uuid,
+    pub user_id: Uuid,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Export {
@@ -103,4 +162,77 @@ pub struct Export {
     #[serde(with = "crate::date_format::option")]
     #[serde(default)]
     pub after: Option<NaiveDateTime>,
+    pub tag: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SetRoleOverride {
+    pub channel_id: Uuid,
+    pub role_id: Uuid,
+    #[serde(default)]
+    pub allow: Permissions,
+    #[serde(default)]
+    pub deny: Permissions,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveRoleOverride {
+    pub channel_id: Uuid,
+    pub role_id: Uuid,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCharacter {
+    pub channel_id: Uuid,
+    pub name: String,
+    pub text_color: Option<String>,
+    pub avatar_id: Option<Uuid>,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EditCharacter {
+    pub character_id: Uuid,
+    pub name: Option<String>,
+    pub text_color: Option<String>,
+    pub avatar_id: Option<Uuid>,
+    pub description: Option<String>,
+}
+
+/// Exported messages of a character, or of a sender speaking without one under `name`.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CharacterLog {
+    pub sender_id: Uuid,
+    pub character: Option<ChannelCharacter>,
+    pub name: String,
+    pub messages: Vec<Message>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitChannel {
+    pub channel_id: Uuid,
+    /// Messages at and after the position go to the new channel.
+    pub from_pos: f64,
+    pub name: String,
+    /// Move the messages instead of copying them.
+    #[serde(default)]
+    pub move_messages: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeChannels {
+    /// The channel merged into the target and archived afterwards.
+    pub source_id: Uuid,
+    pub target_id: Uuid,
+    /// Interleave by `order_date` instead of `created`.
+    #[serde(default)]
+    pub by_order_date: bool,
 }
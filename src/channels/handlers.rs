@@ -1,9 +1,12 @@
 use super::api::{Create, Edit};
-use super::models::ChannelMember;
+use super::models::{ChannelCategory, ChannelCharacter, ChannelMember, ChannelRoleOverride, ReadMarker};
 use super::Channel;
+use crate::audit::{self, AuditAction, AuditLog};
 use crate::channels::api::{
-    AddMember, ChannelMemberWithUser, ChannelWithMember, ChannelWithRelated, CheckChannelName, EditMember, Export,
-    JoinChannel,
+    AddMember, ChannelMemberWithUser, ChannelPosition, ChannelWithMember, ChannelWithRelated, CharacterLog,
+    CheckChannelName, CreateCategory, CreateCharacter, EditCategory, EditCharacter, EditMember, Export, JoinChannel,
+    KickFromChannel, MarkRead, MergeChannels, RemoveRoleOverride, ReorderCategories, ReorderChannels, SetRoleOverride,
+    SplitChannel,
 };
 use crate::channels::models::Member;
 use crate::csrf::authenticate;
@@ -11,24 +14,26 @@ use crate::database;
 use crate::database::Querist;
 use crate::error::{AppError, Find};
 use crate::events::context::get_heartbeat_map;
-use crate::events::Event;
+use crate::events::{DbEvent, DbEventType, Event};
 use crate::interface::{self, missing, ok_response, parse_body, parse_query, IdQuery, Response};
-use crate::messages::Message;
-use crate::spaces::{Space, SpaceMember};
+use crate::messages::{check_pos, Message, SystemEvent};
+use crate::spaces::permissions::{channel_permission, space_permission};
+use crate::spaces::{Permissions, Space, SpaceMember, SpaceRole};
+use crate::stats::Stats;
 use hyper::{Body, Request};
 use std::collections::HashMap;
 use uuid::Uuid;
 
-async fn admin_only<T: Querist>(db: &mut T, user_id: &Uuid, space_id: &Uuid) -> Result<(), AppError> {
-    SpaceMember::get(db, user_id, space_id).await.or_no_permission()?;
-    Ok(())
-}
-
 async fn query(req: Request<Body>) -> Result<Channel, AppError> {
     let query: IdQuery = parse_query(req.uri())?;
+    let session = authenticate(&req).await.ok();
 
     let mut db = database::get().await?;
-    Channel::get_by_id(&mut *db, &query.id).await.or_not_found()
+    let db = &mut *db;
+    let channel = Channel::get_by_id(db, &query.id).await.or_not_found()?;
+    let user_id = session.as_ref().map(|session| &session.user_id);
+    channel.ensure_readable(db, user_id).await?;
+    Ok(channel)
 }
 
 async fn query_with_related(req: Request<Body>) -> Result<ChannelWithRelated, AppError> {
@@ -38,9 +43,11 @@ async fn query_with_related(req: Request<Body>) -> Result<ChannelWithRelated, Ap
     let mut conn = database::get().await?;
     let db = &mut *conn;
     let (mut channel, space) = Channel::get_with_space(db, &query.id).await.or_not_found()?;
+    let user_id = session.as_ref().map(|session| session.user_id);
+    channel.ensure_readable(db, user_id.as_ref()).await?;
     let members = Member::get_by_channel(db, channel.id).await?;
     let my_member: Option<&Member> =
-        session.and_then(|session| members.iter().find(|member| member.user.id == session.user_id));
+        user_id.and_then(|user_id| members.iter().find(|member| member.user.id == user_id));
 
     let color_list = ChannelMember::get_color_list(db, &channel.id).await?;
     let heartbeat_map = {
@@ -51,12 +58,18 @@ async fn query_with_related(req: Request<Body>) -> Result<ChannelWithRelated, Ap
         }
     };
 
-    let encoded_events = if channel.is_public || my_member.is_some() {
-        Event::get_from_cache(&query.id).await
+    let (encoded_events, pinned) = if channel.is_public || my_member.is_some() {
+        let pinned = Message::get_pinned(db, &channel.id, None, 64).await?;
+        (Event::get_from_cache(&query.id, user_id.as_ref()).await, pinned)
     } else {
         channel.topic = String::new();
-        Vec::new()
+        (Vec::new(), Vec::new())
+    };
+    let read_marker = match user_id {
+        Some(user_id) => ReadMarker::get(db, &user_id, &channel.id).await?,
+        None => None,
     };
+    let characters = ChannelCharacter::get_by_channel(db, &channel.id).await?;
 
     let with_related = ChannelWithRelated {
         channel,
@@ -65,6 +78,9 @@ async fn query_with_related(req: Request<Body>) -> Result<ChannelWithRelated, Ap
         color_list,
         heartbeat_map,
         encoded_events,
+        read_marker,
+        characters,
+        pinned,
     };
     Ok(with_related)
 }
@@ -85,14 +101,28 @@ async fn create(req: Request<Body>) -> Result<ChannelWithMember, AppError> {
     Space::get_by_id(db, &space_id)
         .await?
         .ok_or_else(|| AppError::BadRequest("The space not found".to_string()))?;
-    admin_only(db, &session.user_id, &space_id).await?;
+    space_permission(db, &session.user_id, &space_id, Permissions::CREATE_CHANNELS).await?;
+    Space::ensure_active(db, &space_id).await?;
 
     let channel = Channel::create(db, &space_id, &*name, is_public, default_dice_type.as_deref()).await?;
     let channel_member = ChannelMember::add_user(db, &session.user_id, &channel.id, &*character_name, true).await?;
+    let payload = serde_json::json!({ "name": channel.name, "isPublic": channel.is_public });
+    let action = AuditAction::ChannelCreated;
+    AuditLog::record(
+        db,
+        &space_id,
+        &session.user_id,
+        action,
+        Some(&channel.id),
+        None,
+        payload,
+    )
+    .await?;
     trans.commit().await?;
     let joined = ChannelWithMember {
         channel,
         member: channel_member,
+        read_marker: None,
     };
     Event::space_updated(space_id);
     Ok(joined)
@@ -110,17 +140,20 @@ async fn edit(req: Request<Body>) -> Result<Channel, AppError> {
         remove_masters,
         is_public,
         is_document,
+        system_events,
+        slow_mode_seconds,
+        preview_rate_limit,
     } = interface::parse_body(req).await?;
 
     let mut conn = database::get().await?;
     let mut trans = conn.transaction().await?;
     let db = &mut trans;
 
-    let space_member = SpaceMember::get_by_channel(db, &session.user_id, &channel_id)
-        .await
-        .or_no_permission()?;
-    if !space_member.is_admin {
-        return Err(AppError::NoPermission(format!("user is not admin")));
+    channel_permission(db, &session.user_id, &channel_id, Permissions::MANAGE_CHANNELS).await?;
+    let before = Channel::get_by_id(db, &channel_id).await.or_not_found()?;
+    let push_members = !(grant_masters.is_empty() && remove_masters.is_empty());
+    if push_members {
+        Channel::ensure_active(db, &channel_id).await?;
     }
     let channel = Channel::edit(
         db,
@@ -131,19 +164,66 @@ async fn edit(req: Request<Body>) -> Result<Channel, AppError> {
         default_roll_command.as_deref(),
         is_public,
         is_document,
+        system_events.as_deref(),
+        slow_mode_seconds,
+        preview_rate_limit,
     )
     .await?;
-    let push_members = !(grant_masters.is_empty() && remove_masters.is_empty());
-    for user_id in grant_masters {
-        ChannelMember::set_master(db, &user_id, &channel_id, true).await.ok();
+    let changed = audit::diff(&before, &channel);
+    if changed.as_object().map_or(false, |changed| !changed.is_empty()) {
+        let action = AuditAction::ChannelEdited;
+        let space_id = &channel.space_id;
+        AuditLog::record(db, space_id, &session.user_id, action, Some(&channel_id), None, changed).await?;
     }
-    for user_id in remove_masters {
-        ChannelMember::set_master(db, &user_id, &channel_id, false).await.ok();
+    let masters = grant_masters
+        .into_iter()
+        .map(|user_id| (user_id, true))
+        .chain(remove_masters.into_iter().map(|user_id| (user_id, false)));
+    let mut notifications = Vec::new();
+    let mut system_messages = Vec::new();
+    let mut cache = crate::cache::conn().await;
+    for (user_id, is_master) in masters {
+        if let Ok(Some(member)) = ChannelMember::set_master(db, &user_id, &channel_id, is_master).await {
+            if is_master {
+                let event = SystemEvent::BecameMaster;
+                let name = &*member.character_name;
+                let text = event.describe();
+                system_messages
+                    .extend(Message::create_system(db, &mut cache, &channel, &user_id, name, event, text).await?);
+            }
+            if is_master && user_id != session.user_id {
+                let kind = DbEventType::NewMaster;
+                let space_id = Some(&channel.space_id);
+                let payload = serde_json::json!({ "channelName": channel.name, "grantedBy": session.user_id });
+                notifications.push(DbEvent::create(db, kind, &user_id, space_id, Some(&channel_id), payload).await?);
+            }
+            let action = if is_master {
+                AuditAction::MasterGranted
+            } else {
+                AuditAction::MasterRevoked
+            };
+            let space_id = &channel.space_id;
+            let payload = serde_json::json!({});
+            AuditLog::record(
+                db,
+                space_id,
+                &session.user_id,
+                action,
+                Some(&channel_id),
+                Some(&user_id),
+                payload,
+            )
+            .await?;
+        }
     }
     trans.commit().await?;
     if push_members {
         Event::push_members(channel_id);
     }
+    for message in system_messages {
+        Event::new_message(channel.space_id, message);
+    }
+    notifications.into_iter().for_each(Event::notify);
     Event::channel_edited(channel.clone());
     Event::space_updated(channel.space_id);
     Ok(channel)
@@ -168,10 +248,40 @@ async fn add_member(req: Request<Body>) -> Result<ChannelWithMember, AppError> {
     SpaceMember::get(db, &session.user_id, &channel.space_id)
         .await
         .or_no_permission()?;
+    if !channel.is_public {
+        channel_permission(db, &session.user_id, &channel_id, Permissions::MASTER).await?;
+    }
+    SpaceMember::get(db, &user_id, &channel.space_id)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("The user is not a member of the space".to_string()))?;
+    Channel::ensure_active(db, &channel_id).await?;
     let member = ChannelMember::add_user(db, &user_id, &channel_id, &*character_name, false).await?;
+    let mut cache = crate::cache::conn().await;
+    let event = SystemEvent::Joined;
+    let name = &*member.character_name;
+    let system_message =
+        Message::create_system(db, &mut cache, &channel, &user_id, name, event, event.describe()).await?;
+    let notification = if user_id != session.user_id {
+        let kind = DbEventType::Invited;
+        let space_id = Some(&channel.space_id);
+        let payload = serde_json::json!({ "channelName": channel.name, "invitedBy": session.user_id });
+        Some(DbEvent::create(db, kind, &user_id, space_id, Some(&channel_id), payload).await?)
+    } else {
+        None
+    };
     trans.commit().await?;
     Event::push_members(channel_id);
-    Ok(ChannelWithMember { channel, member })
+    if let Some(message) = system_message {
+        Event::new_message(channel.space_id, message);
+    }
+    if let Some(notification) = notification {
+        Event::notify(notification);
+    }
+    Ok(ChannelWithMember {
+        channel,
+        member,
+        read_marker: None,
+    })
 }
 
 async fn edit_member(req: Request<Body>) -> Result<ChannelMember, AppError> {
@@ -186,24 +296,42 @@ async fn edit_member(req: Request<Body>) -> Result<ChannelMember, AppError> {
     let mut trans = conn.transaction().await?;
     let db = &mut trans;
 
-    ChannelMember::get(db, &session.user_id, &channel_id)
+    let before = ChannelMember::get(db, &session.user_id, &channel_id)
         .await
         .or_no_permission()?;
+    Channel::ensure_active(db, &channel_id).await?;
 
     let character_name = character_name.as_deref();
     let text_color = text_color.as_deref();
     let channel_member = ChannelMember::edit(db, session.user_id, channel_id, character_name, text_color)
         .await?
-        .or_not_found();
+        .or_not_found()?;
+    let mut system_message = None;
+    if channel_member.character_name != before.character_name {
+        let channel = Channel::get_by_id(db, &channel_id).await.or_not_found()?;
+        let mut cache = crate::cache::conn().await;
+        let event = SystemEvent::Renamed;
+        let name = &*before.character_name;
+        let text = format!("{} to {}", event.describe(), channel_member.character_name);
+        let message = Message::create_system(db, &mut cache, &channel, &session.user_id, name, event, &*text).await?;
+        system_message = message.map(|message| (channel.space_id, message));
+    }
     trans.commit().await?;
     Event::push_members(channel_id);
-    channel_member
+    if let Some((space_id, message)) = system_message {
+        Event::new_message(space_id, message);
+    }
+    Ok(channel_member)
 }
 
 async fn all_members(req: Request<Body>) -> Result<Vec<ChannelMemberWithUser>, AppError> {
     let IdQuery { id } = parse_query(req.uri())?;
+    let session = authenticate(&req).await.ok();
     let mut db = database::get().await?;
     let db = &mut *db;
+    let channel = Channel::get_by_id(db, &id).await.or_not_found()?;
+    let user_id = session.as_ref().map(|session| &session.user_id);
+    channel.ensure_readable(db, user_id).await?;
 
     ChannelMember::get_by_channel(db, &id, true).await.map_err(Into::into)
 }
@@ -225,18 +353,110 @@ async fn join(req: Request<Body>) -> Result<ChannelWithMember, AppError> {
     SpaceMember::get(db, &session.user_id, &channel.space_id)
         .await
         .or_no_permission()?;
+    Channel::ensure_active(db, &channel_id).await?;
     let member = ChannelMember::add_user(db, &session.user_id, &channel.id, &*character_name, false).await?;
+    let mut cache = crate::cache::conn().await;
+    let event = SystemEvent::Joined;
+    let name = &*member.character_name;
+    let text = event.describe();
+    let system_message = Message::create_system(db, &mut cache, &channel, &session.user_id, name, event, text).await?;
+    trans.commit().await?;
+    Event::push_members(channel_id);
+    if let Some(message) = system_message {
+        Event::new_message(channel.space_id, message);
+    }
+    Ok(ChannelWithMember {
+        channel,
+        member,
+        read_marker: None,
+    })
+}
+
+async fn mark_read(req: Request<Body>) -> Result<Option<ReadMarker>, AppError> {
+    let session = authenticate(&req).await?;
+    let MarkRead { channel_id, pos } = interface::parse_body(req).await?;
+    check_pos(pos)?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    ChannelMember::get(db, &session.user_id, &channel_id)
+        .await
+        .or_no_permission()?;
+    match ReadMarker::advance(db, &session.user_id, &channel_id, pos).await? {
+        Some(marker) => {
+            Event::read_marker_updated(marker.clone());
+            Ok(Some(marker))
+        }
+        None => ReadMarker::get(db, &session.user_id, &channel_id)
+            .await
+            .map_err(Into::into),
+    }
+}
+
+async fn kick(req: Request<Body>) -> Result<bool, AppError> {
+    let session = authenticate(&req).await?;
+    let KickFromChannel { channel_id, user_id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let mut trans = conn.transaction().await?;
+    let db = &mut trans;
+    let channel = Channel::get_by_id(db, &channel_id).await.or_not_found()?;
+    let permissions = channel_permission(db, &session.user_id, &channel_id, Permissions::MASTER).await?;
+    let member = ChannelMember::get(db, &user_id, &channel_id).await.or_not_found()?;
+    if member.is_master && !permissions.contains(Permissions::MANAGE_CHANNELS) {
+        return Err(AppError::NoPermission(format!(
+            "Only channel managers can kick a master"
+        )));
+    }
+    Channel::ensure_active(db, &channel_id).await?;
+    ChannelMember::remove_user(db, &user_id, &channel_id).await?;
+    let mut cache = crate::cache::conn().await;
+    let event = SystemEvent::Left;
+    let name = &*member.character_name;
+    let system_message =
+        Message::create_system(db, &mut cache, &channel, &user_id, name, event, event.describe()).await?;
+    let payload = serde_json::json!({ "characterName": member.character_name });
+    let action = AuditAction::ChannelMemberKicked;
+    AuditLog::record(
+        db,
+        &channel.space_id,
+        &session.user_id,
+        action,
+        Some(&channel_id),
+        Some(&user_id),
+        payload,
+    )
+    .await?;
     trans.commit().await?;
     Event::push_members(channel_id);
-    Ok(ChannelWithMember { channel, member })
+    if let Some(message) = system_message {
+        Event::new_message(channel.space_id, message);
+    }
+    Event::removed_from_channel(user_id, channel.space_id, channel_id);
+    Ok(true)
 }
 
 async fn leave(req: Request<Body>) -> Result<bool, AppError> {
     let session = authenticate(&req).await?;
     let IdQuery { id } = parse_query(req.uri())?;
-    let mut db = database::get().await?;
-    ChannelMember::remove_user(&mut *db, &session.user_id, &id).await?;
+    let mut conn = database::get().await?;
+    let mut trans = conn.transaction().await?;
+    let db = &mut trans;
+    Channel::ensure_active(db, &id).await?;
+    let channel = Channel::get_by_id(db, &id).await.or_not_found()?;
+    let member = ChannelMember::get(db, &session.user_id, &id).await?;
+    ChannelMember::remove_user(db, &session.user_id, &id).await?;
+    let mut system_message = None;
+    if let Some(member) = member {
+        let mut cache = crate::cache::conn().await;
+        let event = SystemEvent::Left;
+        let name = &*member.character_name;
+        let text = event.describe();
+        system_message = Message::create_system(db, &mut cache, &channel, &session.user_id, name, event, text).await?;
+    }
+    trans.commit().await?;
     Event::push_members(id);
+    if let Some(message) = system_message {
+        Event::new_message(channel.space_id, message);
+    }
     Ok(true)
 }
 
@@ -249,40 +469,505 @@ async fn delete(req: Request<Body>) -> Result<bool, AppError> {
 
     let channel = Channel::get_by_id(db, &id).await.or_not_found()?;
 
-    admin_only(db, &session.user_id, &channel.space_id).await?;
+    channel_permission(db, &session.user_id, &id, Permissions::MANAGE_CHANNELS).await?;
 
     Channel::delete(db, &id).await?;
+    let payload = serde_json::json!({ "name": channel.name });
+    let action = AuditAction::ChannelDeleted;
+    AuditLog::record(
+        db,
+        &channel.space_id,
+        &session.user_id,
+        action,
+        Some(&id),
+        None,
+        payload,
+    )
+    .await?;
     log::info!("channel {} was deleted.", &id);
     Event::channel_deleted(channel.space_id, id);
     Event::space_updated(channel.space_id);
     Ok(true)
 }
 
+async fn archive(req: Request<Body>, archived: bool) -> Result<Channel, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    channel_permission(db, &session.user_id, &id, Permissions::MANAGE_CHANNELS).await?;
+    let channel = Channel::set_archived(db, &id, archived).await?.or_not_found()?;
+    let action = if archived {
+        AuditAction::ChannelArchived
+    } else {
+        AuditAction::ChannelUnarchived
+    };
+    let payload = serde_json::json!({ "name": channel.name });
+    AuditLog::record(
+        db,
+        &channel.space_id,
+        &session.user_id,
+        action,
+        Some(&id),
+        None,
+        payload,
+    )
+    .await?;
+    Event::channel_edited(channel.clone());
+    Event::space_updated(channel.space_id);
+    Ok(channel)
+}
+
 async fn by_space(req: Request<Body>) -> Result<Vec<Channel>, AppError> {
     let IdQuery { id } = parse_query(req.uri())?;
+    let session = authenticate(&req).await.ok();
     let mut conn = database::get().await?;
     let db = &mut *conn;
-    Channel::get_by_space(db, &id).await.map_err(Into::into)
+    let user_id = session.as_ref().map(|session| &session.user_id);
+    Channel::get_visible_by_space(db, &id, user_id)
+        .await
+        .map_err(Into::into)
 }
 
-async fn export(req: Request<Body>) -> Result<Vec<Message>, AppError> {
-    let Export { channel_id, after } = parse_query(req.uri())?;
+async fn categories(req: Request<Body>) -> Result<Vec<ChannelCategory>, AppError> {
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    ChannelCategory::get_by_space(&mut *conn, &id).await.map_err(Into::into)
+}
+
+async fn create_category(req: Request<Body>) -> Result<ChannelCategory, AppError> {
+    let session = authenticate(&req).await?;
+    let CreateCategory { space_id, name } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    space_permission(db, &session.user_id, &space_id, Permissions::MANAGE_CHANNELS).await?;
+    let category = ChannelCategory::create(db, &space_id, &*name).await?;
+    Event::space_updated(space_id);
+    Ok(category)
+}
+
+async fn edit_category(req: Request<Body>) -> Result<ChannelCategory, AppError> {
+    let session = authenticate(&req).await?;
+    let EditCategory { category_id, name } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let category = ChannelCategory::get(db, &category_id).await.or_not_found()?;
+    space_permission(db, &session.user_id, &category.space_id, Permissions::MANAGE_CHANNELS).await?;
+    let category = ChannelCategory::edit(db, &category_id, &*name).await?.or_not_found()?;
+    Event::space_updated(category.space_id);
+    Ok(category)
+}
+
+async fn delete_category(req: Request<Body>) -> Result<bool, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let category = ChannelCategory::get(db, &id).await.or_not_found()?;
+    space_permission(db, &session.user_id, &category.space_id, Permissions::MANAGE_CHANNELS).await?;
+    ChannelCategory::delete(db, &id).await?;
+    Event::space_updated(category.space_id);
+    Ok(true)
+}
+
+async fn reorder_categories(req: Request<Body>) -> Result<Vec<ChannelCategory>, AppError> {
+    let session = authenticate(&req).await?;
+    let ReorderCategories { space_id, category_ids } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    space_permission(db, &session.user_id, &space_id, Permissions::MANAGE_CHANNELS).await?;
+    ChannelCategory::reorder(db, &space_id, &*category_ids).await?;
+    let categories = ChannelCategory::get_by_space(db, &space_id).await?;
+    Event::space_updated(space_id);
+    Ok(categories)
+}
+
+async fn reorder_channels(req: Request<Body>) -> Result<Vec<Channel>, AppError> {
     let session = authenticate(&req).await?;
+    let ReorderChannels { space_id, channels } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let mut trans = conn.transaction().await?;
+    let db = &mut trans;
+    space_permission(db, &session.user_id, &space_id, Permissions::MANAGE_CHANNELS).await?;
+    let categories = ChannelCategory::get_by_space(db, &space_id).await?;
+    let mut positions = Vec::with_capacity(channels.len());
+    for ChannelPosition {
+        channel_id,
+        category_id,
+    } in channels
+    {
+        if let Some(category_id) = category_id {
+            if !categories.iter().any(|category| category.id == category_id) {
+                return Err(AppError::BadRequest(format!(
+                    "Category {} is not in the space",
+                    category_id
+                )));
+            }
+        }
+        positions.push((channel_id, category_id));
+    }
+    Channel::reorder(db, &space_id, &*positions).await?;
+    let channels = Channel::get_visible_by_space(db, &space_id, Some(&session.user_id)).await?;
+    trans.commit().await?;
+    Event::space_updated(space_id);
+    Ok(channels)
+}
+
+async fn export(req: Request<Body>) -> Result<Vec<Message>, AppError> {
+    exported_messages(&req).await
+}
+
+/// Exported messages grouped by the character they were sent as, in order of first appearance.
+async fn export_by_character(req: Request<Body>) -> Result<Vec<CharacterLog>, AppError> {
+    let Export { channel_id, .. } = parse_query(req.uri())?;
+    let messages = exported_messages(&req).await?;
+    let mut conn = database::get().await?;
+    let characters = ChannelCharacter::get_by_channel(&mut *conn, &channel_id).await?;
+    let mut logs: Vec<CharacterLog> = Vec::new();
+    for message in messages {
+        if message.system_event.is_some() {
+            continue;
+        }
+        let character = message
+            .character_id
+            .and_then(|id| characters.iter().find(|character| character.id == id));
+        let log = logs.iter_mut().find(|log| match (&log.character, character) {
+            (Some(a), Some(b)) => a.id == b.id,
+            (None, None) => log.sender_id == message.sender_id && log.name == message.name,
+            _ => false,
+        });
+        match log {
+            Some(log) => log.messages.push(message),
+            None => logs.push(CharacterLog {
+                sender_id: message.sender_id,
+                character: character.cloned(),
+                name: message.name.clone(),
+                messages: vec![message],
+            }),
+        }
+    }
+    Ok(logs)
+}
+
+async fn exported_messages(req: &Request<Body>) -> Result<Vec<Message>, AppError> {
+    let Export { channel_id, after, tag } = parse_query(req.uri())?;
+    let session = authenticate(req).await?;
     let mut conn = database::get().await?;
     let mut trans = conn.transaction().await?;
     let db = &mut trans;
 
     let channel = Channel::get_by_id(db, &channel_id).await?.or_not_found()?;
 
-    let space_member = SpaceMember::get(db, &session.user_id, &channel.space_id)
-        .await
-        .or_no_permission()?;
+    let permissions = channel_permission(db, &session.user_id, &channel_id, Permissions::NONE).await?;
     let channel_member = ChannelMember::get(db, &session.user_id, &channel_id).await?;
-    if channel_member.is_none() && !space_member.is_admin {
+    if channel_member.is_none() && (!channel.is_public || !permissions.contains(Permissions::MANAGE_CHANNELS)) {
         return Err(AppError::NoPermission(format!("user is not channel member")));
     }
     let hide = channel_member.map_or(true, |member| !member.is_master);
-    Message::export(db, &channel.id, hide, after).await.map_err(Into::into)
+    let tag = tag.as_deref().map(|tag| tag.trim_start_matches('#'));
+    Message::export(db, &channel.id, hide, after, tag)
+        .await
+        .map_err(Into::into)
+}
+
+/// Branches a scene: a new channel with the members of the channel and its messages from a position onward.
+async fn split(req: Request<Body>) -> Result<ChannelWithMember, AppError> {
+    let session = authenticate(&req).await?;
+    let SplitChannel {
+        channel_id,
+        from_pos,
+        name,
+        move_messages,
+    } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let mut trans = conn.transaction().await?;
+    let db = &mut trans;
+    let source = Channel::get_by_id(db, &channel_id).await.or_not_found()?;
+    let space_id = source.space_id;
+    space_permission(db, &session.user_id, &space_id, Permissions::CREATE_CHANNELS).await?;
+    channel_permission(db, &session.user_id, &channel_id, Permissions::MASTER).await?;
+    if move_messages {
+        Channel::ensure_active(db, &channel_id).await?;
+    } else {
+        Space::ensure_active(db, &space_id).await?;
+    }
+    let default_dice_type = Some(&*source.default_dice_type);
+    let channel = Channel::create(db, &space_id, &*name, source.is_public, default_dice_type).await?;
+    ChannelMember::copy(db, &channel_id, &channel.id).await?;
+    let member = match ChannelMember::get(db, &session.user_id, &channel.id).await? {
+        Some(member) => member,
+        None => ChannelMember::add_user(db, &session.user_id, &channel.id, "", true).await?,
+    };
+    let messages = if move_messages {
+        Message::move_range(db, &channel_id, &channel.id, from_pos).await?
+    } else {
+        Message::copy_range(db, &channel_id, &channel.id, from_pos).await?
+    };
+    let payload = serde_json::json!({
+        "name": channel.name,
+        "isPublic": channel.is_public,
+        "splitFrom": channel_id,
+        "fromPos": from_pos,
+        "moved": move_messages,
+        "messages": messages.len(),
+    });
+    let action = AuditAction::ChannelCreated;
+    AuditLog::record(
+        db,
+        &space_id,
+        &session.user_id,
+        action,
+        Some(&channel.id),
+        None,
+        payload,
+    )
+    .await?;
+    trans.commit().await?;
+    let mut cache = crate::cache::conn().await;
+    crate::pos::reset_channel_pos(&mut cache, &channel_id).await?;
+    crate::pos::reset_channel_pos(&mut cache, &channel.id).await?;
+    Event::space_updated(space_id);
+    Event::channel_edited(channel.clone());
+    if move_messages {
+        Event::channel_edited(source);
+        Event::channel_resync(space_id, channel_id);
+    }
+    Event::channel_resync(space_id, channel.id);
+    Ok(ChannelWithMember {
+        channel,
+        member,
+        read_marker: None,
+    })
+}
+
+/// Regroups a scene: the history of the source is interleaved into the target and the source is archived.
+async fn merge(req: Request<Body>) -> Result<Channel, AppError> {
+    let session = authenticate(&req).await?;
+    let MergeChannels {
+        source_id,
+        target_id,
+        by_order_date,
+    } = interface::parse_body(req).await?;
+    if source_id == target_id {
+        return Err(AppError::BadRequest("Can't merge a channel into itself".to_string()));
+    }
+    let mut conn = database::get().await?;
+    let mut trans = conn.transaction().await?;
+    let db = &mut trans;
+    let source = Channel::get_by_id(db, &source_id).await.or_not_found()?;
+    let target = Channel::get_by_id(db, &target_id).await.or_not_found()?;
+    if source.space_id != target.space_id {
+        return Err(AppError::BadRequest(
+            "The channels are not in the same space".to_string(),
+        ));
+    }
+    let space_id = target.space_id;
+    space_permission(db, &session.user_id, &space_id, Permissions::MANAGE_CHANNELS).await?;
+    channel_permission(db, &session.user_id, &source_id, Permissions::MASTER).await?;
+    channel_permission(db, &session.user_id, &target_id, Permissions::MASTER).await?;
+    Channel::ensure_active(db, &source_id).await?;
+    Channel::ensure_active(db, &target_id).await?;
+    ChannelMember::copy(db, &source_id, &target_id).await?;
+    let merged = Message::merge(db, &source_id, &target_id, by_order_date).await?;
+    ReadMarker::recount_by_channel(db, &target_id).await?;
+    let source = Channel::set_archived(db, &source_id, true).await?.or_not_found()?;
+    let payload = serde_json::json!({ "sourceName": source.name, "messages": merged, "byOrderDate": by_order_date });
+    let action = AuditAction::ChannelsMerged;
+    AuditLog::record(
+        db,
+        &space_id,
+        &session.user_id,
+        action,
+        Some(&target_id),
+        Some(&source_id),
+        payload,
+    )
+    .await?;
+    trans.commit().await?;
+    let mut cache = crate::cache::conn().await;
+    crate::pos::reset_channel_pos(&mut cache, &source_id).await?;
+    crate::pos::reset_channel_pos(&mut cache, &target_id).await?;
+    Stats::invalidate(&mut cache, &space_id, &source_id).await?;
+    Stats::invalidate(&mut cache, &space_id, &target_id).await?;
+    Event::push_members(target_id);
+    Event::channel_edited(source);
+    Event::channel_resync(space_id, source_id);
+    Event::channel_resync(space_id, target_id);
+    Event::space_updated(space_id);
+    Ok(target)
+}
+
+async fn characters(req: Request<Body>) -> Result<Vec<ChannelCharacter>, AppError> {
+    let IdQuery { id } = parse_query(req.uri())?;
+    let session = authenticate(&req).await.ok();
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let channel = Channel::get_by_id(db, &id).await.or_not_found()?;
+    let user_id = session.as_ref().map(|session| &session.user_id);
+    channel.ensure_readable(db, user_id).await?;
+    ChannelCharacter::get_by_channel(db, &id).await.map_err(Into::into)
+}
+
+async fn create_character(req: Request<Body>) -> Result<ChannelCharacter, AppError> {
+    let session = authenticate(&req).await?;
+    let CreateCharacter {
+        channel_id,
+        name,
+        text_color,
+        avatar_id,
+        description,
+    } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    ChannelMember::get(db, &session.user_id, &channel_id)
+        .await
+        .or_no_permission()?;
+    Channel::ensure_active(db, &channel_id).await?;
+    let text_color = text_color.as_deref();
+    let character = ChannelCharacter::create(
+        db,
+        &channel_id,
+        &session.user_id,
+        &*name,
+        text_color,
+        avatar_id.as_ref(),
+        &*description,
+    )
+    .await?;
+    Ok(character)
+}
+
+/// Only the owner of a character is able to edit or delete it.
+async fn owned_character<T: Querist>(
+    db: &mut T,
+    user_id: &Uuid,
+    character_id: &Uuid,
+) -> Result<ChannelCharacter, AppError> {
+    let character = ChannelCharacter::get(db, character_id).await.or_not_found()?;
+    if character.user_id != *user_id {
+        return Err(AppError::NoPermission(format!("The character is not yours")));
+    }
+    Channel::ensure_active(db, &character.channel_id).await?;
+    Ok(character)
+}
+
+async fn edit_character(req: Request<Body>) -> Result<ChannelCharacter, AppError> {
+    let session = authenticate(&req).await?;
+    let EditCharacter {
+        character_id,
+        name,
+        text_color,
+        avatar_id,
+        description,
+    } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    owned_character(db, &session.user_id, &character_id).await?;
+    ChannelCharacter::edit(
+        db,
+        &character_id,
+        name.as_deref(),
+        text_color.as_deref(),
+        avatar_id.as_ref(),
+        description.as_deref(),
+    )
+    .await?
+    .or_not_found()
+}
+
+async fn delete_character(req: Request<Body>) -> Result<bool, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    owned_character(db, &session.user_id, &id).await?;
+    Ok(ChannelCharacter::delete(db, &id).await? > 0)
+}
+
+async fn role_overrides(req: Request<Body>) -> Result<Vec<ChannelRoleOverride>, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    SpaceMember::get_by_channel(db, &session.user_id, &id)
+        .await
+        .or_no_permission()?;
+    ChannelRoleOverride::get_by_channel(db, &id).await.map_err(Into::into)
+}
+
+async fn set_role_override(req: Request<Body>) -> Result<ChannelRoleOverride, AppError> {
+    let session = authenticate(&req).await?;
+    let SetRoleOverride {
+        channel_id,
+        role_id,
+        allow,
+        deny,
+    } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let channel = Channel::get_by_id(db, &channel_id).await.or_not_found()?;
+    let role = SpaceRole::get(db, &role_id).await.or_not_found()?;
+    if role.space_id != channel.space_id {
+        return Err(AppError::BadRequest(
+            "The role is not in the space of the channel".to_string(),
+        ));
+    }
+    let mine = space_permission(db, &session.user_id, &channel.space_id, Permissions::MANAGE_ROLES).await?;
+    if !mine.contains(allow) {
+        return Err(AppError::NoPermission(format!(
+            "Can't allow permissions beyond your own"
+        )));
+    }
+    let role_override = ChannelRoleOverride::set(db, &channel_id, &role_id, allow, deny).await?;
+    let payload = serde_json::json!({ "allow": allow, "deny": deny });
+    let action = AuditAction::RoleOverrideSet;
+    let space_id = &channel.space_id;
+    AuditLog::record(
+        db,
+        space_id,
+        &session.user_id,
+        action,
+        Some(&channel_id),
+        Some(&role_id),
+        payload,
+    )
+    .await?;
+    Event::space_updated(channel.space_id);
+    Ok(role_override)
+}
+
+async fn remove_role_override(req: Request<Body>) -> Result<bool, AppError> {
+    let session = authenticate(&req).await?;
+    let RemoveRoleOverride { channel_id, role_id } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let channel = Channel::get_by_id(db, &channel_id).await.or_not_found()?;
+    space_permission(db, &session.user_id, &channel.space_id, Permissions::MANAGE_ROLES).await?;
+    ChannelRoleOverride::remove(db, &channel_id, &role_id).await?;
+    let payload = serde_json::json!({});
+    let action = AuditAction::RoleOverrideRemoved;
+    let space_id = &channel.space_id;
+    AuditLog::record(
+        db,
+        space_id,
+        &session.user_id,
+        action,
+        Some(&channel_id),
+        Some(&role_id),
+        payload,
+    )
+    .await?;
+    Event::space_updated(channel.space_id);
+    Ok(true)
+}
+
+async fn my_permissions(req: Request<Body>) -> Result<Permissions, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    Permissions::channel(&mut *conn, &session.user_id, &id)
+        .await?
+        .or_no_permission()
 }
 
 async fn my_channels(req: Request<Body>) -> Result<Vec<ChannelWithMember>, AppError> {
@@ -314,9 +999,30 @@ pub async fn router(req: Request<Body>, path: &str) -> Result<Response, AppError
         ("/all_members", Method::GET) => all_members(req).await.map(ok_response),
         ("/join", Method::POST) => join(req).await.map(ok_response),
         ("/leave", Method::POST) => leave(req).await.map(ok_response),
+        ("/kick", Method::POST) => kick(req).await.map(ok_response),
+        ("/read", Method::POST) => mark_read(req).await.map(ok_response),
         ("/delete", Method::POST) => delete(req).await.map(ok_response),
+        ("/archive", Method::POST) => archive(req, true).await.map(ok_response),
+        ("/unarchive", Method::POST) => archive(req, false).await.map(ok_response),
         ("/check_name", Method::GET) => check_channel_name_exists(req).await.map(ok_response),
+        ("/categories", Method::GET) => categories(req).await.map(ok_response),
+        ("/create_category", Method::POST) => create_category(req).await.map(ok_response),
+        ("/edit_category", Method::POST) => edit_category(req).await.map(ok_response),
+        ("/delete_category", Method::POST) => delete_category(req).await.map(ok_response),
+        ("/reorder_categories", Method::POST) => reorder_categories(req).await.map(ok_response),
+        ("/reorder_channels", Method::POST) => reorder_channels(req).await.map(ok_response),
         ("/export", Method::GET) => export(req).await.map(ok_response),
+        ("/export_by_character", Method::GET) => export_by_character(req).await.map(ok_response),
+        ("/split", Method::POST) => split(req).await.map(ok_response),
+        ("/merge", Method::POST) => merge(req).await.map(ok_response),
+        ("/characters", Method::GET) => characters(req).await.map(ok_response),
+        ("/create_character", Method::POST) => create_character(req).await.map(ok_response),
+        ("/edit_character", Method::POST) => edit_character(req).await.map(ok_response),
+        ("/delete_character", Method::POST) => delete_character(req).await.map(ok_response),
+        ("/role_overrides", Method::GET) => role_overrides(req).await.map(ok_response),
+        ("/set_role_override", Method::POST) => set_role_override(req).await.map(ok_response),
+        ("/remove_role_override", Method::POST) => remove_role_override(req).await.map(ok_response),
+        ("/my_permissions", Method::GET) => my_permissions(req).await.map(ok_response),
         _ => missing(),
     }
 }
@@ -3,10 +3,12 @@ use postgres_types::FromSql;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::cache::{make_key, AsyncCommands};
 use crate::channels::api::{ChannelMemberWithUser, ChannelWithMember};
 use crate::database::Querist;
-use crate::error::{DbError, ModelError};
-use crate::spaces::{Space, SpaceMember};
+use crate::error::{AppError, DbError, Find, ModelError, ValidationFailed};
+use crate::messages::{Message, SystemEvent};
+use crate::spaces::{Permissions, Space, SpaceMember};
 use crate::users::User;
 use crate::utils::{inner_result_map, merge_blank};
 use std::collections::HashMap;
@@ -29,8 +31,21 @@ pub struct Channel {
     pub deleted: bool,
     pub default_dice_type: String,
     pub default_roll_command: String,
+    pub archived: bool,
+    pub category_id: Option<Uuid>,
+    /// Sort position of the channel in the space, ascending.
+    pub position: i32,
+    /// Kinds of system messages posted into the timeline of the channel.
+    pub system_events: Vec<SystemEvent>,
+    /// Minimum interval between messages of a non-master member, 0 to disable.
+    pub slow_mode_seconds: i32,
+    /// Maximum previews a member broadcasts per second, 0 for no limit.
+    pub preview_rate_limit: i32,
 }
 
+pub const MAX_SLOW_MODE_SECONDS: i32 = 60 * 60 * 6;
+pub const MAX_PREVIEW_RATE_LIMIT: i32 = 100;
+
 impl Channel {
     pub async fn create<T: Querist>(
         db: &mut T,
@@ -85,10 +100,132 @@ impl Channel {
         Ok(rows.into_iter().map(|row| row.get(0)).collect())
     }
 
+    /// Channels of the space except the secret ones the user is not a member of.
+    pub async fn get_visible_by_space<T: Querist>(
+        db: &mut T,
+        space_id: &Uuid,
+        user_id: Option<&Uuid>,
+    ) -> Result<Vec<Channel>, DbError> {
+        let rows = db
+            .query(include_str!("sql/get_visible_by_space.sql"), &[space_id, &user_id])
+            .await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+
+    /// Members of the channel if it is secret, `None` if everyone in the space can see it.
+    pub async fn audience<T: Querist>(db: &mut T, id: &Uuid) -> Result<Option<Vec<Uuid>>, DbError> {
+        let row = db.query_one(include_str!("sql/get_audience.sql"), &[id]).await?;
+        match row {
+            Some(row) => row.try_get(0),
+            None => Ok(None),
+        }
+    }
+
+    /// Secret channels are only readable by their members.
+    pub async fn ensure_readable<T: Querist>(&self, db: &mut T, user_id: Option<&Uuid>) -> Result<(), AppError> {
+        if self.is_public {
+            return Ok(());
+        }
+        let user_id = user_id.ok_or_else(|| AppError::NoPermission(format!("secret channel")))?;
+        ChannelMember::get(db, user_id, &self.id).await.or_no_permission()?;
+        Ok(())
+    }
+
     pub async fn delete<T: Querist>(db: &mut T, id: &Uuid) -> Result<u64, DbError> {
         db.execute(include_str!("sql/delete_channel.sql"), &[id]).await
     }
 
+    pub async fn set_archived<T: Querist>(db: &mut T, id: &Uuid, archived: bool) -> Result<Option<Channel>, DbError> {
+        let result = db
+            .query_one(include_str!("sql/set_archived.sql"), &[id, &archived])
+            .await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+
+    /// Rejects changes to a channel if it or its space is archived.
+    pub async fn ensure_active<T: Querist>(db: &mut T, id: &Uuid) -> Result<(), AppError> {
+        let row = db.query_one(include_str!("sql/is_archived.sql"), &[id]).await?;
+        match row.map(|row| (row.get(0), row.get(1))) {
+            Some((_, true)) => Err(AppError::Archived("space")),
+            Some((true, _)) => Err(AppError::Archived("channel")),
+            _ => Ok(()),
+        }
+    }
+
+    /// Enforces slow mode on a member, the first message starts the interval.
+    pub async fn check_slow_mode(&self, cache: &mut crate::cache::Connection, user_id: &Uuid) -> Result<(), AppError> {
+        if self.slow_mode_seconds <= 0 {
+            return Ok(());
+        }
+        let mut key = make_key(b"channel", &self.id, b"slow_mode:");
+        key.extend_from_slice(user_id.as_bytes());
+        let interval = self.slow_mode_seconds as u64 * 1000;
+        let started: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(1)
+            .arg("NX")
+            .arg("PX")
+            .arg(interval)
+            .query_async(&mut cache.inner)
+            .await?;
+        if started.is_none() {
+            let remaining: i64 = cache.inner.pttl(&key).await?;
+            return Err(AppError::RateLimited("slow mode", remaining.max(0) as u64));
+        }
+        Ok(())
+    }
+
+    /// Caps previews a member broadcasts per second.
+    pub async fn check_preview_rate(
+        &self,
+        cache: &mut crate::cache::Connection,
+        user_id: &Uuid,
+    ) -> Result<(), AppError> {
+        if self.preview_rate_limit <= 0 {
+            return Ok(());
+        }
+        let mut key = make_key(b"channel", &self.id, b"preview_rate:");
+        key.extend_from_slice(user_id.as_bytes());
+        let counter: i32 = cache.inner.incr(&key, 1).await?;
+        if counter == 1 {
+            cache.inner.pexpire::<_, ()>(&key, 1000).await?;
+        }
+        if counter > self.preview_rate_limit {
+            let remaining: i64 = cache.inner.pttl(&key).await?;
+            return Err(AppError::RateLimited("previews", remaining.max(0) as u64));
+        }
+        Ok(())
+    }
+
+    /// Positions follow the order of `channels`, channels not listed keep theirs.
+    pub async fn reorder<T: Querist>(
+        db: &mut T,
+        space_id: &Uuid,
+        channels: &[(Uuid, Option<Uuid>)],
+    ) -> Result<u64, DbError> {
+        let (channel_ids, category_ids): (Vec<Uuid>, Vec<Option<Uuid>>) = channels.iter().cloned().unzip();
+        db.execute(
+            include_str!("sql/reorder_channels.sql"),
+            &[space_id, &channel_ids, &category_ids],
+        )
+        .await
+    }
+
+    /// Copy the channels of a space with their settings, but without members and messages.
+    ///
+    /// Secret channels are left behind unless `user_id` is a member of them.
+    pub async fn clone_space<T: Querist>(
+        db: &mut T,
+        from_space: &Uuid,
+        to_space: &Uuid,
+        user_id: &Uuid,
+    ) -> Result<Vec<Channel>, DbError> {
+        let rows = db
+            .query(include_str!("sql/clone_channels.sql"), &[from_space, to_space, user_id])
+            .await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+
     pub async fn edit<T: Querist>(
         db: &mut T,
         id: &Uuid,
@@ -98,6 +235,9 @@ impl Channel {
         default_roll_command: Option<&str>,
         is_public: Option<bool>,
         is_document: Option<bool>,
+        system_events: Option<&[SystemEvent]>,
+        slow_mode_seconds: Option<i32>,
+        preview_rate_limit: Option<i32>,
     ) -> Result<Channel, ModelError> {
         use crate::validators;
 
@@ -111,6 +251,12 @@ impl Channel {
         if let Some(dice) = default_dice_type {
             validators::DICE.run(dice)?;
         }
+        if !slow_mode_seconds.map_or(true, |seconds| (0..=MAX_SLOW_MODE_SECONDS).contains(&seconds)) {
+            return Err(ValidationFailed("Slow mode shall be between 0 and 6 hours.").into());
+        }
+        if !preview_rate_limit.map_or(true, |limit| (0..=MAX_PREVIEW_RATE_LIMIT).contains(&limit)) {
+            return Err(ValidationFailed("Preview rate limit shall be between 0 and 100.").into());
+        }
         let row = db
             .query_exactly_one(
                 include_str!("sql/edit_channel.sql"),
@@ -122,6 +268,9 @@ impl Channel {
                     &default_roll_command,
                     &is_public,
                     &is_document,
+                    &system_events,
+                    &slow_mode_seconds,
+                    &preview_rate_limit,
                 ],
             )
             .await?;
@@ -143,6 +292,7 @@ impl Channel {
             .map(|row| ChannelWithMember {
                 channel: row.get(0),
                 member: row.get(1),
+                read_marker: row.get(2),
             })
             .collect();
         Ok(joined_channels)
@@ -257,6 +407,28 @@ impl ChannelMember {
         Ok(row.map(|row| row.get(0)))
     }
 
+    /// Copies the joined members of a channel, with their names and master state, into another.
+    pub async fn copy<T: Querist>(db: &mut T, from_channel: &Uuid, to_channel: &Uuid) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/copy_members.sql"), &[from_channel, to_channel])
+            .await
+    }
+
+    /// Members mentioned by user id or by character name, who are able to see the message.
+    pub async fn resolve_mentions<T: Querist>(db: &mut T, message: &Message) -> Result<Vec<Uuid>, DbError> {
+        let rows = db
+            .query(
+                include_str!("sql/resolve_mentions.sql"),
+                &[
+                    &message.channel_id,
+                    &message.sender_id,
+                    &message.entities,
+                    &message.whisper_to_users,
+                ],
+            )
+            .await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+
     pub async fn remove_user<T: Querist>(db: &mut T, user_id: &Uuid, channel_id: &Uuid) -> Result<u64, DbError> {
         db.execute(include_str!("sql/remove_user_from_channel.sql"), &[user_id, channel_id])
             .await
@@ -320,6 +492,7 @@ impl ChannelMember {
         ChannelMember::edit(db, *user_id, *channel_id, None, Some(color)).await
     }
 
+    /// `None` if the user is not a member of the channel or the value is unchanged.
     pub async fn set_master<T: Querist>(
         db: &mut T,
         user_id: &Uuid,
@@ -333,6 +506,271 @@ impl ChannelMember {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, FromSql, Clone)]
+#[serde(rename_all = "camelCase")]
+#[postgres(name = "channel_role_overrides")]
+pub struct ChannelRoleOverride {
+    pub channel_id: Uuid,
+    pub role_id: Uuid,
+    pub allow: Permissions,
+    pub deny: Permissions,
+}
+
+impl ChannelRoleOverride {
+    pub async fn get_by_channel<T: Querist>(
+        db: &mut T,
+        channel_id: &Uuid,
+    ) -> Result<Vec<ChannelRoleOverride>, DbError> {
+        let rows = db
+            .query(include_str!("sql/get_role_overrides.sql"), &[channel_id])
+            .await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+
+    pub async fn set<T: Querist>(
+        db: &mut T,
+        channel_id: &Uuid,
+        role_id: &Uuid,
+        allow: Permissions,
+        deny: Permissions,
+    ) -> Result<ChannelRoleOverride, DbError> {
+        let row = db
+            .query_exactly_one(
+                include_str!("sql/set_role_override.sql"),
+                &[channel_id, role_id, &allow, &deny],
+            )
+            .await?;
+        row.try_get(0)
+    }
+
+    pub async fn remove<T: Querist>(db: &mut T, channel_id: &Uuid, role_id: &Uuid) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/remove_role_override.sql"), &[channel_id, role_id])
+            .await
+    }
+
+    /// Channels and roles must be cloned first.
+    pub async fn clone_space<T: Querist>(db: &mut T, from_space: &Uuid, to_space: &Uuid) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/clone_role_overrides.sql"), &[from_space, to_space])
+            .await
+    }
+}
+
+/// How far a user has read a channel.
+#[derive(Debug, Serialize, Deserialize, FromSql, Clone)]
+#[serde(rename_all = "camelCase")]
+#[postgres(name = "channel_read_markers")]
+pub struct ReadMarker {
+    pub user_id: Uuid,
+    pub channel_id: Uuid,
+    /// The `pos` of the last read message.
+    pub pos: f64,
+    pub unread_count: i32,
+    pub mention_count: i32,
+    #[serde(with = "crate::date_format")]
+    pub updated: NaiveDateTime,
+}
+
+impl ReadMarker {
+    pub async fn get<T: Querist>(db: &mut T, user_id: &Uuid, channel_id: &Uuid) -> Result<Option<ReadMarker>, DbError> {
+        let result = db
+            .query_one(include_str!("sql/get_read_marker.sql"), &[user_id, channel_id])
+            .await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+
+    /// Moves the marker forward and recounts the messages after it,
+    /// returns `None` if the marker is already at or past `pos`.
+    pub async fn advance<T: Querist>(
+        db: &mut T,
+        user_id: &Uuid,
+        channel_id: &Uuid,
+        pos: f64,
+    ) -> Result<Option<ReadMarker>, DbError> {
+        let result = db
+            .query_one(
+                include_str!("sql/advance_read_marker.sql"),
+                &[user_id, channel_id, &pos],
+            )
+            .await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+
+    /// Counts a new message for the members who can see it, except the sender.
+    ///
+    /// Deleted or moved messages are not uncounted until the marker advances again.
+    /// A member without a marker yet starts from every message of the channel they can see.
+    pub async fn count_message<T: Querist>(
+        db: &mut T,
+        message: &Message,
+        whisper_to_users: Option<&Vec<Uuid>>,
+        mentioned: &[Uuid],
+    ) -> Result<u64, DbError> {
+        db.execute(
+            include_str!("sql/count_new_message.sql"),
+            &[
+                &message.channel_id,
+                &message.sender_id,
+                &message.pos,
+                &mentioned,
+                &whisper_to_users,
+            ],
+        )
+        .await
+    }
+
+    /// Recounts the messages after the markers of a channel, once its messages were moved around.
+    pub async fn recount_by_channel<T: Querist>(db: &mut T, channel_id: &Uuid) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/recount_read_markers.sql"), &[channel_id])
+            .await
+    }
+}
+
+/// A collapsible group of channels in a space.
+#[derive(Debug, Serialize, Deserialize, FromSql, Clone)]
+#[serde(rename_all = "camelCase")]
+#[postgres(name = "channel_categories")]
+pub struct ChannelCategory {
+    pub id: Uuid,
+    pub space_id: Uuid,
+    pub name: String,
+    pub position: i32,
+    #[serde(with = "crate::date_format")]
+    pub created: NaiveDateTime,
+}
+
+impl ChannelCategory {
+    pub async fn create<T: Querist>(db: &mut T, space_id: &Uuid, name: &str) -> Result<ChannelCategory, ModelError> {
+        use crate::validators::DISPLAY_NAME;
+        let name = merge_blank(name);
+        DISPLAY_NAME.run(&name)?;
+        let row = db
+            .query_exactly_one(include_str!("sql/create_category.sql"), &[space_id, &name])
+            .await?;
+        row.try_get(0).map_err(Into::into)
+    }
+
+    pub async fn get<T: Querist>(db: &mut T, id: &Uuid) -> Result<Option<ChannelCategory>, DbError> {
+        let result = db.query_one(include_str!("sql/get_category.sql"), &[id]).await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+
+    pub async fn get_by_space<T: Querist>(db: &mut T, space_id: &Uuid) -> Result<Vec<ChannelCategory>, DbError> {
+        let rows = db
+            .query(include_str!("sql/get_categories_by_space.sql"), &[space_id])
+            .await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+
+    pub async fn edit<T: Querist>(db: &mut T, id: &Uuid, name: &str) -> Result<Option<ChannelCategory>, ModelError> {
+        use crate::validators::DISPLAY_NAME;
+        let name = merge_blank(name);
+        DISPLAY_NAME.run(&name)?;
+        let result = db.query_one(include_str!("sql/edit_category.sql"), &[id, &name]).await;
+        inner_result_map(result, |row| row.try_get(0)).map_err(Into::into)
+    }
+
+    /// Channels in the category become uncategorized.
+    pub async fn delete<T: Querist>(db: &mut T, id: &Uuid) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/delete_category.sql"), &[id]).await
+    }
+
+    /// Positions follow the order of `category_ids`, categories not listed keep theirs.
+    pub async fn reorder<T: Querist>(db: &mut T, space_id: &Uuid, category_ids: &[Uuid]) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/reorder_categories.sql"), &[space_id, &category_ids])
+            .await
+    }
+}
+
+/// One of the characters a member is able to speak as in a channel.
+#[derive(Debug, Serialize, Deserialize, FromSql, Clone)]
+#[serde(rename_all = "camelCase")]
+#[postgres(name = "channel_characters")]
+pub struct ChannelCharacter {
+    pub id: Uuid,
+    pub channel_id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub text_color: Option<String>,
+    pub avatar_id: Option<Uuid>,
+    pub description: String,
+    #[serde(with = "crate::date_format")]
+    pub created: NaiveDateTime,
+}
+
+impl ChannelCharacter {
+    pub async fn create<T: Querist>(
+        db: &mut T,
+        channel_id: &Uuid,
+        user_id: &Uuid,
+        name: &str,
+        text_color: Option<&str>,
+        avatar_id: Option<&Uuid>,
+        description: &str,
+    ) -> Result<ChannelCharacter, ModelError> {
+        use crate::validators::{CHARACTER_NAME, DESCRIPTION, HEX_COLOR};
+        let name = merge_blank(name);
+        CHARACTER_NAME.run(&name)?;
+        if let Some(text_color) = text_color {
+            HEX_COLOR.run(text_color)?;
+        }
+        let description = description.trim();
+        DESCRIPTION.run(description)?;
+        let row = db
+            .query_exactly_one(
+                include_str!("sql/create_character.sql"),
+                &[channel_id, user_id, &name, &text_color, &avatar_id, &description],
+            )
+            .await?;
+        row.try_get(0).map_err(Into::into)
+    }
+
+    pub async fn get<T: Querist>(db: &mut T, id: &Uuid) -> Result<Option<ChannelCharacter>, DbError> {
+        let result = db.query_one(include_str!("sql/get_character.sql"), &[id]).await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+
+    pub async fn get_by_channel<T: Querist>(db: &mut T, channel_id: &Uuid) -> Result<Vec<ChannelCharacter>, DbError> {
+        let rows = db
+            .query(include_str!("sql/get_characters_by_channel.sql"), &[channel_id])
+            .await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+
+    pub async fn edit<T: Querist>(
+        db: &mut T,
+        id: &Uuid,
+        name: Option<&str>,
+        text_color: Option<&str>,
+        avatar_id: Option<&Uuid>,
+        description: Option<&str>,
+    ) -> Result<Option<ChannelCharacter>, ModelError> {
+        use crate::validators::{CHARACTER_NAME, DESCRIPTION, HEX_COLOR};
+        let name = name.map(merge_blank);
+        if let Some(name) = name.as_ref() {
+            CHARACTER_NAME.run(name)?;
+        }
+        if let Some(text_color) = text_color {
+            HEX_COLOR.run(text_color)?;
+        }
+        let description = description.map(str::trim);
+        if let Some(description) = description {
+            DESCRIPTION.run(description)?;
+        }
+        let result = db
+            .query_one(
+                include_str!("sql/edit_character.sql"),
+                &[id, &name, &text_color, &avatar_id, &description],
+            )
+            .await;
+        inner_result_map(result, |row| row.try_get(0)).map_err(Into::into)
+    }
+
+    /// Messages sent as the character keep their name but lose the reference.
+    pub async fn delete<T: Querist>(db: &mut T, id: &Uuid) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/delete_character.sql"), &[id]).await
+    }
+}
+
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Member {
@@ -392,11 +830,53 @@ async fn channels_test() -> Result<(), crate::error::AppError> {
     let channels = Channel::get_by_space(db, &space.id).await?;
     assert_eq!(channels[0].id, channel.id);
 
+    // categories and ordering
+    let category = ChannelCategory::create(db, &space.id, "Scenes").await?;
+    let other = ChannelCategory::create(db, &space.id, "Out of Character").await?;
+    ChannelCategory::reorder(db, &space.id, &[other.id, category.id]).await?;
+    let categories = ChannelCategory::get_by_space(db, &space.id).await?;
+    assert_eq!(categories[0].id, other.id);
+    Channel::reorder(db, &space.id, &[(channel.id, Some(category.id))]).await?;
+    let channel = Channel::get_by_id(db, &channel.id).await?.unwrap();
+    assert_eq!(channel.category_id, Some(category.id));
+    ChannelCategory::delete(db, &category.id).await?;
+    let channel = Channel::get_by_id(db, &channel.id).await?.unwrap();
+    assert_eq!(channel.category_id, None);
+    assert!(Channel::audience(db, &channel.id).await?.is_none());
+
+    // cloning keeps categories
+    Channel::reorder(db, &space.id, &[(channel.id, Some(other.id))]).await?;
+    let copy = Space::create(db, "Copied Space".to_string(), &user.id, String::new(), None, None).await?;
+    let cloned = Channel::clone_space(db, &space.id, &copy.id, &user.id).await?;
+    let copied_categories = ChannelCategory::get_by_space(db, &copy.id).await?;
+    assert_eq!(copied_categories.len(), 1);
+    assert_eq!(copied_categories[0].name, other.name);
+    assert_eq!(cloned[0].category_id, Some(copied_categories[0].id));
+    Channel::reorder(db, &space.id, &[(channel.id, None)]).await?;
+
     let new_name = "深水城水很深";
-    let channel_edited = Channel::edit(db, &channel.id, Some(new_name), None, None, None, Some(false), None).await?;
+    let channel_edited = Channel::edit(
+        db,
+        &channel.id,
+        Some(new_name),
+        None,
+        None,
+        None,
+        Some(false),
+        None,
+        None,
+        Some(30),
+        None,
+    )
+    .await?;
     assert_eq!(channel_edited.name, new_name);
+    assert_eq!(channel_edited.slow_mode_seconds, 30);
     assert_eq!(channel_edited.is_public, false);
     let (_, space) = Channel::get_with_space(db, &channel.id).await?.unwrap();
+    assert!(Channel::set_archived(db, &channel.id, true).await?.unwrap().archived);
+    assert!(Channel::ensure_active(db, &channel.id).await.is_err());
+    Channel::set_archived(db, &channel.id, false).await?;
+    Channel::ensure_active(db, &channel.id).await?;
     let channel = Channel::get_by_name(db, space.id, new_name).await?.unwrap();
     assert!(Channel::get_by_name(db, space.id, "Madoka").await?.is_none());
 
@@ -416,9 +896,14 @@ async fn channels_test() -> Result<(), crate::error::AppError> {
         .member;
     assert_eq!(member_fetched.join_date, member_altered.join_date);
     assert_eq!(member.join_date, member_fetched.join_date);
+    assert_eq!(Channel::audience(db, &channel.id).await?, Some(vec![user.id]));
 
+    // a kicked member is out of the audience of the secret channel
     ChannelMember::remove_user(db, &user.id, &channel.id).await?;
     assert_eq!(ChannelMember::get_by_channel(db, &channel.id, false).await?.len(), 0);
+    assert_eq!(Channel::audience(db, &channel.id).await?, Some(vec![]));
+    let visible = Channel::get_visible_by_space(db, &space.id, Some(&user.id)).await?;
+    assert!(visible.iter().all(|c| c.id != channel.id));
 
     ChannelMember::add_user(db, &user.id, &channel.id, "", false)
         .await
@@ -437,10 +922,34 @@ async fn channels_test() -> Result<(), crate::error::AppError> {
 
     let member = Member::get_by_channel(db, channel.id).await?;
     assert_eq!(member.len(), 1);
+    // `channel` was made private above
+    assert_eq!(Channel::audience(db, &channel.id).await?, Some(vec![user.id]));
+    let visible = Channel::get_visible_by_space(db, &space.id, None).await?;
+    assert!(visible.iter().all(|c| c.id != channel.id));
+    let visible = Channel::get_visible_by_space(db, &space.id, Some(&user.id)).await?;
+    assert!(visible.iter().any(|c| c.id == channel.id));
     ChannelMember::get_by_space(db, &space.id).await?;
 
+    // read markers
+    let marker = ReadMarker::advance(db, &user.id, &channel.id, 2.0).await?.unwrap();
+    assert_eq!(marker.unread_count, 0);
+    assert!(ReadMarker::advance(db, &user.id, &channel.id, 1.0).await?.is_none());
+    let marker = ReadMarker::get(db, &user.id, &channel.id).await?.unwrap();
+    assert_eq!(marker.pos, 2.0);
+
     Channel::max_pos(db).await?;
 
+    // characters
+    let npc = ChannelCharacter::create(db, &channel.id, &user.id, " Old  Man ", Some("#aabbcc"), None, "").await?;
+    assert_eq!(npc.name, "Old Man");
+    let npc = ChannelCharacter::edit(db, &npc.id, None, None, None, Some("A mysterious stranger"))
+        .await?
+        .unwrap();
+    assert_eq!(npc.description, "A mysterious stranger");
+    assert_eq!(ChannelCharacter::get_by_channel(db, &channel.id).await?.len(), 1);
+    ChannelCharacter::delete(db, &npc.id).await?;
+    assert!(ChannelCharacter::get(db, &npc.id).await?.is_none());
+
     ChannelMember::remove_user(db, &user.id, &channel.id).await?;
     ChannelMember::remove_user(db, &user.id, &channel_2.id).await?;
     assert!(ChannelMember::get(db, &user.id, &channel.id).await?.is_none());
@@ -0,0 +1,26 @@
+WITH tail AS (
+    SELECT count(*)::int AS unread_count,
+           (count(*) FILTER (
+               WHERE mentions(m.entities, $1, COALESCE(cm.character_name, ''))
+               ))::int  AS mention_count
+    FROM messages m
+             LEFT JOIN channel_members cm ON cm.channel_id = $2 AND cm.user_id = $1
+    WHERE m.channel_id = $2
+      AND m.pos > $3
+      AND m.deleted = false
+      AND m.sender_id <> $1
+      AND (m.whisper_to_users IS NULL
+        OR $1 = ANY (m.whisper_to_users)
+        OR cm.is_master)
+)
+INSERT
+INTO channel_read_markers (user_id, channel_id, pos, unread_count, mention_count)
+SELECT $1, $2, $3, tail.unread_count, tail.mention_count
+FROM tail
+ON CONFLICT (user_id, channel_id) DO UPDATE
+    SET pos           = excluded.pos,
+        unread_count  = excluded.unread_count,
+        mention_count = excluded.mention_count,
+        updated       = (now() at time zone 'utc')
+WHERE channel_read_markers.pos < excluded.pos
+RETURNING channel_read_markers;
@@ -0,0 +1,35 @@
+-- Categories are copied along, channels keep the copy of their category.
+-- Secret channels are only copied if user $3 is a member of them.
+WITH categories AS (
+    SELECT id AS source_id, uuid_generate_v1mc() AS id, name, position
+    FROM channel_categories
+    WHERE space_id = $1
+),
+     copied_categories AS (
+         INSERT INTO channel_categories (id, space_id, name, position)
+             SELECT id, $2, name, position
+             FROM categories
+     )
+INSERT
+INTO channels (name, topic, space_id, is_public, default_dice_type, default_roll_command, is_document, position,
+               system_events, category_id)
+SELECT c.name,
+       c.topic,
+       $2,
+       c.is_public,
+       c.default_dice_type,
+       c.default_roll_command,
+       c.is_document,
+       c.position,
+       c.system_events,
+       categories.id
+FROM channels c
+         LEFT JOIN categories ON categories.source_id = c.category_id
+WHERE c.space_id = $1
+  AND c.deleted = false
+  AND (c.is_public = true OR EXISTS(SELECT 1
+                                    FROM channel_members cm
+                                    WHERE cm.channel_id = c.id
+                                      AND cm.user_id = $3
+                                      AND cm.is_joined))
+RETURNING channels;
@@ -0,0 +1,10 @@
+-- Channels and roles are matched by their names, which are unique in a space.
+INSERT INTO channel_role_overrides (channel_id, role_id, allow, deny)
+SELECT new_channel.id, new_role.id, o.allow, o.deny
+FROM channel_role_overrides o
+         INNER JOIN channels old_channel ON o.channel_id = old_channel.id
+         INNER JOIN space_roles old_role ON o.role_id = old_role.id
+         INNER JOIN channels new_channel ON new_channel.space_id = $2 AND new_channel.name = old_channel.name
+         INNER JOIN space_roles new_role ON new_role.space_id = $2 AND new_role.name = old_role.name
+WHERE old_channel.space_id = $1
+  AND old_channel.deleted = false;
@@ -0,0 +1,6 @@
+INSERT INTO channel_members (user_id, channel_id, character_name, text_color, is_master)
+SELECT user_id, $2, character_name, text_color, is_master
+FROM channel_members
+WHERE channel_id = $1
+  AND is_joined = true
+ON CONFLICT (user_id, channel_id) DO NOTHING;
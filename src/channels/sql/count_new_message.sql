@@ -0,0 +1,34 @@
+WITH counted AS (
+    UPDATE channel_read_markers rm
+        SET unread_count = rm.unread_count + 1,
+            mention_count = rm.mention_count + (rm.user_id = ANY ($4::uuid[]))::int
+        FROM channel_members cm
+        WHERE rm.channel_id = $1
+            AND rm.pos < $3
+            AND cm.channel_id = $1
+            AND cm.user_id = rm.user_id
+            AND cm.user_id <> $2
+            AND cm.is_joined
+            AND ($5::uuid[] IS NULL OR cm.user_id = ANY ($5::uuid[]) OR cm.is_master)
+        RETURNING rm.user_id
+)
+-- Members without a marker haven't read anything yet, their counters start from every message they can see.
+INSERT
+INTO channel_read_markers (user_id, channel_id, unread_count, mention_count)
+SELECT cm.user_id, cm.channel_id, tail.unread_count, tail.mention_count
+FROM channel_members cm
+         CROSS JOIN LATERAL (
+    SELECT count(*)::int                                                                   AS unread_count,
+           (count(*) FILTER (WHERE mentions(m.entities, cm.user_id, cm.character_name)))::int AS mention_count
+    FROM messages m
+    WHERE m.channel_id = $1
+      AND m.deleted = false
+      AND m.sender_id <> cm.user_id
+      AND (m.whisper_to_users IS NULL OR cm.user_id = ANY (m.whisper_to_users) OR cm.is_master)
+    ) tail
+WHERE cm.channel_id = $1
+  AND cm.user_id <> $2
+  AND cm.is_joined
+  AND ($5::uuid[] IS NULL OR cm.user_id = ANY ($5::uuid[]) OR cm.is_master)
+  AND NOT EXISTS(SELECT 1 FROM channel_read_markers rm WHERE rm.channel_id = $1 AND rm.user_id = cm.user_id)
+ON CONFLICT (user_id, channel_id) DO NOTHING;
@@ -1,3 +1,4 @@
-INSERT INTO channels (space_id, name, is_public, default_dice_type)
-VALUES ($1, $2, $3, COALESCE($4, 'd20'))
+INSERT INTO channels (space_id, name, is_public, default_dice_type, position)
+VALUES ($1, $2, $3, COALESCE($4, 'd20'),
+        (SELECT COALESCE(max(position) + 1, 0) FROM channels WHERE space_id = $1 AND deleted = false))
 RETURNING channels;
@@ -0,0 +1,3 @@
+INSERT INTO channel_characters (channel_id, user_id, name, text_color, avatar_id, description)
+VALUES ($1, $2, $3, $4, $5, $6)
+RETURNING channel_characters;
@@ -0,0 +1,3 @@
+DELETE
+FROM channel_categories
+WHERE id = $1;
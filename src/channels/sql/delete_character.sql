@@ -0,0 +1,3 @@
+DELETE
+FROM channel_characters
+WHERE id = $1;
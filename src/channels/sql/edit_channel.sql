@@ -5,6 +5,9 @@ SET
     default_dice_type = COALESCE($4, default_dice_type),
     default_roll_command = COALESCE($5, default_roll_command),
     is_public = COALESCE($6, is_public),
-    is_document = COALESCE($7, is_document)
+    is_document = COALESCE($7, is_document),
+    system_events = COALESCE($8, system_events),
+    slow_mode_seconds = COALESCE($9, slow_mode_seconds),
+    preview_rate_limit = COALESCE($10, preview_rate_limit)
 WHERE id = $1 AND deleted = false
 RETURNING channels;
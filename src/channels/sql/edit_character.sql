@@ -0,0 +1,7 @@
+UPDATE channel_characters
+SET name        = COALESCE($2, name),
+    text_color  = COALESCE($3, text_color),
+    avatar_id   = COALESCE($4, avatar_id),
+    description = COALESCE($5, description)
+WHERE id = $1
+RETURNING channel_characters;
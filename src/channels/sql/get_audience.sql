@@ -0,0 +1,7 @@
+SELECT CASE
+           WHEN c.is_public THEN NULL
+           ELSE array(SELECT cm.user_id FROM channel_members cm WHERE cm.channel_id = c.id AND cm.is_joined)
+           END
+FROM channels c
+WHERE c.id = $1
+LIMIT 1;
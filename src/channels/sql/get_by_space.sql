@@ -2,4 +2,4 @@ SELECT channel
 FROM channels channel
 WHERE channel.space_id = $1
   AND deleted = false
-ORDER BY channel.created;
+ORDER BY channel.position, channel.created;
@@ -0,0 +1,4 @@
+SELECT channel_categories
+FROM channel_categories
+WHERE space_id = $1
+ORDER BY position, created;
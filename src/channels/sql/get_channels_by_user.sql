@@ -1,5 +1,6 @@
-SELECT c, cm
+SELECT c, cm, rm
 FROM channel_members cm
     INNER JOIN channels c ON cm.channel_id = c.id AND c.deleted = false
     INNER JOIN space_members sm ON cm.user_id = sm.user_id AND c.space_id = sm.space_id
+    LEFT JOIN channel_read_markers rm ON rm.user_id = cm.user_id AND rm.channel_id = cm.channel_id
 WHERE cm.user_id = $1 AND cm.is_joined;
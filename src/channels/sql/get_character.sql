@@ -0,0 +1,3 @@
+SELECT channel_characters
+FROM channel_characters
+WHERE id = $1;
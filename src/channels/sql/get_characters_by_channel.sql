@@ -0,0 +1,4 @@
+SELECT channel_characters
+FROM channel_characters
+WHERE channel_id = $1
+ORDER BY user_id, created;
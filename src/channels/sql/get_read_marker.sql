@@ -0,0 +1,4 @@
+SELECT channel_read_markers
+FROM channel_read_markers
+WHERE user_id = $1
+  AND channel_id = $2;
@@ -0,0 +1,3 @@
+SELECT o
+FROM channel_role_overrides o
+WHERE o.channel_id = $1;
@@ -0,0 +1,10 @@
+SELECT channel
+FROM channels channel
+WHERE channel.space_id = $1
+  AND channel.deleted = false
+  AND (channel.is_public = true OR EXISTS(SELECT 1
+                                          FROM channel_members cm
+                                          WHERE cm.channel_id = channel.id
+                                            AND cm.user_id = $2
+                                            AND cm.is_joined))
+ORDER BY channel.position, channel.created;
@@ -0,0 +1,6 @@
+SELECT c.archived, s.archived
+FROM channels c
+         INNER JOIN spaces s ON c.space_id = s.id
+WHERE c.id = $1
+  AND c.deleted = false
+LIMIT 1;
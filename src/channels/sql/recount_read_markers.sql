@@ -0,0 +1,19 @@
+UPDATE channel_read_markers rm
+SET unread_count  = tail.unread_count,
+    mention_count = tail.mention_count,
+    updated       = (now() at time zone 'utc')
+FROM channel_read_markers marker
+         LEFT JOIN channel_members cm ON cm.channel_id = marker.channel_id AND cm.user_id = marker.user_id
+         CROSS JOIN LATERAL (
+    SELECT count(*)::int                                                                                AS unread_count,
+           (count(*) FILTER (WHERE mentions(m.entities, marker.user_id, COALESCE(cm.character_name, ''))))::int AS mention_count
+    FROM messages m
+    WHERE m.channel_id = marker.channel_id
+      AND m.pos > marker.pos
+      AND m.deleted = false
+      AND m.sender_id <> marker.user_id
+      AND (m.whisper_to_users IS NULL OR marker.user_id = ANY (m.whisper_to_users) OR cm.is_master)
+    ) tail
+WHERE marker.channel_id = $1
+  AND rm.channel_id = marker.channel_id
+  AND rm.user_id = marker.user_id;
@@ -0,0 +1,4 @@
+DELETE
+FROM channel_role_overrides
+WHERE channel_id = $1
+  AND role_id = $2;
@@ -0,0 +1,5 @@
+UPDATE channel_categories category
+SET position = o.position
+FROM unnest($2::uuid[]) WITH ORDINALITY AS o(id, position)
+WHERE category.id = o.id
+  AND category.space_id = $1;
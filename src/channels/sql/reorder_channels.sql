@@ -0,0 +1,7 @@
+UPDATE channels channel
+SET position    = o.position,
+    category_id = o.category_id
+FROM unnest($2::uuid[], $3::uuid[]) WITH ORDINALITY AS o(id, category_id, position)
+WHERE channel.id = o.id
+  AND channel.space_id = $1
+  AND channel.deleted = false;
@@ -0,0 +1,7 @@
+SELECT cm.user_id
+FROM channel_members cm
+WHERE cm.channel_id = $1
+  AND cm.user_id <> $2
+  AND cm.is_joined
+  AND mentions($3, cm.user_id, cm.character_name)
+  AND ($4::uuid[] IS NULL OR cm.user_id = ANY ($4::uuid[]) OR cm.is_master);
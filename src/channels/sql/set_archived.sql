@@ -0,0 +1,5 @@
+UPDATE channels
+SET archived = $2
+WHERE id = $1
+  AND deleted = false
+RETURNING channels;
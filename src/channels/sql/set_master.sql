@@ -3,4 +3,5 @@ SET is_master = COALESCE($3, is_master)
 WHERE user_id = $1
   AND channel_id = $2
   AND is_joined
+  AND is_master IS DISTINCT FROM COALESCE($3, is_master)
 RETURNING channel_members;
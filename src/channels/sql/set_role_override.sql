@@ -0,0 +1,4 @@
+INSERT INTO channel_role_overrides (channel_id, role_id, allow, deny)
+VALUES ($1, $2, $3, $4)
+ON CONFLICT (channel_id, role_id) DO UPDATE SET allow = $3, deny = $4
+RETURNING channel_role_overrides;
@@ -83,6 +83,22 @@ pub trait Querist: Send {
     ) -> Result<u64, DbError> {
         self.execute_typed(source, &[], params).await
     }
+
+    /// Marks a point to roll back to if the next statements may fail.
+    ///
+    /// A failed statement aborts the whole transaction, but outside of a transaction
+    /// it only fails itself, so savepoints are a no-op there.
+    async fn savepoint(&mut self, _name: &'static str) -> Result<(), DbError> {
+        Ok(())
+    }
+
+    async fn rollback_to(&mut self, _name: &'static str) -> Result<(), DbError> {
+        Ok(())
+    }
+
+    async fn release(&mut self, _name: &'static str) -> Result<(), DbError> {
+        Ok(())
+    }
 }
 
 pub fn get_postgres_url() -> String {
@@ -285,4 +301,20 @@ impl Querist for Transaction<'_> {
         let statement = self.get_statement(source.into(), types).await?;
         self.transaction.execute(&statement, params).await
     }
+
+    async fn savepoint(&mut self, name: &'static str) -> Result<(), DbError> {
+        self.transaction.batch_execute(&format!("SAVEPOINT {}", name)).await
+    }
+
+    async fn rollback_to(&mut self, name: &'static str) -> Result<(), DbError> {
+        self.transaction
+            .batch_execute(&format!("ROLLBACK TO SAVEPOINT {}", name))
+            .await
+    }
+
+    async fn release(&mut self, name: &'static str) -> Result<(), DbError> {
+        self.transaction
+            .batch_execute(&format!("RELEASE SAVEPOINT {}", name))
+            .await
+    }
 }
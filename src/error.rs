@@ -24,6 +24,8 @@ pub enum AppError {
     NoPermission(String),
     #[error("Validation failed: {0}")]
     Validation(#[from] ValidationFailed),
+    #[error("Validation failed: {0}")]
+    Entity(#[from] crate::messages::EntityError),
     #[error("An unexpected error occurred")]
     Unexpected(anyhow::Error),
     #[error("An unexpected serialize error occurred")]
@@ -36,6 +38,10 @@ pub enum AppError {
     Conflict(String),
     #[error("Limit exceed")]
     LimitExceeded(&'static str),
+    #[error("The {0} is archived")]
+    Archived(&'static str),
+    #[error("Too many requests, retry after {1} ms")]
+    RateLimited(&'static str, u64),
     #[error("An I/O error occurred")]
     Hyper {
         #[from]
@@ -57,10 +63,12 @@ impl AppError {
             Unauthenticated(_) => StatusCode::UNAUTHORIZED,
             NotFound(_) => StatusCode::NOT_FOUND,
             NoPermission(_) => StatusCode::FORBIDDEN,
-            Validation(_) | BadRequest(_) => StatusCode::BAD_REQUEST,
+            Validation(_) | Entity(_) | BadRequest(_) => StatusCode::BAD_REQUEST,
             MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
             Conflict(_) => StatusCode::CONFLICT,
             LimitExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+            Archived(_) => StatusCode::FORBIDDEN,
+            RateLimited(_, _) => StatusCode::TOO_MANY_REQUESTS,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -71,11 +79,13 @@ impl AppError {
             Unauthenticated(_) => "UNAUTHENTICATED",
             NotFound(_) => "NOT_FOUND",
             NoPermission(_) => "NO_PERMISSION",
-            Validation(_) => "VALIDATION_FAIL",
+            Validation(_) | Entity(_) => "VALIDATION_FAIL",
             BadRequest(_) => "BAD_REQUEST",
             MethodNotAllowed => "METHOD_NOT_ALLOWED",
             LimitExceeded(_) => "LIMIT_EXCEEDED",
             Conflict(_) => "CONFLICT",
+            Archived(_) => "ARCHIVED",
+            RateLimited(_, _) => "RATE_LIMITED",
             _ => "UNEXPECTED",
         }
     }
@@ -87,6 +97,9 @@ impl AppError {
             NotFound(something) => Value::String(something.to_string()),
             Conflict(something) => Value::String(something.clone()),
             LimitExceeded(what) => Value::String(what.to_string()),
+            Archived(what) => Value::String(what.to_string()),
+            RateLimited(what, retry_after) => serde_json::json!({ "what": what, "retryAfter": retry_after }),
+            Entity(e) => serde_json::to_value(e).unwrap_or(Value::Null),
             _ => Value::Null,
         }
     }
@@ -153,6 +166,8 @@ pub enum ModelError {
     Database(DbError),
     #[error("{0} already exists")]
     Conflict(String),
+    #[error("{0}")]
+    Entity(#[from] crate::messages::EntityError),
 }
 
 impl From<ModelError> for AppError {
@@ -164,6 +179,7 @@ impl From<ModelError> for AppError {
                 backtrace: Backtrace::capture(),
             },
             ModelError::Conflict(type_) => AppError::Conflict(type_),
+            ModelError::Entity(e) => AppError::Entity(e),
         }
     }
 }
@@ -185,14 +201,14 @@ impl From<DbError> for ModelError {
 pub fn log_error(e: &AppError, uri: &Uri) {
     use crate::error::AppError::*;
     match e {
-        NotFound(_) => log::debug!("{} - {}", uri, e),
+        NotFound(_) | RateLimited(_, _) => log::debug!("{} - {}", uri, e),
         Conflict(e) => log::warn!("[Conflict] {} {}", uri, e),
-        Validation(_) | BadRequest(_) | MethodNotAllowed => {
+        Validation(_) | Entity(_) | BadRequest(_) | MethodNotAllowed => {
             log::info!("[Bad Request] {} - {}", uri, e)
         }
         e => {
             log::error!("{} - {}\n", uri, e);
-            if let Some(backtrace) = e.backtrace() {
+            if let Some(backtrace) = std::error::request_ref::<Backtrace>(e) {
                 log::error!("{}", backtrace);
             }
             let mut source: Option<&_> = e.source();
@@ -8,3 +8,4 @@ pub mod tasks;
 
 pub use events::{Event, EventBody};
 pub use handlers::router;
+pub use models::{DbEvent, DbEventType};
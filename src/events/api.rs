@@ -1,6 +1,29 @@
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(Deserialize, Serialize)]
 pub struct Token {
     pub token: Option<String>,
 }
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Notifications {
+    #[serde(default)]
+    pub unread_only: bool,
+    /// The `created` of the last notification of the previous page.
+    #[serde(default, with = "crate::date_format::option")]
+    pub before: Option<NaiveDateTime>,
+    /// The `id` of the last notification of the previous page, to page through notifications created at the same time.
+    pub before_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkRead {
+    /// Marks all notifications as read if omitted.
+    #[serde(default)]
+    pub ids: Option<Vec<Uuid>>,
+}
@@ -11,12 +11,40 @@ use crate::utils::timestamp;
 pub struct SyncEvent {
     pub event: Event,
     pub encoded: String,
+    /// Users allowed to receive the event, `None` for everyone in the mailbox.
+    pub audience: Option<Vec<Uuid>>,
+    /// Users who receive their own version of the event instead.
+    pub excluded: Vec<Uuid>,
 }
 
 impl SyncEvent {
     pub fn new(event: Event) -> SyncEvent {
+        SyncEvent::with_audience(event, None)
+    }
+
+    pub fn with_audience(event: Event, audience: Option<Vec<Uuid>>) -> SyncEvent {
         let encoded = serde_json::to_string(&event).unwrap();
-        SyncEvent { encoded, event }
+        SyncEvent {
+            encoded,
+            event,
+            audience,
+            excluded: Vec::new(),
+        }
+    }
+
+    pub fn excluding(event: Event, excluded: Vec<Uuid>) -> SyncEvent {
+        let mut event = SyncEvent::new(event);
+        event.excluded = excluded;
+        event
+    }
+
+    pub fn visible_to(&self, user_id: Option<&Uuid>) -> bool {
+        match (&self.audience, user_id) {
+            (None, Some(user_id)) => !self.excluded.contains(user_id),
+            (None, None) => true,
+            (Some(audience), Some(user_id)) => audience.contains(user_id),
+            (Some(_), None) => false,
+        }
     }
 }
 
@@ -35,6 +63,14 @@ pub fn get_heartbeat_map() -> &'static HeartbeatMap {
     HEARTBEAT_MAP.get_or_init(|| Mutex::new(HashMap::new()))
 }
 
+/// Audiences of channels with the time they were resolved, see `Event::audience`.
+type AudienceMap = std::sync::Mutex<HashMap<Uuid, (i64, Option<Vec<Uuid>>)>>;
+static AUDIENCE_MAP: OnceCell<AudienceMap> = OnceCell::new();
+
+pub fn get_audience_map() -> &'static AudienceMap {
+    AUDIENCE_MAP.get_or_init(|| std::sync::Mutex::new(HashMap::new()))
+}
+
 pub async fn get_mailbox_broadcast_rx(id: &Uuid) -> broadcast::Receiver<Arc<SyncEvent>> {
     let broadcast_table = get_broadcast_table();
     let table = broadcast_table.read().await;
@@ -1,10 +1,11 @@
-use crate::channels::models::Member;
-use crate::channels::Channel;
+use crate::channels::models::{Member, ReadMarker};
+use crate::channels::{Channel, ChannelMember};
 
 use crate::events::context;
 use crate::events::context::SyncEvent;
+use crate::events::models::DbEvent;
 use crate::events::preview::{Preview, PreviewPost};
-use crate::messages::Message;
+use crate::messages::{Message, ReactionCount};
 use crate::spaces::api::SpaceWithRelated;
 use crate::spaces::models::{space_users_status, StatusKind, UserStatus};
 use crate::utils::timestamp;
@@ -16,6 +17,8 @@ use std::sync::Arc;
 use tokio::spawn;
 use uuid::Uuid;
 
+pub const AUDIENCE_TTL: i64 = 10 * 1000;
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct EventQuery {
@@ -52,6 +55,19 @@ pub enum EventBody {
         channel_id: Uuid,
         message: Box<Message>,
     },
+    /// Messages changed by a bulk action; clients add edited messages they don't have.
+    #[serde(rename_all = "camelCase")]
+    MessagesBulkEdited {
+        channel_id: Uuid,
+        edited: Vec<Message>,
+        deleted: Vec<Uuid>,
+    },
+    #[serde(rename_all = "camelCase")]
+    ReactionChanged {
+        channel_id: Uuid,
+        message_id: Uuid,
+        reactions: Vec<ReactionCount>,
+    },
     #[serde(rename_all = "camelCase")]
     MessagePreview {
         channel_id: Uuid,
@@ -71,6 +87,11 @@ pub enum EventBody {
         channel_id: Uuid,
         members: Vec<Member>,
     },
+    /// The history of the channel was rewritten, clients should load it again.
+    #[serde(rename_all = "camelCase")]
+    ChannelResync {
+        channel_id: Uuid,
+    },
     Initialized,
     #[serde(rename_all = "camelCase")]
     StatusMap {
@@ -79,9 +100,46 @@ pub enum EventBody {
     },
     #[serde(rename_all = "camelCase")]
     SpaceUpdated {
-        space_with_related: SpaceWithRelated,
+        space_with_related: Box<SpaceWithRelated>,
     },
     AppUpdated,
+    /// A new notification in the inbox of the user, sent to the user mailbox.
+    #[serde(rename_all = "camelCase")]
+    Notification {
+        notification: DbEvent,
+    },
+    /// Sent to the user mailbox so that other devices of the user stay in sync.
+    #[serde(rename_all = "camelCase")]
+    ReadMarkerUpdated {
+        marker: ReadMarker,
+    },
+    /// Sent to the user mailbox of a user kicked from a channel, who no longer
+    /// receives events about the channel if it is secret.
+    #[serde(rename_all = "camelCase")]
+    RemovedFromChannel {
+        space_id: Uuid,
+        channel_id: Uuid,
+    },
+}
+
+impl EventBody {
+    /// The channel the event is about, if any.
+    fn channel_id(&self) -> Option<Uuid> {
+        use EventBody::*;
+        match self {
+            NewMessage { channel_id, .. }
+            | MessageDeleted { channel_id, .. }
+            | MessageEdited { channel_id, .. }
+            | MessagesBulkEdited { channel_id, .. }
+            | ReactionChanged { channel_id, .. }
+            | MessagePreview { channel_id, .. }
+            | ChannelDeleted { channel_id }
+            | ChannelEdited { channel_id, .. }
+            | Members { channel_id, .. }
+            | ChannelResync { channel_id } => Some(*channel_id),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Serialize, Debug)]
@@ -117,7 +175,26 @@ impl Event {
         Event::fire(EventBody::MessageEdited { message, channel_id }, mailbox)
     }
 
+    pub fn messages_bulk_edited(mailbox: Uuid, channel_id: Uuid, edited: Vec<Message>, deleted: Vec<Uuid>) {
+        let body = EventBody::MessagesBulkEdited {
+            channel_id,
+            edited,
+            deleted,
+        };
+        Event::fire(body, mailbox)
+    }
+
+    pub fn reaction_changed(mailbox: Uuid, channel_id: Uuid, message_id: Uuid, reactions: Vec<ReactionCount>) {
+        let body = EventBody::ReactionChanged {
+            channel_id,
+            message_id,
+            reactions,
+        };
+        Event::fire(body, mailbox)
+    }
+
     pub fn channel_deleted(mailbox: Uuid, channel_id: Uuid) {
+        Event::invalidate_audience(&channel_id);
         Event::transient(mailbox, EventBody::ChannelDeleted { channel_id })
     }
 
@@ -162,6 +239,7 @@ impl Event {
     }
 
     pub fn push_members(channel_id: Uuid) {
+        Event::invalidate_audience(&channel_id);
         spawn(async move {
             if let Err(e) = Event::fire_members(channel_id).await {
                 log::warn!("Failed to fetch member list: {}", e);
@@ -169,17 +247,37 @@ impl Event {
         });
     }
 
+    pub fn channel_resync(mailbox: Uuid, channel_id: Uuid) {
+        Event::fire(EventBody::ChannelResync { channel_id }, mailbox)
+    }
+
     pub fn channel_edited(channel: Channel) {
         let space_id = channel.space_id;
         let channel_id = channel.id;
+        Event::invalidate_audience(&channel_id);
         Event::transient(space_id, EventBody::ChannelEdited { channel, channel_id })
     }
 
+    pub fn notify(notification: DbEvent) {
+        if let Some(receiver_id) = notification.receiver_id {
+            Event::transient(receiver_id, EventBody::Notification { notification })
+        }
+    }
+
+    pub fn read_marker_updated(marker: ReadMarker) {
+        Event::transient(marker.user_id, EventBody::ReadMarkerUpdated { marker })
+    }
+
+    pub fn removed_from_channel(user_id: Uuid, space_id: Uuid, channel_id: Uuid) {
+        Event::invalidate_audience(&channel_id);
+        Event::transient(user_id, EventBody::RemovedFromChannel { space_id, channel_id })
+    }
+
     pub fn cache_key(mailbox: &Uuid) -> Vec<u8> {
         cache::make_key(b"mailbox", mailbox, b"events")
     }
 
-    pub async fn get_from_cache(mailbox: &Uuid) -> Vec<String> {
+    pub async fn get_from_cache(mailbox: &Uuid, user_id: Option<&Uuid>) -> Vec<String> {
         let cache = super::context::get_cache().try_mailbox(mailbox).await;
         if let Some(cache) = cache {
             let cache = cache.lock().await;
@@ -187,6 +285,7 @@ impl Event {
                 .events
                 .iter()
                 .chain(cache.preview_map.values())
+                .filter(|event| event.visible_to(user_id))
                 .map(|event| event.encoded.clone())
                 .collect()
         } else {
@@ -196,19 +295,58 @@ impl Event {
 
     pub fn space_updated(space_id: Uuid) {
         tokio::spawn(async move {
-            match crate::spaces::handlers::space_related(&space_id).await {
-                Ok(space_with_related) => {
-                    let body = EventBody::SpaceUpdated { space_with_related };
-                    Event::transient(space_id, body);
-                }
-                Err(e) => log::error!(
+            if let Err(e) = Event::fire_space_updated(space_id).await {
+                log::error!(
                     "There an error occurred while preparing the `space_updated` event: {}",
                     e
-                ),
+                );
             }
         });
     }
 
+    /// Members of secret channels receive the space with the secret channels they are in,
+    /// grouped by the channels they can see, and everyone else the space with public channels.
+    async fn fire_space_updated(space_id: Uuid) -> Result<(), anyhow::Error> {
+        let mut conn = database::get().await?;
+        let db = &mut *conn;
+        let secret_channels: Vec<Uuid> = Channel::get_by_space(db, &space_id)
+            .await?
+            .into_iter()
+            .filter(|channel| !channel.is_public)
+            .map(|channel| channel.id)
+            .collect();
+        let channel_members = ChannelMember::get_by_space(db, &space_id).await?;
+        drop(conn);
+        let mut visible: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for channel_id in secret_channels.iter() {
+            for member in channel_members.get(channel_id).into_iter().flatten() {
+                visible.entry(member.user_id).or_default().push(*channel_id);
+            }
+        }
+        let excluded: Vec<Uuid> = visible.keys().cloned().collect();
+        let mut groups: HashMap<Vec<Uuid>, Vec<Uuid>> = HashMap::new();
+        for (user_id, channels) in visible {
+            groups.entry(channels).or_default().push(user_id);
+        }
+
+        let event = |space_with_related| Event {
+            mailbox: space_id,
+            body: EventBody::SpaceUpdated {
+                space_with_related: Box::new(space_with_related),
+            },
+            timestamp: timestamp(),
+        };
+        let space_with_related = crate::spaces::handlers::space_related(&space_id, None).await?;
+        let public = SyncEvent::excluding(event(space_with_related), excluded);
+        Event::send(space_id, Arc::new(public)).await;
+        for (_, audience) in groups {
+            let space_with_related = crate::spaces::handlers::space_related(&space_id, Some(&audience[0])).await?;
+            let private = SyncEvent::with_audience(event(space_with_related), Some(audience));
+            Event::send(space_id, Arc::new(private)).await;
+        }
+        Ok(())
+    }
+
     async fn send(mailbox: Uuid, event: Arc<SyncEvent>) {
         let broadcast_table = context::get_broadcast_table();
         let table = broadcast_table.read().await;
@@ -225,25 +363,71 @@ impl Event {
             .ok_or(anyhow::anyhow!("channel not found"))?;
         let members = Member::get_by_channel(db, channel_id).await?;
         drop(conn);
-        let event = SyncEvent::new(Event {
-            mailbox: channel_id,
-            body: EventBody::Members { members, channel_id },
-            timestamp: timestamp(),
-        });
+        let audience = if channel.is_public {
+            None
+        } else {
+            Some(members.iter().map(|member| member.user.id).collect())
+        };
+        let event = SyncEvent::with_audience(
+            Event {
+                mailbox: channel_id,
+                body: EventBody::Members { members, channel_id },
+                timestamp: timestamp(),
+            },
+            audience,
+        );
 
         Event::send(channel.space_id, Arc::new(event)).await;
         Ok(())
     }
 
-    fn build(body: EventBody, mailbox: Uuid) -> Arc<SyncEvent> {
-        Arc::new(SyncEvent::new(Event {
+    /// Events about a secret channel are only delivered to its members.
+    ///
+    /// Audiences are cached for `AUDIENCE_TTL` milliseconds, so that a burst of previews doesn't
+    /// query the database for each of them. Membership changes invalidate them at once.
+    async fn audience(body: &EventBody) -> Option<Vec<Uuid>> {
+        let channel_id = body.channel_id()?;
+        let now = timestamp();
+        if let Some((resolved, audience)) = context::get_audience_map().lock().unwrap().get(&channel_id) {
+            if now - resolved < AUDIENCE_TTL {
+                return audience.clone();
+            }
+        }
+        let result = match database::get().await {
+            Ok(mut conn) => Channel::audience(&mut *conn, &channel_id)
+                .await
+                .map_err(anyhow::Error::from),
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(audience) => {
+                let mut audience_map = context::get_audience_map().lock().unwrap();
+                audience_map.insert(channel_id, (now, audience.clone()));
+                audience
+            }
+            Err(e) => {
+                log::error!("Failed to get the audience of channel {}: {}", channel_id, e);
+                Some(Vec::new())
+            }
+        }
+    }
+
+    fn invalidate_audience(channel_id: &Uuid) {
+        context::get_audience_map().lock().unwrap().remove(channel_id);
+    }
+
+    async fn build(body: EventBody, mailbox: Uuid) -> Arc<SyncEvent> {
+        let audience = Event::audience(&body).await;
+        let event = Event {
             mailbox,
             body,
             timestamp: timestamp(),
-        }))
+        };
+        Arc::new(SyncEvent::with_audience(event, audience))
     }
 
     async fn async_fire(body: EventBody, mailbox: Uuid) {
+        let event = Event::build(body, mailbox).await;
         let cache = super::context::get_cache().mailbox(&mailbox).await;
         let mut cache = cache.lock().await;
 
@@ -252,7 +436,7 @@ impl Event {
             Other,
         }
 
-        let kind = match &body {
+        let kind = match &event.event.body {
             EventBody::MessagePreview { preview, channel_id: _ } => Kind::Preview {
                 sender_id: preview.sender_id,
                 channel_id: preview.channel_id,
@@ -260,7 +444,6 @@ impl Event {
             _ => Kind::Other,
         };
 
-        let event = Event::build(body, mailbox);
         match kind {
             Kind::Preview { sender_id, channel_id } => {
                 cache.preview_map.insert((sender_id, channel_id), event.clone());
@@ -275,7 +458,7 @@ impl Event {
 
     pub fn transient(mailbox: Uuid, body: EventBody) {
         spawn(async move {
-            let event = Event::build(body, mailbox);
+            let event = Event::build(body, mailbox).await;
             Event::send(mailbox, event).await;
         });
     }
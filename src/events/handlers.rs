@@ -1,13 +1,13 @@
-use super::api::Token;
+use super::api::{MarkRead, Notifications, Token};
 use super::events::EventQuery;
-use super::Event;
+use super::{DbEvent, Event};
 use crate::cache::make_key;
 use crate::csrf::authenticate;
 use crate::database::Querist;
 use crate::error::{AppError, Find};
 use crate::events::context::get_mailbox_broadcast_rx;
 use crate::events::events::ClientEvent;
-use crate::interface::{missing, ok_response, parse_query, Request, Response};
+use crate::interface::{self, missing, ok_response, parse_query, Request, Response};
 use crate::spaces::models::StatusKind;
 use crate::spaces::{Space, SpaceMember};
 use crate::utils::timestamp;
@@ -44,7 +44,7 @@ async fn check_space_perms<T: Querist>(
     Ok(())
 }
 
-async fn push_events(mailbox: Uuid, outgoing: &mut Sender) -> Result<(), anyhow::Error> {
+async fn push_events(mailbox: Uuid, user_id: Option<Uuid>, outgoing: &mut Sender) -> Result<(), anyhow::Error> {
     use futures::channel::mpsc::channel;
     use tokio::sync::broadcast::error::RecvError;
     use tokio::time::interval;
@@ -66,7 +66,7 @@ async fn push_events(mailbox: Uuid, outgoing: &mut Sender) -> Result<(), anyhow:
         let mut tx = tx.clone();
         let mut mailbox_rx = get_mailbox_broadcast_rx(&mailbox).await;
 
-        let cached_events = Event::get_from_cache(&mailbox).await;
+        let cached_events = Event::get_from_cache(&mailbox, user_id.as_ref()).await;
         for e in cached_events.into_iter() {
             tx.send(WsMessage::Text(e)).await.ok();
         }
@@ -78,7 +78,8 @@ async fn push_events(mailbox: Uuid, outgoing: &mut Sender) -> Result<(), anyhow:
 
         loop {
             let message = match mailbox_rx.recv().await {
-                Ok(event) => WsMessage::Text(event.encoded.clone()),
+                Ok(event) if event.visible_to(user_id.as_ref()) => WsMessage::Text(event.encoded.clone()),
+                Ok(_) => continue,
                 Err(RecvError::Lagged(lagged)) => {
                     log::warn!("lagged {} at {}", lagged, mailbox);
                     continue;
@@ -150,13 +151,16 @@ async fn connect(req: Request) -> Result<Response, anyhow::Error> {
     let space = Space::get_by_id(db, &mailbox).await?;
     if let Some(space) = space.as_ref() {
         check_space_perms(db, space, &user_id).await?;
+    } else if user_id.as_ref().ok() != Some(&mailbox) {
+        // Mailboxes other than spaces belong to users.
+        return Err(AppError::NoPermission(format!("This mailbox is not yours")).into());
     }
     let user_id = user_id.ok();
     establish_web_socket(req, move |ws_stream| async move {
         let (mut outgoing, incoming) = ws_stream.split();
 
         let server_push_events = async move {
-            if let Err(e) = push_events(mailbox, &mut outgoing).await {
+            if let Err(e) = push_events(mailbox, user_id, &mut outgoing).await {
                 log::warn!("Failed to push events: {}", e);
             }
             outgoing.close().await.ok();
@@ -199,6 +203,45 @@ pub async fn token(req: Request) -> Result<Token, AppError> {
     }
 }
 
+async fn notifications(req: Request) -> Result<Vec<DbEvent>, AppError> {
+    let session = authenticate(&req).await?;
+    let Notifications {
+        unread_only,
+        before,
+        before_id,
+        limit,
+    } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let limit = limit.unwrap_or(64).clamp(1, 256);
+    DbEvent::get_by_receiver(
+        &mut *conn,
+        &session.user_id,
+        unread_only,
+        before,
+        before_id.as_ref(),
+        limit,
+    )
+    .await
+    .map_err(Into::into)
+}
+
+async fn unread_count(req: Request) -> Result<i64, AppError> {
+    let session = authenticate(&req).await?;
+    let mut conn = database::get().await?;
+    DbEvent::unread_count(&mut *conn, &session.user_id)
+        .await
+        .map_err(Into::into)
+}
+
+async fn mark_read(req: Request) -> Result<u64, AppError> {
+    let session = authenticate(&req).await?;
+    let MarkRead { ids } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    DbEvent::mark_read(&mut *conn, &session.user_id, ids.as_deref())
+        .await
+        .map_err(Into::into)
+}
+
 pub async fn router(req: Request, path: &str) -> Result<Response, AppError> {
     use hyper::Method;
 
@@ -210,6 +253,9 @@ pub async fn router(req: Request, path: &str) -> Result<Response, AppError> {
             })
         }),
         ("/token", Method::GET) => token(req).await.map(ok_response),
+        ("/notifications", Method::GET) => notifications(req).await.map(ok_response),
+        ("/unread_count", Method::GET) => unread_count(req).await.map(ok_response),
+        ("/mark_read", Method::POST) => mark_read(req).await.map(ok_response),
         _ => missing(),
     }
 }
@@ -1,26 +1,127 @@
 use chrono::NaiveDateTime;
-use postgres_types::FromSql;
+use postgres_types::{FromSql, ToSql};
+use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use uuid::Uuid;
 
-#[derive(FromSql)]
-#[postgres(name = "channels")]
+use crate::database::Querist;
+use crate::error::DbError;
+
+#[derive(Debug, Serialize, Deserialize, FromSql, ToSql, Clone, Copy, PartialEq, Eq)]
+#[postgres(name = "event_type")]
 pub enum DbEventType {
     Joined,
     Left,
     NewMaster,
     NewAdmin,
+    /// Mentioned by user or by character name in a message.
+    Mentioned,
+    /// Someone replied to a message of the receiver.
+    Replied,
+    /// Added to a channel by someone else.
+    Invited,
+    /// A user asks to join a space the receiver manages members of.
+    JoinRequested,
+    /// A join request of the receiver was approved or rejected.
+    JoinRequestDecided,
 }
 
-#[derive(FromSql)]
+/// A persistent notification in the inbox of `receiver_id`.
+#[derive(Debug, Serialize, FromSql, Clone)]
+#[serde(rename_all = "camelCase")]
 #[postgres(name = "events")]
 pub struct DbEvent {
     pub id: Uuid,
+    #[serde(rename = "type")]
     #[postgres(name = "type")]
     pub kind: DbEventType,
     pub channel_id: Option<Uuid>,
     pub space_id: Option<Uuid>,
     pub receiver_id: Option<Uuid>,
     pub payload: JsonValue,
+    #[serde(with = "crate::date_format")]
     pub created: NaiveDateTime,
+    pub read: bool,
+}
+
+impl DbEvent {
+    pub async fn create<T: Querist>(
+        db: &mut T,
+        kind: DbEventType,
+        receiver_id: &Uuid,
+        space_id: Option<&Uuid>,
+        channel_id: Option<&Uuid>,
+        payload: JsonValue,
+    ) -> Result<DbEvent, DbError> {
+        let row = db
+            .query_exactly_one(
+                include_str!("sql/create_notification.sql"),
+                &[&kind, receiver_id, &space_id, &channel_id, &payload],
+            )
+            .await?;
+        row.try_get(0)
+    }
+
+    pub async fn get_by_receiver<T: Querist>(
+        db: &mut T,
+        receiver_id: &Uuid,
+        unread_only: bool,
+        before: Option<NaiveDateTime>,
+        before_id: Option<&Uuid>,
+        limit: i64,
+    ) -> Result<Vec<DbEvent>, DbError> {
+        let rows = db
+            .query(
+                include_str!("sql/get_notifications.sql"),
+                &[receiver_id, &unread_only, &before, &before_id, &limit],
+            )
+            .await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+
+    pub async fn unread_count<T: Querist>(db: &mut T, receiver_id: &Uuid) -> Result<i64, DbError> {
+        let row = db
+            .query_exactly_one(include_str!("sql/count_unread_notifications.sql"), &[receiver_id])
+            .await?;
+        row.try_get(0)
+    }
+
+    /// Marks all notifications of the receiver as read if `ids` is `None`.
+    pub async fn mark_read<T: Querist>(db: &mut T, receiver_id: &Uuid, ids: Option<&[Uuid]>) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/mark_notifications_read.sql"), &[receiver_id, &ids])
+            .await
+    }
+}
+
+#[tokio::test]
+async fn notification_test() -> Result<(), crate::error::AppError> {
+    use crate::database::Client;
+    use crate::users::User;
+
+    let mut client = Client::new().await?;
+    let mut trans = client.transaction().await?;
+    let db = &mut trans;
+    let user = User::register(
+        db,
+        "notification@mythal.net",
+        "notification_test",
+        "Notified",
+        "no password",
+    )
+    .await?;
+    let payload = serde_json::json!({ "text": "hi" });
+    let notification = DbEvent::create(db, DbEventType::Mentioned, &user.id, None, None, payload).await?;
+    assert!(!notification.read);
+    assert_eq!(DbEvent::unread_count(db, &user.id).await?, 1);
+    let inbox = DbEvent::get_by_receiver(db, &user.id, true, None, None, 10).await?;
+    assert_eq!(inbox[0].id, notification.id);
+    DbEvent::mark_read(db, &user.id, Some(&[notification.id])).await?;
+    assert_eq!(DbEvent::unread_count(db, &user.id).await?, 0);
+    assert_eq!(
+        DbEvent::get_by_receiver(db, &user.id, false, None, None, 10)
+            .await?
+            .len(),
+        1
+    );
+    Ok(())
 }
@@ -1,7 +1,10 @@
-use crate::channels::ChannelMember;
+use crate::channels::models::ChannelCharacter;
+use crate::channels::{Channel, ChannelMember};
 use crate::database;
 use crate::error::AppError;
 use crate::events::Event;
+use crate::spaces::permissions::channel_permission;
+use crate::spaces::Permissions;
 use crate::{cache, error::Find};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
@@ -28,6 +31,7 @@ pub struct Preview {
     pub pos: f64,
     #[serde(with = "crate::date_format::option")]
     pub edit_for: Option<NaiveDateTime>,
+    pub character_id: Option<Uuid>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -46,6 +50,8 @@ pub struct PreviewPost {
     #[serde(default)]
     #[serde(with = "crate::date_format::option")]
     pub edit_for: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub character_id: Option<Uuid>,
 }
 
 impl PreviewPost {
@@ -61,6 +67,7 @@ impl PreviewPost {
             entities,
             edit_for,
             clear,
+            character_id,
         } = self;
         let mut conn = database::get().await?;
         let mut cache = cache::conn().await;
@@ -77,6 +84,25 @@ impl PreviewPost {
             .await
             .or_no_permission()?
             .is_master;
+        channel_permission(db, &user_id, &channel_id, Permissions::SEND_MESSAGES).await?;
+        Channel::ensure_active(db, &channel_id).await?;
+        if !clear && !should_finish {
+            let channel = Channel::get_by_id(db, &channel_id).await.or_not_found()?;
+            // Previews over the limit are dropped, the next one carries the latest text anyway.
+            match channel.check_preview_rate(cache, &user_id).await {
+                Err(AppError::RateLimited(_, remaining)) => {
+                    log::debug!("A preview of {} was dropped, retry after {}ms", user_id, remaining);
+                    return Ok(());
+                }
+                result => result?,
+            }
+        }
+        if let Some(character_id) = character_id.as_ref() {
+            let character = ChannelCharacter::get(db, character_id).await.or_not_found()?;
+            if character.user_id != user_id || character.channel_id != channel_id {
+                return Err(AppError::NoPermission(format!("The character is not yours")));
+            }
+        }
         let whisper_to_users = None;
         let preview = Box::new(Preview {
             id,
@@ -95,6 +121,7 @@ impl PreviewPost {
             edit_for,
             clear,
             pos: start,
+            character_id,
         });
 
         if should_finish {
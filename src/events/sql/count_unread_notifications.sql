@@ -0,0 +1,4 @@
+SELECT count(*)
+FROM events
+WHERE receiver_id = $1
+  AND read = false;
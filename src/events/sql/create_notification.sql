@@ -0,0 +1,3 @@
+INSERT INTO events (type, receiver_id, space_id, channel_id, payload)
+VALUES ($1, $2, $3, $4, $5)
+RETURNING events;
@@ -0,0 +1,7 @@
+SELECT events
+FROM events
+WHERE receiver_id = $1
+  AND ($2 = false OR read = false)
+  AND ($3::timestamp IS NULL OR created < $3 OR (created = $3 AND id < $4))
+ORDER BY created DESC, id DESC
+LIMIT $5;
@@ -0,0 +1,5 @@
+UPDATE events
+SET read = true
+WHERE receiver_id = $1
+  AND read = false
+  AND ($2::uuid[] IS NULL OR id = ANY ($2));
@@ -1,6 +1,13 @@
-use crate::events::context::{get_broadcast_table, get_heartbeat_map};
-use crate::events::Event;
-use crate::spaces::Space;
+use crate::channels::models::{ChannelCharacter, ReadMarker};
+use crate::channels::{Channel, ChannelMember};
+use crate::database::Transaction;
+use crate::error::AppError;
+use crate::events::context::{get_audience_map, get_broadcast_table, get_heartbeat_map};
+use crate::events::events::AUDIENCE_TTL;
+use crate::events::{DbEvent, DbEventType, Event};
+use crate::messages::{retain_entities, Message, ScheduledMessage};
+use crate::spaces::{Permissions, Space};
+use crate::stats::Stats;
 use crate::utils::timestamp;
 use crate::{cache, database};
 use futures::StreamExt;
@@ -16,6 +23,22 @@ pub fn start() {
     tokio::spawn(heartbeat_clean());
     tokio::spawn(broadcast_clean());
     tokio::spawn(push_status());
+    tokio::spawn(send_scheduled());
+    tokio::spawn(refresh_space_stats());
+}
+
+async fn refresh_space_stats() {
+    IntervalStream::new(interval(Duration::from_secs(60)))
+        .for_each(|_| async {
+            let result = match database::get().await {
+                Ok(mut db) => Space::refresh_stats(&mut *db).await.map_err(AppError::from),
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = result {
+                log::error!("Failed to refresh space stats: {}", e);
+            }
+        })
+        .await;
 }
 
 async fn push_status() {
@@ -23,7 +46,11 @@ async fn push_status() {
         .for_each(|_| async {
             let spaces = match database::get().await {
                 Ok(mut db) => match Space::all(&mut *db).await {
-                    Ok(all_space) => all_space.into_iter().map(|space| space.id).collect(),
+                    Ok(all_space) => all_space
+                        .into_iter()
+                        .filter(|space| !space.archived)
+                        .map(|space| space.id)
+                        .collect(),
                     _ => vec![],
                 },
                 _ => vec![],
@@ -37,6 +64,140 @@ async fn push_status() {
         .await;
 }
 
+async fn send_scheduled() {
+    IntervalStream::new(interval(Duration::from_secs(5)))
+        .for_each(|_| async {
+            loop {
+                match send_next_scheduled().await {
+                    Ok(true) => continue,
+                    Ok(false) => break,
+                    Err(e) => {
+                        log::error!("Failed to send a scheduled message: {}", e);
+                        break;
+                    }
+                }
+            }
+        })
+        .await;
+}
+
+/// Sends the earliest due scheduled message, returns false if there is none.
+///
+/// The scheduled message is locked, removed and sent in one transaction, so it is sent
+/// exactly once even if the server restarts halfway or several servers are running.
+/// If sending fails, it is put off so that the messages due after it are sent meanwhile.
+async fn send_next_scheduled() -> Result<bool, AppError> {
+    let mut conn = database::get().await?;
+    let mut trans = conn.transaction().await?;
+    let scheduled = match ScheduledMessage::take_due(&mut trans).await? {
+        Some(scheduled) => scheduled,
+        None => return Ok(false),
+    };
+    let id = scheduled.id;
+    if let Err(e) = send_scheduled_message(trans, scheduled).await {
+        let attempts = ScheduledMessage::mark_failed(&mut *conn, &id)
+            .await?
+            .unwrap_or_default();
+        log::warn!("Failed to send scheduled message {} (attempt {}): {}", id, attempts, e);
+    }
+    Ok(true)
+}
+
+async fn send_scheduled_message(mut trans: Transaction<'_>, scheduled: ScheduledMessage) -> Result<(), AppError> {
+    let db = &mut trans;
+    ScheduledMessage::delete(db, &scheduled.id).await?;
+    let channel_id = scheduled.channel_id;
+    let sender_id = scheduled.sender_id;
+    let whisper_to_users = scheduled.whisper_to_users.clone();
+    let member = ChannelMember::get_with_space_member(db, &sender_id, &channel_id).await?;
+    let mut needed = Permissions::SEND_MESSAGES;
+    if whisper_to_users.is_some() {
+        needed = needed | Permissions::WHISPER;
+    }
+    let permissions = Permissions::channel(db, &sender_id, &channel_id).await?;
+    let (channel_member, space_member) = match member {
+        Some(member) if permissions.map_or(false, |permissions| permissions.contains(needed)) => member,
+        _ => {
+            log::info!(
+                "Dropped scheduled message {}: the sender can't send to the channel",
+                scheduled.id
+            );
+            trans.commit().await?;
+            return Ok(());
+        }
+    };
+    match Channel::ensure_active(db, &channel_id).await {
+        Ok(()) => (),
+        Err(AppError::Archived(_)) => {
+            log::info!("Dropped scheduled message {}: the channel is archived", scheduled.id);
+            trans.commit().await?;
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    }
+    let default_name = match scheduled.character_id.as_ref() {
+        Some(character_id) => ChannelCharacter::get(db, character_id)
+            .await?
+            .map(|character| character.name),
+        None => None,
+    }
+    .unwrap_or_else(|| channel_member.character_name.clone());
+    // Scheduled before entities were checked, those not fitting are dropped rather than the message.
+    let entities = retain_entities(&scheduled.text, scheduled.entities());
+    let mut cache = cache::conn().await;
+    let created = Message::create(
+        db,
+        &mut cache,
+        None,
+        &channel_id,
+        &sender_id,
+        &*default_name,
+        &*scheduled.name,
+        &*scheduled.text,
+        entities,
+        scheduled.in_game,
+        scheduled.is_action,
+        channel_member.is_master,
+        whisper_to_users.clone(),
+        scheduled.media_id,
+        None,
+        None,
+        scheduled.character_id,
+        scheduled.tags,
+    )
+    .await;
+    let message = match created {
+        Ok(message) => message,
+        Err(e @ (AppError::Validation(_) | AppError::Entity(_))) => {
+            log::warn!("Dropped scheduled message {}: {}", scheduled.id, e);
+            trans.commit().await?;
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+    let mentioned = ChannelMember::resolve_mentions(db, &message).await?;
+    ReadMarker::count_message(db, &message, whisper_to_users.as_ref(), &*mentioned).await?;
+    let mut notifications = Vec::new();
+    let payload = serde_json::json!({
+        "messageId": message.id,
+        "senderId": message.sender_id,
+        "name": message.name,
+        "text": scheduled.text,
+    });
+    for user_id in &mentioned {
+        let kind = DbEventType::Mentioned;
+        let space_id = Some(&space_member.space_id);
+        notifications.push(DbEvent::create(db, kind, user_id, space_id, Some(&channel_id), payload.clone()).await?);
+    }
+    trans.commit().await?;
+    if whisper_to_users.is_none() {
+        Stats::invalidate(&mut cache, &space_member.space_id, &channel_id).await?;
+    }
+    Event::new_message(space_member.space_id, message);
+    notifications.into_iter().for_each(Event::notify);
+    Ok(())
+}
+
 async fn events_clean() {
     IntervalStream::new(interval(Duration::from_secs(60 * 60 * 2)))
         .for_each(|_| async {
@@ -108,6 +269,10 @@ async fn broadcast_clean() {
             let mut broadcast_table = get_broadcast_table().write().await;
             broadcast_table.retain(|_, v| v.receiver_count() != 0);
             drop(broadcast_table);
+            let now = timestamp();
+            let mut audience_map = get_audience_map().lock().unwrap();
+            audience_map.retain(|_, (resolved, _)| now - *resolved < AUDIENCE_TTL);
+            drop(audience_map);
             log::trace!("clean finished");
         })
         .await;
@@ -1,6 +1,9 @@
 pub mod api;
+mod entities;
 mod handlers;
 mod models;
 
+pub use entities::{retain_entities, EntityError};
 pub use handlers::router;
-pub use models::Message;
+pub use models::check_pos;
+pub use models::{Message, ReactionCount, ScheduledMessage, SystemEvent};
@@ -1,4 +1,7 @@
-use serde::Deserialize;
+use super::models::ReactionCount;
+use super::Message;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use uuid::Uuid;
 
@@ -15,6 +18,12 @@ pub struct NewMessage {
     pub media_id: Option<Uuid>,
     pub whisper_to_users: Option<Vec<Uuid>>,
     pub pos: Option<f64>,
+    /// The message being replied to, in the same channel.
+    pub reply_to: Option<Uuid>,
+    /// Speak as a character of the roster, whose name is used if `name` is blank.
+    pub character_id: Option<Uuid>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -27,6 +36,43 @@ pub struct Edit {
     pub in_game: Option<bool>,
     pub is_action: Option<bool>,
     pub media_id: Option<Uuid>,
+    /// Replaces all tags of the message.
+    pub tags: Option<Vec<String>>,
+}
+
+/// A message to be sent at `send_at`. A blank `name` is replaced by the name at the time of sending.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    pub channel_id: Uuid,
+    #[serde(default)]
+    pub name: String,
+    pub text: String,
+    pub entities: Vec<JsonValue>,
+    pub in_game: bool,
+    pub is_action: bool,
+    pub media_id: Option<Uuid>,
+    pub whisper_to_users: Option<Vec<Uuid>>,
+    pub character_id: Option<Uuid>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(with = "crate::date_format")]
+    pub send_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EditScheduled {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub text: Option<String>,
+    pub entities: Option<Vec<JsonValue>>,
+    pub in_game: Option<bool>,
+    pub is_action: Option<bool>,
+    pub tags: Option<Vec<String>>,
+    #[serde(with = "crate::date_format::option")]
+    #[serde(default)]
+    pub send_at: Option<NaiveDateTime>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -44,10 +90,56 @@ pub struct MoveBetween {
     pub channel_id: Uuid,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE", tag = "type")]
+pub enum BulkAction {
+    Delete,
+    #[serde(rename_all = "camelCase")]
+    Fold {
+        folded: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    SetInGame {
+        in_game: bool,
+    },
+    /// Appends the messages to the end of another channel of the space.
+    #[serde(rename_all = "camelCase")]
+    Move {
+        channel_id: Uuid,
+    },
+}
+
+/// An action on messages of a channel, selected by `message_ids` or by an inclusive `pos` range.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Bulk {
+    pub channel_id: Uuid,
+    pub message_ids: Option<Vec<Uuid>>,
+    pub range: Option<(f64, f64)>,
+    pub action: BulkAction,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ByChannel {
     pub channel_id: Uuid,
     pub before: Option<f64>,
     pub limit: Option<i32>,
+    /// Only messages with this tag, without the leading `#`.
+    pub tag: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct React {
+    pub message_id: Uuid,
+    pub emoji: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageWithReactions {
+    #[serde(flatten)]
+    pub message: Message,
+    pub reactions: Vec<ReactionCount>,
 }
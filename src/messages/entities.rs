@@ -0,0 +1,326 @@
+//! The schema of `messages.entities`, spans of the text rendered specially.
+//!
+//! Offsets and lengths count UTF-16 code units, as clients do. Entities are checked
+//! and normalized when a message is sent or edited; rows written before are served as is.
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+use uuid::Uuid;
+
+pub const MAX_ENTITIES: usize = 1024;
+
+pub const MAX_DICE_FACE: u32 = 65536;
+
+pub const MAX_DICE_COUNTER: u32 = 128;
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum Entity {
+    Text {
+        start: usize,
+        len: usize,
+    },
+    Strong {
+        start: usize,
+        len: usize,
+    },
+    Emphasis {
+        start: usize,
+        len: usize,
+    },
+    Code {
+        start: usize,
+        len: usize,
+    },
+    Link {
+        start: usize,
+        len: usize,
+        href: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    /// A user or a character of the channel, matched by the `mentions` function of the schema.
+    #[serde(rename_all = "camelCase")]
+    Mention {
+        start: usize,
+        len: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        user_id: Option<Uuid>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        character_name: Option<String>,
+    },
+    /// A dice expression, evaluated by the sender.
+    Expr {
+        start: usize,
+        len: usize,
+        node: ExprNode,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    #[serde(rename = "+")]
+    Add,
+    #[serde(rename = "-")]
+    Sub,
+    #[serde(rename = "*")]
+    Mul,
+    #[serde(rename = "/")]
+    Div,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum ExprNode {
+    Num {
+        value: f64,
+    },
+    /// `counter` dice of `face` faces, with `values` once rolled.
+    Roll {
+        face: u32,
+        #[serde(default = "one")]
+        counter: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        values: Option<Vec<i64>>,
+    },
+    FateRoll {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        values: Option<Vec<i64>>,
+    },
+    Binary {
+        l: Box<ExprNode>,
+        op: BinaryOp,
+        r: Box<ExprNode>,
+    },
+    Max {
+        node: Box<ExprNode>,
+    },
+    Min {
+        node: Box<ExprNode>,
+    },
+    SubExpr {
+        node: Box<ExprNode>,
+    },
+}
+
+impl ExprNode {
+    fn is_valid(&self) -> bool {
+        use ExprNode::*;
+        match self {
+            Num { value } => value.is_finite(),
+            Roll { face, counter, values } => {
+                (1..=MAX_DICE_FACE).contains(face)
+                    && (1..=MAX_DICE_COUNTER).contains(counter)
+                    && values.as_ref().is_none_or(|values| {
+                        values.len() == *counter as usize
+                            && values.iter().all(|value| (1..=*face as i64).contains(value))
+                    })
+            }
+            FateRoll { values } => values
+                .as_ref()
+                .is_none_or(|values| values.len() == 4 && values.iter().all(|value| (-1..=1).contains(value))),
+            Binary { l, r, .. } => l.is_valid() && r.is_valid(),
+            Max { node } | Min { node } | SubExpr { node } => node.is_valid(),
+        }
+    }
+}
+
+/// Serialized into the context of the error response, as `{ "kind": "OutOfRange", "index": 0, ... }`.
+#[derive(Error, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind")]
+pub enum EntityError {
+    #[error("A message shall not have more than {} entities.", MAX_ENTITIES)]
+    TooMany,
+    #[error("Entity {index} is malformed: {reason}")]
+    Malformed { index: usize, reason: String },
+    #[error("Entity {index} is empty.")]
+    Empty { index: usize },
+    #[error("Entity {index} spans {start}..{end}, beyond the text of length {len}.")]
+    OutOfRange {
+        index: usize,
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+    #[error("Entity {index} splits a character at offset {offset}.")]
+    SplitsCharacter { index: usize, offset: usize },
+    #[error("Entity {index} links to an unsupported URL, only http and https are allowed.")]
+    UnsupportedLink { index: usize },
+    #[error("Entity {index} mentions neither a user nor a character.")]
+    EmptyMention { index: usize },
+    #[error("Entity {index} has an illegal dice expression.")]
+    IllegalExpr { index: usize },
+}
+
+impl Entity {
+    fn span(&self) -> (usize, usize) {
+        use Entity::*;
+        match *self {
+            Text { start, len }
+            | Strong { start, len }
+            | Emphasis { start, len }
+            | Code { start, len }
+            | Link { start, len, .. }
+            | Mention { start, len, .. }
+            | Expr { start, len, .. } => (start, len),
+        }
+    }
+
+    fn check(&self, index: usize) -> Result<(), EntityError> {
+        match self {
+            Entity::Link { href, .. } => {
+                let href = href.to_ascii_lowercase();
+                if !href.starts_with("http://") && !href.starts_with("https://") {
+                    return Err(EntityError::UnsupportedLink { index });
+                }
+            }
+            Entity::Mention {
+                user_id: None,
+                character_name: None,
+                ..
+            } => return Err(EntityError::EmptyMention { index }),
+            Entity::Expr { node, .. } if !node.is_valid() => return Err(EntityError::IllegalExpr { index }),
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+/// `boundaries[i]` is true if offset `i` of the text doesn't fall inside a surrogate pair.
+fn boundaries(text: &str) -> Vec<bool> {
+    let mut boundaries = Vec::with_capacity(text.len() + 1);
+    for c in text.chars() {
+        boundaries.push(true);
+        if c.len_utf16() == 2 {
+            boundaries.push(false);
+        }
+    }
+    boundaries.push(true);
+    boundaries
+}
+
+fn check_entity(index: usize, entity: JsonValue, boundaries: &[bool]) -> Result<JsonValue, EntityError> {
+    let text_len = boundaries.len() - 1;
+    let entity: Entity = serde_json::from_value(entity).map_err(|e| EntityError::Malformed {
+        index,
+        reason: e.to_string(),
+    })?;
+    let (start, len) = entity.span();
+    if len == 0 {
+        return Err(EntityError::Empty { index });
+    }
+    let end = start.saturating_add(len);
+    if end > text_len {
+        return Err(EntityError::OutOfRange {
+            index,
+            start,
+            end,
+            len: text_len,
+        });
+    }
+    for offset in [start, end] {
+        if !boundaries[offset] {
+            return Err(EntityError::SplitsCharacter { index, offset });
+        }
+    }
+    entity.check(index)?;
+    Ok(serde_json::json!(entity))
+}
+
+/// Parses entities of `text` and checks their spans, in the form to be stored.
+pub fn check_entities(text: &str, entities: Vec<JsonValue>) -> Result<JsonValue, EntityError> {
+    if entities.len() > MAX_ENTITIES {
+        return Err(EntityError::TooMany);
+    }
+    let boundaries = boundaries(text);
+    let checked = entities
+        .into_iter()
+        .enumerate()
+        .map(|(index, entity)| check_entity(index, entity, &boundaries))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(JsonValue::Array(checked))
+}
+
+/// Keeps the stored entities which still fit `text` and drops the others, so that
+/// messages saved before entities were checked can be edited.
+pub fn retain_entities(text: &str, entities: Vec<JsonValue>) -> Vec<JsonValue> {
+    let boundaries = boundaries(text);
+    entities
+        .into_iter()
+        .take(MAX_ENTITIES)
+        .enumerate()
+        .filter_map(|(index, entity)| check_entity(index, entity, &boundaries).ok())
+        .collect()
+}
+
+#[test]
+fn check_entities_test() {
+    use serde_json::json;
+
+    let text = "hi @Alice 1d20 😀";
+    let entities = vec![
+        json!({ "type": "Strong", "start": 0, "len": 2 }),
+        json!({ "type": "Mention", "start": 3, "len": 6, "characterName": "Alice", "unknown": true }),
+        json!({ "type": "Expr", "start": 10, "len": 4, "node": { "type": "Roll", "face": 20, "values": [7] } }),
+        json!({ "type": "Text", "start": 15, "len": 2 }),
+    ];
+    let checked = check_entities(text, entities).unwrap();
+    assert_eq!(
+        checked[1],
+        json!({ "type": "Mention", "start": 3, "len": 6, "characterName": "Alice" })
+    );
+    assert_eq!(checked[2]["node"]["counter"], 1);
+
+    let check = |entity: JsonValue| check_entities(text, vec![entity]);
+    assert_eq!(
+        check(json!({ "type": "Text", "start": 10, "len": 8 })),
+        Err(EntityError::OutOfRange {
+            index: 0,
+            start: 10,
+            end: 18,
+            len: 17
+        })
+    );
+    assert_eq!(
+        check(json!({ "type": "Text", "start": 15, "len": 1 })),
+        Err(EntityError::SplitsCharacter { index: 0, offset: 16 })
+    );
+    assert_eq!(
+        check(json!({ "type": "Text", "start": 0, "len": 0 })),
+        Err(EntityError::Empty { index: 0 })
+    );
+    assert_eq!(
+        check(json!({ "type": "Link", "start": 0, "len": 2, "href": "javascript:alert(1)" })),
+        Err(EntityError::UnsupportedLink { index: 0 })
+    );
+    assert_eq!(
+        check(json!({ "type": "Mention", "start": 0, "len": 2 })),
+        Err(EntityError::EmptyMention { index: 0 })
+    );
+    assert_eq!(
+        check(json!({ "type": "Expr", "start": 0, "len": 2, "node": { "type": "Roll", "face": 0 } })),
+        Err(EntityError::IllegalExpr { index: 0 })
+    );
+    assert!(matches!(
+        check(json!({ "type": "Blink", "start": 0, "len": 2 })),
+        Err(EntityError::Malformed { index: 0, .. })
+    ));
+    assert!(matches!(
+        check(json!({ "type": "Text", "start": -1, "len": 2 })),
+        Err(EntityError::Malformed { index: 0, .. })
+    ));
+
+    let legacy = vec![
+        json!({ "type": "Strong", "start": 0, "len": 2 }),
+        json!({ "type": "Text", "start": 10, "len": 8 }),
+        json!({ "type": "Blink", "start": 0, "len": 2 }),
+    ];
+    assert_eq!(
+        retain_entities(text, legacy),
+        vec![json!({ "type": "Strong", "start": 0, "len": 2 })]
+    );
+}
@@ -1,14 +1,20 @@
-use super::api::{Edit, NewMessage};
+use super::api::{Bulk, BulkAction, Edit, EditScheduled, MessageWithReactions, NewMessage, React, Schedule};
+use super::models::{Reaction, ReactionCount, ScheduledMessage, TagCount, MAX_SCHEDULED};
 use super::Message;
+use crate::audit::{AuditAction, AuditLog};
+use crate::channels::models::{ChannelCharacter, ReadMarker};
 use crate::channels::{Channel, ChannelMember};
 use crate::csrf::authenticate;
 use crate::error::{AppError, Find};
-use crate::events::Event;
+use crate::events::{DbEvent, DbEventType, Event};
 use crate::interface::{missing, ok_response, parse_query, Response};
 use crate::messages::api::{ByChannel, MoveBetween};
-use crate::spaces::SpaceMember;
+use crate::spaces::permissions::channel_permission;
+use crate::spaces::{Permissions, SpaceMember};
+use crate::stats::Stats;
 use crate::{database, interface};
 use hyper::{Body, Request};
+use uuid::Uuid;
 
 async fn send(req: Request<Body>) -> Result<Message, AppError> {
     let session = authenticate(&req).await?;
@@ -23,12 +29,49 @@ async fn send(req: Request<Body>) -> Result<Message, AppError> {
         media_id,
         whisper_to_users,
         pos: request_pos,
+        reply_to,
+        character_id,
+        tags,
     } = interface::parse_body(req).await?;
     let mut conn = database::get().await?;
-    let db = &mut *conn;
+    // The message, the unread counters and the notifications are saved together.
+    let mut trans = conn.transaction().await?;
+    let db = &mut trans;
     let (channel_member, space_member) = ChannelMember::get_with_space_member(db, &session.user_id, &channel_id)
         .await
         .or_no_permission()?;
+    let mut needed = Permissions::SEND_MESSAGES;
+    if whisper_to_users.is_some() {
+        needed = needed | Permissions::WHISPER;
+    }
+    if media_id.is_some() {
+        needed = needed | Permissions::UPLOAD_MEDIA;
+    }
+    channel_permission(db, &session.user_id, &channel_id, needed).await?;
+    Channel::ensure_active(db, &channel_id).await?;
+    let replied = if let Some(reply_to) = reply_to.as_ref() {
+        let parent = Message::get(db, reply_to, Some(&session.user_id))
+            .await?
+            .or_not_found()?;
+        if parent.channel_id != channel_id {
+            return Err(AppError::BadRequest(format!(
+                "Can only reply to a message in the same channel"
+            )));
+        }
+        Some(parent)
+    } else {
+        None
+    };
+    let default_name = match character_id.as_ref() {
+        Some(character_id) => {
+            ChannelCharacter::get(db, character_id)
+                .await?
+                .filter(|character| character.user_id == session.user_id && character.channel_id == channel_id)
+                .ok_or_else(|| AppError::BadRequest(format!("No such character of yours in the channel")))?
+                .name
+        }
+        None => channel_member.character_name.clone(),
+    };
     let mut cache = crate::cache::conn().await;
     let message = Message::create(
         db,
@@ -36,22 +79,178 @@ async fn send(req: Request<Body>) -> Result<Message, AppError> {
         message_id.as_ref(),
         &channel_id,
         &session.user_id,
-        &*channel_member.character_name,
+        &*default_name,
         &*name,
         &*text,
         entities,
         in_game,
         is_action,
         channel_member.is_master,
-        whisper_to_users,
+        whisper_to_users.clone(),
         media_id,
         request_pos,
+        reply_to,
+        character_id,
+        tags,
     )
     .await?;
+    let mentioned = ChannelMember::resolve_mentions(db, &message).await?;
+    ReadMarker::count_message(db, &message, whisper_to_users.as_ref(), &*mentioned).await?;
+    let mut notifications = Vec::new();
+    let payload = serde_json::json!({
+        "messageId": message.id,
+        "senderId": message.sender_id,
+        "name": message.name,
+        "text": text,
+    });
+    for user_id in &mentioned {
+        let kind = DbEventType::Mentioned;
+        let space_id = Some(&space_member.space_id);
+        notifications.push(DbEvent::create(db, kind, user_id, space_id, Some(&channel_id), payload.clone()).await?);
+    }
+    if let Some(parent) = replied {
+        let receiver = parent.sender_id;
+        let can_see = match whisper_to_users.as_ref() {
+            Some(users) => users.contains(&receiver),
+            None => true,
+        };
+        if receiver != session.user_id && can_see && !mentioned.contains(&receiver) {
+            let kind = DbEventType::Replied;
+            let space_id = Some(&space_member.space_id);
+            notifications.push(DbEvent::create(db, kind, &receiver, space_id, Some(&channel_id), payload).await?);
+        }
+    }
+    if !channel_member.is_master {
+        // Claimed only once the message is saved, a failed send doesn't hold the slot.
+        let channel = Channel::get_by_id(db, &channel_id).await.or_not_found()?;
+        channel.check_slow_mode(&mut cache, &session.user_id).await?;
+    }
+    trans.commit().await?;
+    if whisper_to_users.is_none() {
+        Stats::invalidate(&mut cache, &space_member.space_id, &channel_id).await?;
+    }
     Event::new_message(space_member.space_id, message.clone());
+    notifications.into_iter().for_each(Event::notify);
     Ok(message)
 }
 
+async fn schedule(req: Request<Body>) -> Result<ScheduledMessage, AppError> {
+    let session = authenticate(&req).await?;
+    let Schedule {
+        channel_id,
+        name,
+        text,
+        entities,
+        in_game,
+        is_action,
+        media_id,
+        whisper_to_users,
+        character_id,
+        tags,
+        send_at,
+    } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    ChannelMember::get(db, &session.user_id, &channel_id)
+        .await
+        .or_no_permission()?;
+    let mut needed = Permissions::SEND_MESSAGES;
+    if whisper_to_users.is_some() {
+        needed = needed | Permissions::WHISPER;
+    }
+    if media_id.is_some() {
+        needed = needed | Permissions::UPLOAD_MEDIA;
+    }
+    channel_permission(db, &session.user_id, &channel_id, needed).await?;
+    Channel::ensure_active(db, &channel_id).await?;
+    if let Some(character_id) = character_id.as_ref() {
+        ChannelCharacter::get(db, character_id)
+            .await?
+            .filter(|character| character.user_id == session.user_id && character.channel_id == channel_id)
+            .ok_or_else(|| AppError::BadRequest(format!("No such character of yours in the channel")))?;
+    }
+    let pending = ScheduledMessage::get_by_channel(db, &channel_id, &session.user_id).await?;
+    if pending.len() >= MAX_SCHEDULED {
+        return Err(AppError::BadRequest(format!(
+            "Too many scheduled messages in the channel"
+        )));
+    }
+    let scheduled = ScheduledMessage::create(
+        db,
+        &channel_id,
+        &session.user_id,
+        &*name,
+        &*text,
+        entities,
+        in_game,
+        is_action,
+        media_id,
+        whisper_to_users,
+        character_id,
+        tags,
+        send_at,
+    )
+    .await?;
+    Ok(scheduled)
+}
+
+/// Pending scheduled messages of the user in a channel.
+async fn scheduled(req: Request<Body>) -> Result<Vec<ScheduledMessage>, AppError> {
+    let session = authenticate(&req).await?;
+    let interface::IdQuery { id } = interface::parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    ScheduledMessage::get_by_channel(db, &id, &session.user_id)
+        .await
+        .map_err(Into::into)
+}
+
+async fn edit_scheduled(req: Request<Body>) -> Result<ScheduledMessage, AppError> {
+    let session = authenticate(&req).await?;
+    let EditScheduled {
+        id,
+        name,
+        text,
+        entities,
+        in_game,
+        is_action,
+        tags,
+        send_at,
+    } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let scheduled = ScheduledMessage::get(db, &id).await.or_not_found()?;
+    if scheduled.sender_id != session.user_id {
+        return Err(AppError::NoPermission(format!("user id dismatch")));
+    }
+    Channel::ensure_active(db, &scheduled.channel_id).await?;
+    ScheduledMessage::edit(
+        db,
+        &id,
+        name.as_deref(),
+        text.as_deref(),
+        entities,
+        in_game,
+        is_action,
+        tags,
+        send_at,
+    )
+    .await?
+    .ok_or(AppError::NotFound("scheduled message"))
+}
+
+async fn cancel_scheduled(req: Request<Body>) -> Result<bool, AppError> {
+    let session = authenticate(&req).await?;
+    let interface::IdQuery { id } = interface::parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let scheduled = ScheduledMessage::get(db, &id).await.or_not_found()?;
+    if scheduled.sender_id != session.user_id {
+        return Err(AppError::NoPermission(format!("user id dismatch")));
+    }
+    Ok(ScheduledMessage::delete(db, &id).await? > 0)
+}
+
 async fn edit(req: Request<Body>) -> Result<Message, AppError> {
     let session = authenticate(&req).await?;
     let Edit {
@@ -62,6 +261,7 @@ async fn edit(req: Request<Body>) -> Result<Message, AppError> {
         in_game,
         is_action,
         media_id,
+        tags,
     } = interface::parse_body(req).await?;
     let mut db = database::get().await?;
     let mut trans = db.transaction().await?;
@@ -76,7 +276,17 @@ async fn edit(req: Request<Body>) -> Result<Message, AppError> {
     if !channel.is_document && message.sender_id != session.user_id {
         return Err(AppError::NoPermission(format!("user id dismatch")));
     }
-    if name.is_some() || text.is_some() || entities.is_some() || in_game.is_some() || is_action.is_some() {
+    Channel::ensure_active(db, &channel.id).await?;
+    if media_id.is_some() {
+        channel_permission(db, &session.user_id, &message.channel_id, Permissions::UPLOAD_MEDIA).await?;
+    }
+    if name.is_some()
+        || text.is_some()
+        || entities.is_some()
+        || in_game.is_some()
+        || is_action.is_some()
+        || tags.is_some()
+    {
         let text = text.as_deref();
         let name = name.as_deref();
         message = Message::edit(
@@ -89,6 +299,7 @@ async fn edit(req: Request<Body>) -> Result<Message, AppError> {
             is_action,
             None,
             media_id,
+            tags,
         )
         .await?
         .ok_or_else(|| unexpected!("The message had been delete."))?;
@@ -113,16 +324,18 @@ async fn move_between(req: Request<Body>) -> Result<bool, AppError> {
         .await
         .or_not_found()?;
     let channel = Channel::get_by_id(db, &message.channel_id).await.or_not_found()?;
-    let channel_member = ChannelMember::get(db, &session.user_id, &message.channel_id)
+    let permissions = channel_permission(db, &session.user_id, &message.channel_id, Permissions::NONE).await?;
+    ChannelMember::get(db, &session.user_id, &message.channel_id)
         .await
         .or_no_permission()?;
     if !channel.is_document {
-        if !channel_member.is_master && message.sender_id != session.user_id {
+        if !permissions.contains(Permissions::MASTER) && message.sender_id != session.user_id {
             return Err(AppError::NoPermission(format!(
                 "Only the master can move other's messages."
             )));
         }
     }
+    Channel::ensure_active(db, &channel.id).await?;
 
     let message = match range {
         (None, None) => return Err(AppError::BadRequest("a and b cannot both be null".to_string())),
@@ -146,12 +359,132 @@ async fn move_between(req: Request<Body>) -> Result<bool, AppError> {
     Ok(true)
 }
 
-async fn query(req: Request<Body>) -> Result<Message, AppError> {
+/// Applies an action to many messages of a channel in one transaction, returning the ids of them.
+async fn bulk(req: Request<Body>) -> Result<Vec<Uuid>, AppError> {
+    let session = authenticate(&req).await?;
+    let Bulk {
+        channel_id,
+        message_ids,
+        range,
+        action,
+    } = interface::parse_body(req).await?;
+    if message_ids.is_none() && range.is_none() {
+        return Err(AppError::BadRequest(format!("Select messages by ids or by a range")));
+    }
+    let mut conn = database::get().await?;
+    let mut trans = conn.transaction().await?;
+    let db = &mut trans;
+    let channel = Channel::get_by_id(db, &channel_id).await.or_not_found()?;
+    let permissions = channel_permission(db, &session.user_id, &channel_id, Permissions::NONE).await?;
+    ChannelMember::get(db, &session.user_id, &channel_id)
+        .await
+        .or_no_permission()?;
+    Channel::ensure_active(db, &channel_id).await?;
+    let messages = Message::select_bulk(db, &channel_id, &session.user_id, message_ids.as_deref(), range).await?;
+    if let Some(ids) = message_ids.as_ref() {
+        if !ids.iter().all(|id| messages.iter().any(|message| message.id == *id)) {
+            return Err(AppError::NotFound("messages"));
+        }
+    }
+    if messages.is_empty() {
+        return Ok(Vec::new());
+    }
+    let allowed = match action {
+        BulkAction::Delete => permissions.contains(Permissions::MODERATE_MESSAGES),
+        _ if channel.is_document => true,
+        BulkAction::SetInGame { .. } => false,
+        BulkAction::Fold { .. } | BulkAction::Move { .. } => permissions.contains(Permissions::MASTER),
+    };
+    if !allowed && messages.iter().any(|message| message.sender_id != session.user_id) {
+        return Err(AppError::NoPermission(format!(
+            "Some of the messages are sent by others"
+        )));
+    }
+    let ids: Vec<Uuid> = messages.iter().map(|message| message.id).collect();
+    let space_id = channel.space_id;
+    let mut cache = crate::cache::conn().await;
+    match action {
+        BulkAction::Delete => {
+            Message::bulk_delete(db, &*ids).await?;
+            for message in messages.iter().filter(|message| message.sender_id != session.user_id) {
+                let payload =
+                    serde_json::json!({ "senderId": message.sender_id, "name": message.name, "text": message.text });
+                let action = AuditAction::MessageDeleted;
+                AuditLog::record(
+                    db,
+                    &space_id,
+                    &session.user_id,
+                    action,
+                    Some(&channel_id),
+                    Some(&message.id),
+                    payload,
+                )
+                .await?;
+            }
+            trans.commit().await?;
+            Event::messages_bulk_edited(space_id, channel_id, Vec::new(), ids.clone());
+        }
+        BulkAction::Fold { folded } => {
+            let edited = Message::bulk_edit(db, &*ids, Some(folded), None).await?;
+            for message in messages.iter().filter(|message| message.sender_id != session.user_id) {
+                let payload = serde_json::json!({ "senderId": message.sender_id, "folded": folded });
+                let action = AuditAction::MessageFolded;
+                AuditLog::record(
+                    db,
+                    &space_id,
+                    &session.user_id,
+                    action,
+                    Some(&channel_id),
+                    Some(&message.id),
+                    payload,
+                )
+                .await?;
+            }
+            trans.commit().await?;
+            Event::messages_bulk_edited(space_id, channel_id, edited, Vec::new());
+        }
+        BulkAction::SetInGame { in_game } => {
+            let edited = Message::bulk_edit(db, &*ids, None, Some(in_game)).await?;
+            trans.commit().await?;
+            Event::messages_bulk_edited(space_id, channel_id, edited, Vec::new());
+        }
+        BulkAction::Move { channel_id: target_id } => {
+            if target_id == channel_id {
+                return Err(AppError::BadRequest(format!("The messages are already in the channel")));
+            }
+            let target = Channel::get_by_id(db, &target_id).await.or_not_found()?;
+            if target.space_id != space_id {
+                return Err(AppError::BadRequest(format!(
+                    "Can only move to a channel of the same space"
+                )));
+            }
+            channel_permission(db, &session.user_id, &target_id, Permissions::SEND_MESSAGES).await?;
+            ChannelMember::get(db, &session.user_id, &target_id)
+                .await
+                .or_no_permission()?;
+            Channel::ensure_active(db, &target_id).await?;
+            let moved = Message::move_to_channel(db, &*ids, &target_id).await?;
+            trans.commit().await?;
+            crate::pos::reset_channel_pos(&mut cache, &target_id).await?;
+            Stats::invalidate(&mut cache, &space_id, &target_id).await?;
+            Event::messages_bulk_edited(space_id, channel_id, Vec::new(), ids.clone());
+            Event::messages_bulk_edited(space_id, target_id, moved, Vec::new());
+        }
+    }
+    Stats::invalidate(&mut cache, &space_id, &channel_id).await?;
+    Ok(ids)
+}
+
+async fn query(req: Request<Body>) -> Result<MessageWithReactions, AppError> {
     let interface::IdQuery { id } = interface::parse_query(req.uri())?;
     let mut conn = database::get().await?;
     let db = &mut *conn;
     let user_id = authenticate(&req).await.ok().map(|session| session.user_id);
-    Message::get(db, &id, user_id.as_ref()).await.or_not_found()
+    let message = Message::get(db, &id, user_id.as_ref()).await.or_not_found()?;
+    let channel = Channel::get_by_id(db, &message.channel_id).await.or_not_found()?;
+    channel.ensure_readable(db, user_id.as_ref()).await?;
+    let reactions = Reaction::get(db, &message.id).await?;
+    Ok(MessageWithReactions { message, reactions })
 }
 
 async fn delete(req: Request<Body>) -> Result<Message, AppError> {
@@ -163,10 +496,27 @@ async fn delete(req: Request<Body>) -> Result<Message, AppError> {
     let space_member = SpaceMember::get_by_channel(db, &session.user_id, &message.channel_id)
         .await
         .or_no_permission()?;
-    if !space_member.is_admin && message.sender_id != session.user_id {
+    let permissions = channel_permission(db, &session.user_id, &message.channel_id, Permissions::NONE).await?;
+    if !permissions.contains(Permissions::MODERATE_MESSAGES) && message.sender_id != session.user_id {
         return Err(AppError::NoPermission(format!("user id mismatch")));
     }
+    Channel::ensure_active(db, &message.channel_id).await?;
     Message::delete(db, &id).await?;
+    if message.sender_id != session.user_id {
+        let payload = serde_json::json!({ "senderId": message.sender_id, "name": message.name, "text": message.text });
+        let action = AuditAction::MessageDeleted;
+        let space_id = &space_member.space_id;
+        AuditLog::record(
+            db,
+            space_id,
+            &session.user_id,
+            action,
+            Some(&message.channel_id),
+            Some(&id),
+            payload,
+        )
+        .await?;
+    }
     Event::message_deleted(space_member.space_id, message.channel_id, message.id);
     Ok(message)
 }
@@ -178,27 +528,45 @@ async fn toggle_fold(req: Request<Body>) -> Result<Message, AppError> {
     let db = &mut *conn;
     let message = Message::get(db, &id, Some(&session.user_id)).await.or_not_found()?;
     let channel = Channel::get_by_id(db, &message.channel_id).await.or_not_found()?;
-    let channel_member = ChannelMember::get(db, &session.user_id, &message.channel_id)
+    let permissions = channel_permission(db, &session.user_id, &message.channel_id, Permissions::NONE).await?;
+    ChannelMember::get(db, &session.user_id, &message.channel_id)
         .await
         .or_no_permission()?;
     if !channel.is_document {
-        if message.sender_id != session.user_id && !channel_member.is_master {
+        if message.sender_id != session.user_id && !permissions.contains(Permissions::MASTER) {
             return Err(AppError::NoPermission(format!("user id dismatch")));
         }
     }
+    Channel::ensure_active(db, &channel.id).await?;
     let folded = Some(!message.folded);
-    let message = Message::edit(db, None, &message.id, None, None, None, None, folded, None)
+    let message = Message::edit(db, None, &message.id, None, None, None, None, folded, None, None)
         .await?
         .ok_or_else(|| unexpected!("message not found"))?;
+    if message.sender_id != session.user_id {
+        let payload = serde_json::json!({ "senderId": message.sender_id, "folded": message.folded });
+        let action = AuditAction::MessageFolded;
+        let channel_id = &message.channel_id;
+        AuditLog::record(
+            db,
+            &channel.space_id,
+            &session.user_id,
+            action,
+            Some(channel_id),
+            Some(&id),
+            payload,
+        )
+        .await?;
+    }
     Event::message_edited(channel.space_id, message.clone());
     Ok(message)
 }
 
-async fn by_channel(req: Request<Body>) -> Result<Vec<Message>, AppError> {
+async fn by_channel(req: Request<Body>) -> Result<Vec<MessageWithReactions>, AppError> {
     let ByChannel {
         channel_id,
         limit,
         before,
+        tag,
     } = parse_query(req.uri())?;
 
     let mut db = database::get().await?;
@@ -207,14 +575,102 @@ async fn by_channel(req: Request<Body>) -> Result<Vec<Message>, AppError> {
     let channel = Channel::get_by_id(db, &channel_id).await.or_not_found()?;
     if !channel.is_public {
         let session = authenticate(&req).await?;
-        ChannelMember::get(db, &session.user_id, &channel_id)
-            .await
-            .or_no_permission()?;
+        channel.ensure_readable(db, Some(&session.user_id)).await?;
     }
     let limit = limit.unwrap_or(128);
-    Message::get_by_channel(db, &channel_id, before, limit)
+    let tag = tag.as_deref().map(|tag| tag.trim_start_matches('#'));
+    let messages = Message::get_by_channel(db, &channel_id, before, limit, tag).await?;
+    let message_ids: Vec<Uuid> = messages.iter().map(|message| message.id).collect();
+    let mut reactions = Reaction::get_by_messages(db, &*message_ids).await?;
+    Ok(messages
+        .into_iter()
+        .map(|message| {
+            let reactions = reactions.remove(&message.id).unwrap_or_default();
+            MessageWithReactions { message, reactions }
+        })
+        .collect())
+}
+
+async fn pin(req: Request<Body>, pinned: bool) -> Result<Message, AppError> {
+    let session = authenticate(&req).await?;
+    let interface::IdQuery { id } = interface::parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let message = Message::get(db, &id, Some(&session.user_id)).await.or_not_found()?;
+    let channel = Channel::get_by_id(db, &message.channel_id).await.or_not_found()?;
+    channel_permission(db, &session.user_id, &channel.id, Permissions::MASTER).await?;
+    Channel::ensure_active(db, &channel.id).await?;
+    if message.pinned == pinned {
+        return Ok(message);
+    }
+    let message = Message::set_pinned(db, &id, pinned)
+        .await?
+        .ok_or_else(|| unexpected!("message not found"))?;
+    Event::message_edited(channel.space_id, message.clone());
+    Ok(message)
+}
+
+async fn pinned(req: Request<Body>) -> Result<Vec<Message>, AppError> {
+    let ByChannel {
+        channel_id,
+        limit,
+        before,
+        ..
+    } = parse_query(req.uri())?;
+
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+
+    let channel = Channel::get_by_id(db, &channel_id).await.or_not_found()?;
+    if !channel.is_public {
+        let session = authenticate(&req).await?;
+        channel.ensure_readable(db, Some(&session.user_id)).await?;
+    }
+    let limit = limit.unwrap_or(64);
+    let messages = Message::get_pinned(db, &channel_id, before, limit).await?;
+    Ok(messages)
+}
+
+async fn tags(req: Request<Body>) -> Result<Vec<TagCount>, AppError> {
+    let interface::IdQuery { id } = interface::parse_query(req.uri())?;
+    let session = authenticate(&req).await.ok();
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let channel = Channel::get_by_id(db, &id).await.or_not_found()?;
+    let user_id = session.as_ref().map(|session| &session.user_id);
+    channel.ensure_readable(db, user_id).await?;
+    Message::tags(db, &id).await.map_err(Into::into)
+}
+
+async fn react(req: Request<Body>, add: bool) -> Result<Vec<ReactionCount>, AppError> {
+    let session = authenticate(&req).await?;
+    let React { message_id, emoji } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let message = Message::get(db, &message_id, Some(&session.user_id))
         .await
-        .map_err(Into::into)
+        .or_not_found()?;
+    let (channel_member, space_member) =
+        ChannelMember::get_with_space_member(db, &session.user_id, &message.channel_id)
+            .await
+            .or_no_permission()?;
+    if let Some(whisper_to_users) = message.whisper_to_users.as_ref() {
+        if !channel_member.is_master && !whisper_to_users.contains(&session.user_id) {
+            return Err(AppError::NoPermission(format!("The message is a whisper to others")));
+        }
+    }
+    Channel::ensure_active(db, &message.channel_id).await?;
+    let emoji = emoji.trim();
+    let changed = if add {
+        Reaction::add(db, &message_id, &session.user_id, emoji).await?
+    } else {
+        Reaction::remove(db, &message_id, &session.user_id, emoji).await?
+    };
+    let reactions = Reaction::get(db, &message_id).await?;
+    if changed {
+        Event::reaction_changed(space_member.space_id, message.channel_id, message_id, reactions.clone());
+    }
+    Ok(reactions)
 }
 
 pub async fn router(req: Request<Body>, path: &str) -> Result<Response, AppError> {
@@ -228,6 +684,17 @@ pub async fn router(req: Request<Body>, path: &str) -> Result<Response, AppError
         ("/move_between", Method::POST) => move_between(req).await.map(ok_response),
         ("/toggle_fold", Method::POST) => toggle_fold(req).await.map(ok_response),
         ("/delete", Method::POST) => delete(req).await.map(ok_response),
+        ("/bulk", Method::POST) => bulk(req).await.map(ok_response),
+        ("/schedule", Method::POST) => schedule(req).await.map(ok_response),
+        ("/scheduled", Method::GET) => scheduled(req).await.map(ok_response),
+        ("/edit_scheduled", Method::POST) => edit_scheduled(req).await.map(ok_response),
+        ("/cancel_scheduled", Method::POST) => cancel_scheduled(req).await.map(ok_response),
+        ("/pin", Method::POST) => pin(req, true).await.map(ok_response),
+        ("/unpin", Method::POST) => pin(req, false).await.map(ok_response),
+        ("/pinned", Method::GET) => pinned(req).await.map(ok_response),
+        ("/tags", Method::GET) => tags(req).await.map(ok_response),
+        ("/react", Method::POST) => react(req, true).await.map(ok_response),
+        ("/unreact", Method::POST) => react(req, false).await.map(ok_response),
         _ => missing(),
     }
 }
@@ -1,13 +1,16 @@
 use chrono::naive::NaiveDateTime;
-use postgres_types::FromSql;
+use postgres_types::{FromSql, ToSql};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use uuid::Uuid;
 
+use super::entities::{check_entities, retain_entities};
+use crate::channels::Channel;
 use crate::database::Querist;
 use crate::error::{AppError, DbError, ModelError, ValidationFailed};
 use crate::utils::merge_blank;
-use crate::validators::CHARACTER_NAME;
+use crate::validators::{CHARACTER_NAME, TAG};
+use std::collections::HashMap;
 use tokio_postgres::error::SqlState;
 
 pub fn check_pos(pos: f64) -> Result<(), ValidationFailed> {
@@ -21,6 +24,49 @@ pub fn check_pos(pos: f64) -> Result<(), ValidationFailed> {
     Ok(())
 }
 
+pub const MAX_TAGS: usize = 32;
+
+/// The most messages a bulk action applies to.
+pub const MAX_BULK: usize = 1024;
+
+/// Trims tags and their leading `#`, dropping duplicates.
+pub fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>, ValidationFailed> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.trim();
+        let tag = tag.strip_prefix('#').unwrap_or(tag);
+        TAG.run(tag)?;
+        if !normalized.iter().any(|existing| existing == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+    if normalized.len() > MAX_TAGS {
+        return Err(ValidationFailed("Too many tags on a message."));
+    }
+    Ok(normalized)
+}
+
+/// A membership or role change posted into the timeline by the server.
+#[derive(Debug, Serialize, Deserialize, FromSql, ToSql, Clone, Copy, PartialEq, Eq)]
+#[postgres(name = "system_event")]
+pub enum SystemEvent {
+    Joined,
+    Left,
+    BecameMaster,
+    Renamed,
+}
+
+impl SystemEvent {
+    pub fn describe(self) -> &'static str {
+        match self {
+            SystemEvent::Joined => "joined the channel",
+            SystemEvent::Left => "left the channel",
+            SystemEvent::BecameMaster => "became a master",
+            SystemEvent::Renamed => "changed name",
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, FromSql, Clone)]
 #[serde(rename_all = "camelCase")]
 #[postgres(name = "messages")]
@@ -51,6 +97,9 @@ pub struct Message {
     pub order_date: NaiveDateTime,
     pub order_offset: i32,
     pub pos: f64,
+    pub system_event: Option<SystemEvent>,
+    /// The character of the roster the message was sent as.
+    pub character_id: Option<Uuid>,
 }
 
 impl Message {
@@ -76,11 +125,13 @@ impl Message {
         Ok(maybe_message)
     }
 
+    /// Messages before a position, only those tagged `tag` if given.
     pub async fn get_by_channel<T: Querist>(
         db: &mut T,
         channel_id: &Uuid,
         before: Option<f64>,
         limit: i32,
+        tag: Option<&str>,
     ) -> Result<Vec<Message>, ModelError> {
         use postgres_types::Type;
         if limit > 256 || limit < 1 {
@@ -89,6 +140,32 @@ impl Message {
         let rows = db
             .query_typed(
                 include_str!("sql/get_by_channel.sql"),
+                &[Type::UUID, Type::FLOAT8, Type::INT4, Type::TEXT],
+                &[channel_id, &before, &limit, &tag],
+            )
+            .await?;
+        let mut messages: Vec<Message> = vec![];
+        for row in rows {
+            messages.push(row.try_get(0)?);
+        }
+        messages.iter_mut().for_each(Message::hide);
+        Ok(messages)
+    }
+
+    /// Pinned messages of a channel, the latest first.
+    pub async fn get_pinned<T: Querist>(
+        db: &mut T,
+        channel_id: &Uuid,
+        before: Option<f64>,
+        limit: i32,
+    ) -> Result<Vec<Message>, ModelError> {
+        use postgres_types::Type;
+        if limit > 256 || limit < 1 {
+            return Err(ValidationFailed("illegal limit range").into());
+        }
+        let rows = db
+            .query_typed(
+                include_str!("sql/get_pinned.sql"),
                 &[Type::UUID, Type::FLOAT8, Type::INT4],
                 &[channel_id, &before, &limit],
             )
@@ -101,14 +178,39 @@ impl Message {
         Ok(messages)
     }
 
+    pub async fn set_pinned<T: Querist>(db: &mut T, id: &Uuid, pinned: bool) -> Result<Option<Message>, DbError> {
+        let row = db.query_one(include_str!("sql/set_pinned.sql"), &[id, &pinned]).await?;
+        if let Some(row) = row {
+            let mut message: Message = row.try_get(0)?;
+            message.hide();
+            Ok(Some(message))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Tags used by the visible messages of a channel, the most used first.
+    pub async fn tags<T: Querist>(db: &mut T, channel_id: &Uuid) -> Result<Vec<TagCount>, DbError> {
+        let rows = db.query(include_str!("sql/tag_counts.sql"), &[channel_id]).await?;
+        let mut tags = Vec::with_capacity(rows.len());
+        for row in rows {
+            tags.push(TagCount {
+                tag: row.try_get(0)?,
+                count: row.try_get(1)?,
+            });
+        }
+        Ok(tags)
+    }
+
     pub async fn export<T: Querist>(
         db: &mut T,
         channel_id: &Uuid,
         hide: bool,
         after: Option<NaiveDateTime>,
+        tag: Option<&str>,
     ) -> Result<Vec<Message>, DbError> {
         let rows = db
-            .query(include_str!("./sql/export.sql"), &[channel_id, &after])
+            .query(include_str!("./sql/export.sql"), &[channel_id, &after, &tag, &hide])
             .await?;
         let mut messages: Vec<Message> = vec![];
         for row in rows {
@@ -136,6 +238,9 @@ impl Message {
         whisper_to: Option<Vec<Uuid>>,
         media_id: Option<Uuid>,
         request_pos: Option<f64>,
+        reply_to: Option<Uuid>,
+        character_id: Option<Uuid>,
+        tags: Vec<String>,
     ) -> Result<Message, AppError> {
         use postgres_types::Type;
         let pos: f64 = match (request_pos, message_id) {
@@ -153,8 +258,11 @@ impl Message {
         if text.is_empty() {
             return Err(ValidationFailed("Text is empty.").into());
         }
-        let entities = JsonValue::Array(entities);
+        let tags = normalize_tags(tags)?;
+        let entities = check_entities(text, entities).map_err(ModelError::from)?;
         let source = include_str!("sql/create.sql");
+        // The insert is retried on conflicts, which would abort the transaction otherwise.
+        db.savepoint("create_message").await?;
         let types = &[
             Type::UUID,
             Type::UUID,
@@ -168,6 +276,9 @@ impl Message {
             Type::UUID_ARRAY,
             Type::UUID,
             Type::FLOAT8,
+            Type::UUID,
+            Type::UUID,
+            Type::TEXT_ARRAY,
         ];
         let mut row = db
             .query_exactly_one_typed(
@@ -186,9 +297,16 @@ impl Message {
                     &whisper_to,
                     &media_id,
                     &pos,
+                    &reply_to,
+                    &character_id,
+                    &tags,
                 ],
             )
             .await;
+        if row.is_err() {
+            db.rollback_to("create_message").await?;
+        }
+        db.release("create_message").await?;
         if let Err(err) = &row {
             if err.code() == Some(&SqlState::UNIQUE_VIOLATION) {
                 log::warn!(
@@ -240,6 +358,9 @@ impl Message {
                             &whisper_to,
                             &media_id,
                             &reset_pos,
+                            &reply_to,
+                            &character_id,
+                            &tags,
                         ],
                     )
                     .await;
@@ -251,6 +372,42 @@ impl Message {
         Ok(message)
     }
 
+    /// Posts a system message about `user_id` into the timeline, if the channel has it enabled.
+    pub async fn create_system<T: Querist>(
+        db: &mut T,
+        cache: &mut crate::cache::Connection,
+        channel: &Channel,
+        user_id: &Uuid,
+        name: &str,
+        event: SystemEvent,
+        text: &str,
+    ) -> Result<Option<Message>, AppError> {
+        if !channel.system_events.contains(&event) {
+            return Ok(None);
+        }
+        let pos = crate::pos::alloc_new_pos(db, cache, channel.id).await? as f64;
+        let source = include_str!("sql/create_system.sql");
+        db.savepoint("create_system_message").await?;
+        let mut row = db
+            .query_exactly_one(source, &[&channel.id, user_id, &name, &text, &pos, &event])
+            .await;
+        if row.is_err() {
+            db.rollback_to("create_system_message").await?;
+        }
+        db.release("create_system_message").await?;
+        if let Err(err) = &row {
+            if err.code() == Some(&SqlState::UNIQUE_VIOLATION) {
+                log::info!("conflict at position {}", pos);
+                crate::pos::reset_channel_pos(cache, &channel.id).await?;
+                let pos = crate::pos::alloc_new_pos(db, cache, channel.id).await? as f64;
+                row = db
+                    .query_exactly_one(source, &[&channel.id, user_id, &name, &text, &pos, &event])
+                    .await;
+            }
+        }
+        Ok(Some(row?.try_get(0)?))
+    }
+
     pub fn hide(&mut self) {
         if self.whisper_to_users.is_none() {
             return;
@@ -258,6 +415,7 @@ impl Message {
         self.seed = vec![0; 4];
         self.text = String::new();
         self.entities = JsonValue::Array(Vec::new());
+        self.tags = Vec::new();
     }
 
     pub async fn move_above<T: Querist>(
@@ -336,6 +494,21 @@ impl Message {
             Ok(None)
         }
     }
+    /// Copy the messages of document channels into the channels of the same name in another space,
+    /// secret channels only if `sender_id` is a member of them.
+    pub async fn copy_documents<T: Querist>(
+        db: &mut T,
+        from_space: &Uuid,
+        to_space: &Uuid,
+        sender_id: &Uuid,
+    ) -> Result<u64, DbError> {
+        db.execute(
+            include_str!("sql/copy_documents.sql"),
+            &[from_space, to_space, sender_id],
+        )
+        .await
+    }
+
     pub async fn max_pos<T: Querist>(db: &mut T, channel_id: &Uuid) -> f64 {
         db.query_exactly_one(include_str!("./sql/max_pos.sql"), &[channel_id])
             .await
@@ -352,8 +525,30 @@ impl Message {
         is_action: Option<bool>,
         folded: Option<bool>,
         media_id: Option<Uuid>,
+        tags: Option<Vec<String>>,
     ) -> Result<Option<Message>, ModelError> {
-        let entities = entities.map(JsonValue::Array);
+        let entities = match (text, entities) {
+            (None, None) => None,
+            (Some(text), Some(entities)) => Some(check_entities(text, entities)?),
+            (text, entities) => {
+                // The entities shall still fit the text if only one of them is edited.
+                let row = db.query_one(include_str!("sql/get_text.sql"), &[id]).await?;
+                let row = match row {
+                    Some(row) => row,
+                    None => return Ok(None),
+                };
+                let text: &str = text.unwrap_or(row.try_get(0)?);
+                match entities {
+                    Some(entities) => Some(check_entities(text, entities)?),
+                    // Stored entities may predate the checks, those not fitting are dropped.
+                    None => match row.try_get(1)? {
+                        JsonValue::Array(entities) => Some(JsonValue::Array(retain_entities(text, entities))),
+                        _ => Some(JsonValue::Array(Vec::new())),
+                    },
+                }
+            }
+        };
+        let tags = tags.map(normalize_tags).transpose()?;
         let name = name.map(merge_blank);
         if let Some(ref name) = name {
             CHARACTER_NAME.run(name)?;
@@ -361,7 +556,9 @@ impl Message {
         let row = db
             .query_one(
                 include_str!("sql/edit.sql"),
-                &[id, &name, &text, &entities, &in_game, &is_action, &folded, &media_id],
+                &[
+                    id, &name, &text, &entities, &in_game, &is_action, &folded, &media_id, &tags,
+                ],
             )
             .await?;
         if let Some(row) = row {
@@ -376,6 +573,399 @@ impl Message {
     pub async fn delete<T: Querist>(db: &mut T, id: &Uuid) -> Result<u64, DbError> {
         db.execute(include_str!("sql/delete.sql"), &[id]).await
     }
+
+    /// Copies messages from `from_pos` onward into another, empty channel.
+    pub async fn copy_range<T: Querist>(
+        db: &mut T,
+        from_channel: &Uuid,
+        to_channel: &Uuid,
+        from_pos: f64,
+    ) -> Result<Vec<Message>, ModelError> {
+        check_pos(from_pos)?;
+        let rows = db
+            .query(
+                include_str!("sql/copy_range.sql"),
+                &[from_channel, to_channel, &from_pos],
+            )
+            .await?;
+        Message::collect_range(rows)
+    }
+
+    /// Moves messages from `from_pos` onward into another, empty channel.
+    pub async fn move_range<T: Querist>(
+        db: &mut T,
+        from_channel: &Uuid,
+        to_channel: &Uuid,
+        from_pos: f64,
+    ) -> Result<Vec<Message>, ModelError> {
+        check_pos(from_pos)?;
+        let rows = db
+            .query(
+                include_str!("sql/move_range.sql"),
+                &[from_channel, to_channel, &from_pos],
+            )
+            .await?;
+        Message::collect_range(rows)
+    }
+
+    /// Moves all messages of `source` into `target`, interleaved by `created` or `order_date`.
+    /// Read markers of both channels are carried over to their renumbered positions in `target`.
+    pub async fn merge<T: Querist>(
+        db: &mut T,
+        source: &Uuid,
+        target: &Uuid,
+        by_order_date: bool,
+    ) -> Result<u64, DbError> {
+        db.execute(
+            include_str!("sql/merge_channels.sql"),
+            &[source, target, &by_order_date],
+        )
+        .await
+    }
+
+    /// Locks the messages selected by ids and/or an inclusive `pos` range,
+    /// hiding whispers that `user_id` can't see.
+    pub async fn select_bulk<T: Querist>(
+        db: &mut T,
+        channel_id: &Uuid,
+        user_id: &Uuid,
+        ids: Option<&[Uuid]>,
+        range: Option<(f64, f64)>,
+    ) -> Result<Vec<Message>, ModelError> {
+        let (start, end) = match range {
+            Some((a, b)) => {
+                check_pos(a)?;
+                check_pos(b)?;
+                (Some(a.min(b)), Some(a.max(b)))
+            }
+            None => (None, None),
+        };
+        let limit = MAX_BULK as i64 + 1;
+        let rows = db
+            .query(
+                include_str!("sql/select_bulk.sql"),
+                &[channel_id, &ids, &start, &end, user_id, &limit],
+            )
+            .await?;
+        if rows.len() > MAX_BULK {
+            return Err(ValidationFailed("Too many messages selected.").into());
+        }
+        let mut messages = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut message: Message = row.try_get(0)?;
+            let should_hide: Option<bool> = row.try_get(1)?;
+            if should_hide.unwrap_or(true) {
+                message.hide();
+            }
+            messages.push(message);
+        }
+        Ok(messages)
+    }
+
+    pub async fn bulk_delete<T: Querist>(db: &mut T, ids: &[Uuid]) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/bulk_delete.sql"), &[&ids]).await
+    }
+
+    pub async fn bulk_edit<T: Querist>(
+        db: &mut T,
+        ids: &[Uuid],
+        folded: Option<bool>,
+        in_game: Option<bool>,
+    ) -> Result<Vec<Message>, ModelError> {
+        let rows = db
+            .query(include_str!("sql/bulk_edit.sql"), &[&ids, &folded, &in_game])
+            .await?;
+        Message::collect_range(rows)
+    }
+
+    /// Appends messages to the end of another channel, keeping their order.
+    pub async fn move_to_channel<T: Querist>(
+        db: &mut T,
+        ids: &[Uuid],
+        to_channel: &Uuid,
+    ) -> Result<Vec<Message>, ModelError> {
+        let rows = db
+            .query(include_str!("sql/move_to_channel.sql"), &[&ids, to_channel])
+            .await?;
+        Message::collect_range(rows)
+    }
+
+    fn collect_range(rows: Vec<tokio_postgres::Row>) -> Result<Vec<Message>, ModelError> {
+        let mut messages = rows
+            .into_iter()
+            .map(|row| row.try_get(0))
+            .collect::<Result<Vec<Message>, _>>()?;
+        messages.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+        messages.iter_mut().for_each(Message::hide);
+        Ok(messages)
+    }
+}
+
+/// Users who reacted to a message with an emoji, in order of reaction.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: i64,
+    pub user_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
+}
+
+pub struct Reaction;
+
+impl Reaction {
+    /// Returns false if the user has already reacted with the emoji.
+    pub async fn add<T: Querist>(
+        db: &mut T,
+        message_id: &Uuid,
+        user_id: &Uuid,
+        emoji: &str,
+    ) -> Result<bool, ModelError> {
+        use crate::validators::EMOJI;
+        EMOJI.run(emoji)?;
+        let added = db
+            .execute(include_str!("sql/add_reaction.sql"), &[message_id, user_id, &emoji])
+            .await?;
+        Ok(added > 0)
+    }
+
+    pub async fn remove<T: Querist>(
+        db: &mut T,
+        message_id: &Uuid,
+        user_id: &Uuid,
+        emoji: &str,
+    ) -> Result<bool, DbError> {
+        let removed = db
+            .execute(include_str!("sql/remove_reaction.sql"), &[message_id, user_id, &emoji])
+            .await?;
+        Ok(removed > 0)
+    }
+
+    pub async fn get_by_messages<T: Querist>(
+        db: &mut T,
+        message_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<ReactionCount>>, DbError> {
+        let rows = db.query(include_str!("sql/get_reactions.sql"), &[&message_ids]).await?;
+        let mut reactions: HashMap<Uuid, Vec<ReactionCount>> = HashMap::new();
+        for row in rows {
+            let message_id: Uuid = row.try_get(0)?;
+            let count = ReactionCount {
+                emoji: row.try_get(1)?,
+                count: row.try_get(2)?,
+                user_ids: row.try_get(3)?,
+            };
+            reactions.entry(message_id).or_default().push(count);
+        }
+        Ok(reactions)
+    }
+
+    pub async fn get<T: Querist>(db: &mut T, message_id: &Uuid) -> Result<Vec<ReactionCount>, DbError> {
+        let mut reactions = Reaction::get_by_messages(db, &[*message_id]).await?;
+        Ok(reactions.remove(message_id).unwrap_or_default())
+    }
+}
+
+/// The most pending scheduled messages of a user in a channel.
+pub const MAX_SCHEDULED: usize = 64;
+
+/// Times a scheduled message is tried to send before it is given up.
+pub const MAX_SEND_ATTEMPTS: i32 = 5;
+
+/// A message sent by `events::tasks` at `send_at`, then removed.
+///
+/// Failed sends are retried later, up to `MAX_SEND_ATTEMPTS` times, and editing resets them.
+#[derive(Debug, Serialize, FromSql, Clone)]
+#[serde(rename_all = "camelCase")]
+#[postgres(name = "scheduled_messages")]
+pub struct ScheduledMessage {
+    pub id: Uuid,
+    pub channel_id: Uuid,
+    pub sender_id: Uuid,
+    pub name: String,
+    pub text: String,
+    pub entities: JsonValue,
+    pub in_game: bool,
+    pub is_action: bool,
+    pub media_id: Option<Uuid>,
+    pub whisper_to_users: Option<Vec<Uuid>>,
+    pub character_id: Option<Uuid>,
+    pub tags: Vec<String>,
+    #[serde(with = "crate::date_format")]
+    pub send_at: NaiveDateTime,
+    #[serde(with = "crate::date_format")]
+    pub created: NaiveDateTime,
+    #[serde(with = "crate::date_format")]
+    pub modified: NaiveDateTime,
+    pub attempts: i32,
+    #[serde(with = "crate::date_format::option")]
+    pub failed_at: Option<NaiveDateTime>,
+}
+
+fn check_send_at(send_at: NaiveDateTime) -> Result<(), ValidationFailed> {
+    if send_at <= chrono::Utc::now().naive_utc() {
+        return Err(ValidationFailed("The scheduled time has passed."));
+    }
+    Ok(())
+}
+
+impl ScheduledMessage {
+    pub async fn create<T: Querist>(
+        db: &mut T,
+        channel_id: &Uuid,
+        sender_id: &Uuid,
+        name: &str,
+        text: &str,
+        entities: Vec<JsonValue>,
+        in_game: bool,
+        is_action: bool,
+        media_id: Option<Uuid>,
+        whisper_to_users: Option<Vec<Uuid>>,
+        character_id: Option<Uuid>,
+        tags: Vec<String>,
+        send_at: NaiveDateTime,
+    ) -> Result<ScheduledMessage, ModelError> {
+        let name = merge_blank(name);
+        if !name.is_empty() {
+            CHARACTER_NAME.run(&name)?;
+        }
+        if text.is_empty() {
+            return Err(ValidationFailed("Text is empty.").into());
+        }
+        check_send_at(send_at)?;
+        let tags = normalize_tags(tags)?;
+        let entities = check_entities(text, entities)?;
+        let row = db
+            .query_exactly_one(
+                include_str!("sql/create_scheduled.sql"),
+                &[
+                    channel_id,
+                    sender_id,
+                    &name,
+                    &text,
+                    &entities,
+                    &in_game,
+                    &is_action,
+                    &media_id,
+                    &whisper_to_users,
+                    &character_id,
+                    &tags,
+                    &send_at,
+                ],
+            )
+            .await?;
+        Ok(row.try_get(0)?)
+    }
+
+    pub async fn get<T: Querist>(db: &mut T, id: &Uuid) -> Result<Option<ScheduledMessage>, DbError> {
+        let row = db.query_one(include_str!("sql/get_scheduled.sql"), &[id]).await?;
+        match row {
+            Some(row) => row.try_get(0),
+            None => Ok(None),
+        }
+    }
+
+    /// Pending scheduled messages of a sender in a channel, the earliest first.
+    pub async fn get_by_channel<T: Querist>(
+        db: &mut T,
+        channel_id: &Uuid,
+        sender_id: &Uuid,
+    ) -> Result<Vec<ScheduledMessage>, DbError> {
+        let rows = db
+            .query(
+                include_str!("sql/get_scheduled_by_channel.sql"),
+                &[channel_id, sender_id],
+            )
+            .await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+
+    /// Returns `None` if the message had been sent or cancelled.
+    pub async fn edit<T: Querist>(
+        db: &mut T,
+        id: &Uuid,
+        name: Option<&str>,
+        text: Option<&str>,
+        entities: Option<Vec<JsonValue>>,
+        in_game: Option<bool>,
+        is_action: Option<bool>,
+        tags: Option<Vec<String>>,
+        send_at: Option<NaiveDateTime>,
+    ) -> Result<Option<ScheduledMessage>, ModelError> {
+        let name = name.map(merge_blank);
+        if let Some(name) = name.as_deref().filter(|name| !name.is_empty()) {
+            CHARACTER_NAME.run(name)?;
+        }
+        if text == Some("") {
+            return Err(ValidationFailed("Text is empty.").into());
+        }
+        if let Some(send_at) = send_at {
+            check_send_at(send_at)?;
+        }
+        let tags = tags.map(normalize_tags).transpose()?;
+        let entities = match (text, entities) {
+            (None, None) => None,
+            (Some(text), Some(entities)) => Some(check_entities(text, entities)?),
+            (text, entities) => {
+                let scheduled = match ScheduledMessage::get(db, id).await? {
+                    Some(scheduled) => scheduled,
+                    None => return Ok(None),
+                };
+                let text = text.unwrap_or(&*scheduled.text);
+                match entities {
+                    Some(entities) => Some(check_entities(text, entities)?),
+                    None => Some(JsonValue::Array(retain_entities(text, scheduled.entities()))),
+                }
+            }
+        };
+        let row = db
+            .query_one(
+                include_str!("sql/edit_scheduled.sql"),
+                &[id, &name, &text, &entities, &in_game, &is_action, &tags, &send_at],
+            )
+            .await?;
+        match row {
+            Some(row) => Ok(row.try_get(0)?),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn delete<T: Querist>(db: &mut T, id: &Uuid) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/delete_scheduled.sql"), &[id]).await
+    }
+
+    /// Locks the earliest due scheduled message until the end of the transaction.
+    pub async fn take_due<T: Querist>(db: &mut T) -> Result<Option<ScheduledMessage>, DbError> {
+        let row = db
+            .query_one(include_str!("sql/take_due_scheduled.sql"), &[&MAX_SEND_ATTEMPTS])
+            .await?;
+        match row {
+            Some(row) => row.try_get(0),
+            None => Ok(None),
+        }
+    }
+
+    /// Puts off the next attempt to send, returns the attempts so far.
+    pub async fn mark_failed<T: Querist>(db: &mut T, id: &Uuid) -> Result<Option<i32>, DbError> {
+        let row = db
+            .query_one(include_str!("sql/mark_scheduled_failed.sql"), &[id])
+            .await?;
+        match row {
+            Some(row) => row.try_get(0),
+            None => Ok(None),
+        }
+    }
+
+    pub fn entities(&self) -> Vec<JsonValue> {
+        match &self.entities {
+            JsonValue::Array(entities) => entities.clone(),
+            _ => Vec::new(),
+        }
+    }
 }
 
 #[tokio::test]
@@ -421,6 +1011,9 @@ async fn message_test() -> Result<(), crate::error::AppError> {
         Some(vec![]),
         Some(Uuid::nil()),
         None,
+        None,
+        None,
+        vec![],
     )
     .await?;
     assert_eq!(message.text, "");
@@ -439,6 +1032,7 @@ async fn message_test() -> Result<(), crate::error::AppError> {
         None,
         None,
         None,
+        None,
     )
     .await?
     .unwrap();
@@ -453,7 +1047,7 @@ async fn message_test() -> Result<(), crate::error::AppError> {
     ChannelMember::set_master(db, &user.id, &channel.id, false).await?;
     let a = Message::get(db, &message.id, Some(&user.id)).await?.unwrap();
     assert_eq!(a.text, "");
-    let messages = Message::get_by_channel(db, &channel.id, None, 128).await?;
+    let messages = Message::get_by_channel(db, &channel.id, None, 128, None).await?;
     assert_eq!(messages.len(), 1);
     assert_eq!(messages[0].id, a.id);
 
@@ -473,10 +1067,13 @@ async fn message_test() -> Result<(), crate::error::AppError> {
         None,
         Some(Uuid::nil()),
         None,
+        None,
+        None,
+        vec![],
     )
     .await
     .unwrap();
-    let messages = Message::get_by_channel(db, &channel.id, None, 128).await?;
+    let messages = Message::get_by_channel(db, &channel.id, None, 128, None).await?;
     assert_eq!(messages.len(), 2);
     assert_eq!(messages[0].text, b.text);
 
@@ -496,20 +1093,171 @@ async fn message_test() -> Result<(), crate::error::AppError> {
         None,
         Some(Uuid::nil()),
         None,
+        Some(b.id),
+        None,
+        vec!["#clue".to_string(), "npc:Alice".to_string(), "clue".to_string()],
     )
     .await
     .unwrap();
+    assert_eq!(c.parent_message_id, Some(b.id));
+    assert_eq!(c.tags, vec!["clue", "npc:Alice"]);
+    let tagged = Message::get_by_channel(db, &channel.id, None, 128, Some("npc:Alice")).await?;
+    assert_eq!(tagged.len(), 1);
+    assert_eq!(tagged[0].id, c.id);
+    let tags = Message::tags(db, &channel.id).await?;
+    assert_eq!(tags.len(), 2);
     let a = messages[1].pos;
     let b = messages[0].pos;
     Message::move_between(db, &c.id, &a, &b).await.unwrap().unwrap();
-    let messages = Message::get_by_channel(db, &channel.id, None, 128).await?;
+    let messages = Message::get_by_channel(db, &channel.id, None, 128, None).await?;
     assert_eq!(messages.len(), 3);
     assert_eq!(messages[1].id, c.id);
     Message::move_above(db, &c.channel_id, &c.id, &messages[2].pos).await?;
-    let messages = Message::get_by_channel(db, &channel.id, None, 128).await?;
+    let messages = Message::get_by_channel(db, &channel.id, None, 128, None).await?;
     assert_eq!(messages[2].id, c.id);
     Message::move_bottom(db, &c.channel_id, &c.id, &messages[0].pos).await?;
-    let messages = Message::get_by_channel(db, &channel.id, None, 128).await?;
+    let messages = Message::get_by_channel(db, &channel.id, None, 128, None).await?;
     assert_eq!(messages[0].id, c.id);
+
+    let event = SystemEvent::Joined;
+    let joined = Message::create_system(db, &mut cache, &channel, &user.id, "", event, event.describe())
+        .await?
+        .unwrap();
+    assert_eq!(joined.name, user.nickname);
+    assert_eq!(joined.system_event, Some(SystemEvent::Joined));
+    let channel = Channel::edit(
+        db,
+        &channel.id,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(&[]),
+        None,
+        None,
+    )
+    .await?;
+    let event = SystemEvent::Left;
+    let left = Message::create_system(db, &mut cache, &channel, &user.id, "", event, event.describe()).await?;
+    assert!(left.is_none());
+
+    // split
+    let messages = Message::get_by_channel(db, &channel.id, None, 128, None).await?;
+    let from_pos = messages[1].pos;
+    let copy = Channel::create(db, &space.id, "Copied Channel", true, None).await?;
+    let copied = Message::copy_range(db, &channel.id, &copy.id, from_pos).await?;
+    assert_eq!(copied.len(), 2);
+    assert_eq!(copied[0].pos, 1.0);
+    assert_eq!(copied[1].text, messages[0].text);
+    let moved_to = Channel::create(db, &space.id, "Moved Channel", true, None).await?;
+    let moved = Message::move_range(db, &channel.id, &moved_to.id, from_pos).await?;
+    assert_eq!(moved[1].id, messages[0].id);
+    assert_eq!(
+        Message::get_by_channel(db, &channel.id, None, 128, None).await?.len(),
+        messages.len() - 2
+    );
+
+    // merge
+    Message::merge(db, &moved_to.id, &channel.id, false).await?;
+    let merged = Message::get_by_channel(db, &channel.id, None, 128, None).await?;
+    assert_eq!(merged.len(), messages.len());
+    assert_eq!(merged[0].pos, messages.len() as f64);
+
+    // pins
+    let pinned = Message::set_pinned(db, &c.id, true).await?.unwrap();
+    assert!(pinned.pinned);
+    let pins = Message::get_pinned(db, &channel.id, None, 16).await?;
+    assert_eq!(pins.len(), 1);
+    assert_eq!(pins[0].id, c.id);
+    assert!(Message::get_pinned(db, &channel.id, Some(c.pos), 16).await?.is_empty());
+
+    // reactions
+    assert!(Reaction::add(db, &c.id, &user.id, "👍").await?);
+    assert!(!Reaction::add(db, &c.id, &user.id, "👍").await?);
+    let reactions = Reaction::get(db, &c.id).await?;
+    assert_eq!(reactions[0].count, 1);
+    assert_eq!(reactions[0].user_ids, vec![user.id]);
+    assert!(Reaction::remove(db, &c.id, &user.id, "👍").await?);
+    assert!(Reaction::get(db, &c.id).await?.is_empty());
+
+    // scheduled messages
+    let send_at = chrono::Utc::now().naive_utc() + chrono::Duration::hours(1);
+    let text = "The clock strikes midnight.";
+    let scheduled = ScheduledMessage::create(
+        db,
+        &channel.id,
+        &user.id,
+        "",
+        "midnight",
+        vec![],
+        true,
+        false,
+        None,
+        None,
+        None,
+        vec![],
+        send_at,
+    )
+    .await?;
+    let edited = ScheduledMessage::edit(db, &scheduled.id, None, Some(text), None, None, None, None, None)
+        .await?
+        .unwrap();
+    assert_eq!(edited.text, text);
+    assert_eq!(
+        ScheduledMessage::get_by_channel(db, &channel.id, &user.id).await?.len(),
+        1
+    );
+    ScheduledMessage::delete(db, &scheduled.id).await?;
+    assert!(ScheduledMessage::get(db, &scheduled.id).await?.is_none());
+
+    // a failed scheduled message doesn't hold up the next one
+    let mut due = Vec::new();
+    for minutes in [1, 2] {
+        let send_at = chrono::Utc::now().naive_utc() + chrono::Duration::minutes(minutes);
+        let scheduled = ScheduledMessage::create(
+            db,
+            &channel.id,
+            &user.id,
+            "",
+            text,
+            vec![],
+            true,
+            false,
+            None,
+            None,
+            None,
+            vec![],
+            send_at,
+        )
+        .await?;
+        due.push(scheduled.id);
+    }
+    db.execute(
+        "UPDATE scheduled_messages SET send_at = send_at - interval '1 hour' WHERE channel_id = $1",
+        &[&channel.id],
+    )
+    .await?;
+    assert_eq!(ScheduledMessage::take_due(db).await?.unwrap().id, due[0]);
+    assert_eq!(ScheduledMessage::mark_failed(db, &due[0]).await?, Some(1));
+    assert_eq!(ScheduledMessage::take_due(db).await?.unwrap().id, due[1]);
+    for id in due {
+        ScheduledMessage::delete(db, &id).await?;
+    }
+
+    // bulk actions
+    let selected = Message::select_bulk(db, &channel.id, &user.id, None, Some((f64::MAX, 0.0))).await?;
+    let ids: Vec<Uuid> = selected.iter().map(|message| message.id).collect();
+    let folded = Message::bulk_edit(db, &*ids, Some(true), None).await?;
+    assert!(folded.iter().all(|message| message.folded));
+    let target = Channel::create(db, &space.id, "Bulk Target", true, None).await?;
+    let moved = Message::move_to_channel(db, &ids[..1], &target.id).await?;
+    assert_eq!(moved[0].channel_id, target.id);
+    assert_eq!(moved[0].pos, 1.0);
+    Message::bulk_delete(db, &ids[1..]).await?;
+    assert!(Message::get_by_channel(db, &channel.id, None, 128, None)
+        .await?
+        .is_empty());
     Ok(())
 }
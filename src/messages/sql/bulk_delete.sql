@@ -0,0 +1,3 @@
+UPDATE messages
+SET deleted = true
+WHERE id = ANY ($1);
@@ -0,0 +1,6 @@
+UPDATE messages
+SET folded   = COALESCE($2, folded),
+    in_game  = COALESCE($3, in_game),
+    modified = (now() at time zone 'utc')
+WHERE id = ANY ($1)
+RETURNING messages;
@@ -0,0 +1,31 @@
+-- Whispers are left behind, the copies are sent by the owner of the new space.
+-- Secret channels are only copied if the owner $3 is a member of them.
+INSERT INTO messages (sender_id, channel_id, name, media_id, seed, in_game, is_action, is_master, pinned, tags,
+                      folded, text, entities, pos)
+SELECT $3,
+       new_channel.id,
+       m.name,
+       m.media_id,
+       m.seed,
+       m.in_game,
+       m.is_action,
+       m.is_master,
+       m.pinned,
+       m.tags,
+       m.folded,
+       m.text,
+       m.entities,
+       m.pos
+FROM messages m
+         INNER JOIN channels old_channel ON m.channel_id = old_channel.id
+         INNER JOIN channels new_channel ON new_channel.space_id = $2 AND new_channel.name = old_channel.name
+WHERE old_channel.space_id = $1
+  AND old_channel.is_document = true
+  AND old_channel.deleted = false
+  AND (old_channel.is_public = true OR EXISTS(SELECT 1
+                                              FROM channel_members cm
+                                              WHERE cm.channel_id = old_channel.id
+                                                AND cm.user_id = $3
+                                                AND cm.is_joined))
+  AND m.deleted = false
+  AND m.whisper_to_users IS NULL;
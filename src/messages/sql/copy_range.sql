@@ -0,0 +1,30 @@
+-- Positions in the new channel are renumbered from 1 in the original order.
+INSERT INTO messages (sender_id, channel_id, name, media_id, seed, in_game, is_action, is_master, pinned, tags,
+                      folded, text, whisper_to_users, entities, created, modified, order_date, order_offset, pos,
+                      system_event, character_id)
+SELECT m.sender_id,
+       $2,
+       m.name,
+       m.media_id,
+       m.seed,
+       m.in_game,
+       m.is_action,
+       m.is_master,
+       m.pinned,
+       m.tags,
+       m.folded,
+       m.text,
+       m.whisper_to_users,
+       m.entities,
+       m.created,
+       m.modified,
+       m.order_date,
+       m.order_offset,
+       row_number() OVER (ORDER BY m.pos)::float8,
+       m.system_event,
+       m.character_id
+FROM messages m
+WHERE m.channel_id = $1
+  AND m.pos >= $3
+  AND m.deleted = false
+RETURNING messages;
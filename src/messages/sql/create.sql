@@ -10,7 +10,10 @@ INSERT INTO messages (
     is_master,
     whisper_to_users,
     media_id,
-    pos
+    pos,
+    parent_message_id,
+    character_id,
+    tags
 )
 VALUES (
     COALESCE($1, uuid_generate_v1mc()),
@@ -24,6 +27,9 @@ VALUES (
     $9,
     $10,
     $11,
-    $12
+    $12,
+    $13,
+    $14,
+    $15
 )
 RETURNING messages;
@@ -0,0 +1,4 @@
+INSERT INTO scheduled_messages (channel_id, sender_id, name, text, entities, in_game, is_action, media_id,
+                                whisper_to_users, character_id, tags, send_at)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+RETURNING scheduled_messages;
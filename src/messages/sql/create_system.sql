@@ -0,0 +1,5 @@
+INSERT INTO messages (channel_id, sender_id, name, text, pos, system_event)
+SELECT $1::uuid, u.id, COALESCE(NULLIF($3::text, ''), u.nickname), $4::text, $5::float8, $6::system_event
+FROM users u
+WHERE u.id = $2
+RETURNING messages;
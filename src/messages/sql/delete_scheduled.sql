@@ -0,0 +1,3 @@
+DELETE
+FROM scheduled_messages
+WHERE id = $1;
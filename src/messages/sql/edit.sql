@@ -6,6 +6,7 @@ SET name         = COALESCE($2, name),
     is_action    = COALESCE($6, is_action),
     folded       = COALESCE($7, folded),
     media_id     = COALESCE($8, media_id),
+    tags         = COALESCE($9, tags),
     modified     = (now() at time zone 'utc')
 WHERE id = $1
 RETURNING messages;
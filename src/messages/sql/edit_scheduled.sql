@@ -0,0 +1,13 @@
+UPDATE scheduled_messages
+SET name      = COALESCE($2, name),
+    text      = COALESCE($3, text),
+    entities  = COALESCE($4, entities),
+    in_game   = COALESCE($5, in_game),
+    is_action = COALESCE($6, is_action),
+    tags      = COALESCE($7, tags),
+    send_at   = COALESCE($8, send_at),
+    attempts  = 0,
+    failed_at = null,
+    modified  = (now() at time zone 'utc')
+WHERE id = $1
+RETURNING scheduled_messages;
@@ -3,5 +3,5 @@ FROM messages msg
 WHERE msg.channel_id = $1
   AND msg.deleted = false
   AND msg.order_date > coalesce($2, to_timestamp(0)::timestamp)
+  AND ($3::text IS NULL OR (msg.tags @> ARRAY [$3::text] AND (NOT $4 OR msg.whisper_to_users IS NULL)))
 ORDER BY msg.pos;
-
@@ -3,5 +3,6 @@ FROM messages msg
 WHERE msg.channel_id = $1
   AND msg.deleted = false
   AND ($2 IS NULL OR msg.pos < $2) -- before
+  AND ($4 IS NULL OR (msg.tags @> ARRAY [$4] AND msg.whisper_to_users IS NULL)) -- tag
 ORDER BY msg.pos DESC
 LIMIT $3;
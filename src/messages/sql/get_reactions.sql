@@ -0,0 +1,5 @@
+SELECT message_id, emoji, count(*), array_agg(user_id ORDER BY created)
+FROM message_reactions
+WHERE message_id = ANY ($1)
+GROUP BY message_id, emoji
+ORDER BY message_id, min(created);
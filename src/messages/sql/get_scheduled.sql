@@ -0,0 +1,3 @@
+SELECT s
+FROM scheduled_messages s
+WHERE s.id = $1;
@@ -0,0 +1,5 @@
+SELECT s
+FROM scheduled_messages s
+WHERE s.channel_id = $1
+  AND s.sender_id = $2
+ORDER BY s.send_at;
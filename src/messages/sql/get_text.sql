@@ -0,0 +1,4 @@
+SELECT text, entities
+FROM messages
+WHERE id = $1
+  AND deleted = false;
@@ -0,0 +1,5 @@
+UPDATE scheduled_messages
+SET attempts  = attempts + 1,
+    failed_at = (now() at time zone 'utc')
+WHERE id = $1
+RETURNING attempts;
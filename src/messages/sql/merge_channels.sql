@@ -0,0 +1,44 @@
+-- Interleaves all messages of both channels into $2, renumbering positions from 1.
+-- Ordered by `order_date` if $3, otherwise by `created`, ties keep their previous order.
+-- Read markers of both channels follow the last message they had read, one per user in $2,
+-- the least advanced one if the user had read both.
+WITH ranked AS (
+    SELECT id,
+           channel_id,
+           pos AS old_pos,
+           row_number() OVER (
+               ORDER BY
+                   CASE WHEN $3 THEN order_date ELSE created END,
+                   CASE WHEN $3 THEN order_offset ELSE 0 END,
+                   pos,
+                   channel_id = $2 DESC
+               )::float8 AS pos
+    FROM messages
+    WHERE channel_id = $1
+       OR channel_id = $2
+),
+     remapped AS (
+         SELECT rm.user_id,
+                min(COALESCE((SELECT max(r.pos)
+                              FROM ranked r
+                              WHERE r.channel_id = rm.channel_id
+                                AND r.old_pos <= rm.pos), 0.0)) AS pos
+         FROM channel_read_markers rm
+         WHERE rm.channel_id = $1
+            OR rm.channel_id = $2
+         GROUP BY rm.user_id
+     ),
+     dropped AS (
+         DELETE FROM channel_read_markers WHERE channel_id = $1
+     ),
+     moved AS (
+         INSERT INTO channel_read_markers (user_id, channel_id, pos)
+             SELECT user_id, $2, pos
+             FROM remapped
+             ON CONFLICT (user_id, channel_id) DO UPDATE SET pos = excluded.pos
+     )
+UPDATE messages m
+SET channel_id = $2,
+    pos        = ranked.pos
+FROM ranked
+WHERE m.id = ranked.id;
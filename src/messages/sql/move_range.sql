@@ -0,0 +1,13 @@
+-- Positions in the new channel are renumbered from 1 in the original order.
+UPDATE messages m
+SET channel_id = $2,
+    pos        = ranked.pos
+FROM (
+         SELECT id, row_number() OVER (ORDER BY pos)::float8 AS pos
+         FROM messages
+         WHERE channel_id = $1
+           AND pos >= $3
+           AND deleted = false
+     ) ranked
+WHERE m.id = ranked.id
+RETURNING m;
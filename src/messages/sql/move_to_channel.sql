@@ -0,0 +1,16 @@
+-- Appends the messages to the end of $2, keeping their order.
+UPDATE messages m
+SET channel_id = $2,
+    pos        = top.pos + ranked.n
+FROM (
+         SELECT id, row_number() OVER (ORDER BY pos)::float8 AS n
+         FROM messages
+         WHERE id = ANY ($1)
+     ) ranked,
+     (
+         SELECT coalesce(floor(max(pos)), 0) AS pos
+         FROM messages
+         WHERE channel_id = $2
+     ) top
+WHERE m.id = ranked.id
+RETURNING m;
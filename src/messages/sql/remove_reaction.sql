@@ -0,0 +1,5 @@
+DELETE
+FROM message_reactions
+WHERE message_id = $1
+  AND user_id = $2
+  AND emoji = $3;
@@ -0,0 +1,12 @@
+-- Messages of a channel selected by ids or by an inclusive `pos` range, locked for the bulk action.
+SELECT msg, (msg.whisper_to_users IS NOT NULL AND cm.is_master IS NOT true AND $5 <> ALL (msg.whisper_to_users))
+FROM messages msg
+         LEFT JOIN channel_members cm ON cm.channel_id = msg.channel_id AND cm.user_id = $5
+WHERE msg.channel_id = $1
+  AND msg.deleted = false
+  AND ($2::uuid[] IS NULL OR msg.id = ANY ($2))
+  AND ($3::float8 IS NULL OR msg.pos >= $3)
+  AND ($4::float8 IS NULL OR msg.pos <= $4)
+ORDER BY msg.pos
+LIMIT $6
+FOR UPDATE OF msg;
@@ -0,0 +1,5 @@
+UPDATE messages
+SET pinned = $2
+WHERE id = $1
+  AND deleted = false
+RETURNING messages;
@@ -0,0 +1,8 @@
+SELECT tag, count(*)
+FROM messages msg,
+     unnest(msg.tags) tag
+WHERE msg.channel_id = $1
+  AND msg.deleted = false
+  AND msg.whisper_to_users IS NULL
+GROUP BY tag
+ORDER BY count(*) DESC, tag;
@@ -0,0 +1,10 @@
+-- Locks the earliest due scheduled message, skipping those being sent by other servers,
+-- those failed too many times and those failed recently, a minute per failed attempt.
+SELECT s
+FROM scheduled_messages s
+WHERE s.send_at <= (now() at time zone 'utc')
+  AND s.attempts < $1
+  AND (s.failed_at IS NULL OR s.failed_at < (now() at time zone 'utc') - make_interval(mins => s.attempts))
+ORDER BY s.send_at
+LIMIT 1
+FOR UPDATE SKIP LOCKED;
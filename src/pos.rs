@@ -39,7 +39,7 @@ pub async fn pos<T: Querist>(
         Ok(pos)
     } else {
         let bottom: i64 = alloc_new_pos(db, cache, channel_id).await?;
-        cache.inner.set_ex(&pos_key, bottom, 60 * 5).await?;
+        cache.inner.set_ex::<_, _, ()>(&pos_key, bottom, 60 * 5).await?;
         Ok(bottom)
     }
 }
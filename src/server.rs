@@ -1,4 +1,4 @@
-#![feature(backtrace)]
+#![feature(error_generic_member_access)]
 #![allow(dead_code)]
 
 use std::env;
@@ -13,6 +13,7 @@ use hyper::{Body, Request, Server};
 mod utils;
 #[macro_use]
 mod error;
+mod audit;
 mod cache;
 mod channels;
 mod context;
@@ -30,6 +31,7 @@ mod pool;
 mod pos;
 mod session;
 mod spaces;
+mod stats;
 mod users;
 mod validators;
 mod websocket;
@@ -58,6 +60,8 @@ async fn router(req: Request<Body>) -> Result<Response, AppError> {
     table!("/api/channels", channels::router);
     table!("/api/spaces", spaces::router);
     table!("/api/events", events::router);
+    table!("/api/audit", audit::router);
+    table!("/api/stats", stats::router);
     missing()
 }
 
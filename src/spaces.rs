@@ -1,6 +1,8 @@
 pub mod api;
 pub mod handlers;
 pub mod models;
+pub mod permissions;
 
 pub use handlers::router;
-pub use models::{RestrainedMember, Space, SpaceMember};
+pub use models::{RestrainedMember, Space, SpaceInvite, SpaceMember, SpaceRole, SpaceTemplate};
+pub use permissions::Permissions;
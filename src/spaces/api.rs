@@ -1,9 +1,11 @@
 use std::collections::HashMap;
 
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use super::models::UserStatus;
+use super::Permissions;
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -22,6 +24,13 @@ pub struct Join {
     pub token: Option<Uuid>,
 }
 
+/// Optional body of the join request, kept out of the query string.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JoinWithPassword {
+    pub password: String,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Kick {
@@ -45,6 +54,9 @@ pub struct Edit {
     pub explorable: Option<bool>,
     pub is_public: Option<bool>,
     pub allow_spectator: Option<bool>,
+    pub language: Option<String>,
+    /// An empty password removes it.
+    pub password: Option<String>,
     #[serde(default)]
     pub grant_admins: Vec<Uuid>,
     #[serde(default)]
@@ -57,8 +69,11 @@ pub struct SpaceWithRelated {
     pub space: super::Space,
     pub members: HashMap<Uuid, super::models::SpaceMemberWithUser>,
     pub channels: Vec<crate::channels::Channel>,
+    pub categories: Vec<crate::channels::models::ChannelCategory>,
     pub channel_members: HashMap<Uuid, Vec<crate::channels::ChannelMember>>,
     pub users_status: HashMap<Uuid, UserStatus>,
+    pub roles: Vec<super::SpaceRole>,
+    pub role_assignments: HashMap<Uuid, Vec<Uuid>>,
 }
 
 #[derive(Serialize, Debug)]
@@ -68,3 +83,138 @@ pub struct SpaceWithMember {
     pub member: super::SpaceMember,
     pub user: crate::users::User,
 }
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateRole {
+    pub space_id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub permissions: Permissions,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EditRole {
+    pub role_id: Uuid,
+    pub name: Option<String>,
+    pub permissions: Option<Permissions>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignRole {
+    pub role_id: Uuid,
+    pub user_id: Uuid,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateInvite {
+    pub space_id: Uuid,
+    #[serde(default)]
+    pub label: String,
+    pub max_uses: Option<i32>,
+    #[serde(default)]
+    pub channel_ids: Vec<Uuid>,
+    #[serde(default, with = "crate::date_format::option")]
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestJoin {
+    pub space_id: Uuid,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DecideJoinRequest {
+    pub request_id: Uuid,
+    pub approve: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JoinRequestWithUser {
+    pub request: super::models::JoinRequest,
+    pub user: crate::users::User,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferOwnership {
+    pub space_id: Uuid,
+    pub user_id: Uuid,
+    /// The name of the space, typed again by the owner to confirm.
+    pub confirm_name: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CloneSpace {
+    pub space_id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub include_documents: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveTemplate {
+    pub space_id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub is_public: bool,
+    #[serde(default)]
+    pub include_documents: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InstantiateTemplate {
+    pub template_id: Uuid,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ExploreSort {
+    /// The most recent message first.
+    #[default]
+    Activity,
+    Members,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Explore {
+    pub language: Option<String>,
+    pub dice_type: Option<String>,
+    #[serde(default)]
+    pub sort: ExploreSort,
+    /// The `next` of the previous page.
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SpaceWithStats {
+    pub space: super::Space,
+    pub member_count: i64,
+    pub channel_count: i64,
+    #[serde(with = "crate::date_format::option")]
+    pub last_message: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorePage {
+    pub spaces: Vec<SpaceWithStats>,
+    pub next: Option<String>,
+}
@@ -1,18 +1,28 @@
 use std::collections::HashMap;
 
-use super::api::{Create, Edit, SpaceWithRelated};
-use super::models::space_users_status;
-use super::{Space, SpaceMember};
+use super::api::{
+    AssignRole, CloneSpace, Create, CreateInvite, CreateRole, DecideJoinRequest, Edit, EditRole, InstantiateTemplate,
+    JoinRequestWithUser, RequestJoin, SaveTemplate, SpaceWithRelated, TransferOwnership,
+};
+use super::models::{space_users_status, JoinRequest, OwnershipTransfer, RoleKind};
+use super::permissions::space_permission;
+use super::{Permissions, Space, SpaceInvite, SpaceMember, SpaceRole, SpaceTemplate};
+use crate::audit::{self, AuditAction, AuditLog};
+use crate::cache::make_key;
+use crate::channels::models::{ChannelCategory, ChannelRoleOverride};
 use crate::channels::{Channel, ChannelMember};
 use crate::csrf::authenticate;
 use crate::database;
+use crate::database::Querist;
 use crate::error::{AppError, Find};
-use crate::events::Event;
+use crate::events::{DbEvent, DbEventType, Event};
 use crate::interface::{self, missing, ok_response, parse_query, IdQuery, Response};
-use crate::spaces::api::{Join, Kick, SearchParams, SpaceWithMember};
+use crate::messages::Message;
+use crate::spaces::api::{Explore, ExplorePage, Join, JoinWithPassword, Kick, SearchParams, SpaceWithMember};
 use crate::spaces::models::SpaceMemberWithUser;
 use crate::users::User;
 use hyper::{Body, Request};
+use redis::AsyncCommands;
 use uuid::Uuid;
 
 async fn list(_req: Request<Body>) -> Result<Vec<Space>, AppError> {
@@ -27,27 +37,36 @@ async fn query(req: Request<Body>) -> Result<Space, AppError> {
     Space::get_by_id(db, &id).await?.or_not_found()
 }
 
-pub async fn space_related(id: &Uuid) -> Result<SpaceWithRelated, AppError> {
+/// Secret channels are left out unless the user is a member of them.
+pub async fn space_related(id: &Uuid, user_id: Option<&Uuid>) -> Result<SpaceWithRelated, AppError> {
     let mut conn = database::get().await?;
     let db = &mut *conn;
     let space = Space::get_by_id(db, id).await?.or_not_found()?;
     let members = SpaceMemberWithUser::get_by_space(db, id).await?;
-    let channels = Channel::get_by_space(db, id).await?;
+    let channels = Channel::get_visible_by_space(db, id, user_id).await?;
+    let categories = ChannelCategory::get_by_space(db, id).await?;
     let mut cache = crate::cache::conn().await;
     let users_status = space_users_status(&mut cache, space.id).await?;
-    let channel_members = ChannelMember::get_by_space(db, &space.id).await?;
+    let mut channel_members = ChannelMember::get_by_space(db, &space.id).await?;
+    channel_members.retain(|channel_id, _| channels.iter().any(|channel| &channel.id == channel_id));
+    let roles = SpaceRole::get_by_space(db, &space.id).await?;
+    let role_assignments = SpaceRole::assignments(db, &space.id).await?;
     Ok(SpaceWithRelated {
         space,
         members,
         channels,
+        categories,
         users_status,
         channel_members,
+        roles,
+        role_assignments,
     })
 }
 
 async fn query_with_related(req: Request<Body>) -> Result<SpaceWithRelated, AppError> {
     let IdQuery { id } = parse_query(req.uri())?;
-    space_related(&id).await
+    let session = authenticate(&req).await.ok();
+    space_related(&id, session.as_ref().map(|session| &session.user_id)).await
 }
 
 async fn token(req: Request<Body>) -> Result<Uuid, AppError> {
@@ -55,13 +74,7 @@ async fn token(req: Request<Body>) -> Result<Uuid, AppError> {
     let IdQuery { id } = parse_query(req.uri())?;
     let mut conn = database::get().await?;
     let db = &mut *conn;
-    let is_admin = SpaceMember::get(db, &session.user_id, &id)
-        .await?
-        .map(|space_member| space_member.is_admin)
-        .unwrap_or(false);
-    if !is_admin {
-        return Err(AppError::NoPermission(format!("A non-admin tries to get join token")));
-    }
+    space_permission(db, &session.user_id, &id, Permissions::INVITE).await?;
     Space::get_token(db, &id).await.map_err(Into::into)
 }
 
@@ -70,16 +83,20 @@ async fn refresh_token(req: Request<Body>) -> Result<Uuid, AppError> {
     let IdQuery { id } = parse_query(req.uri())?;
     let mut conn = database::get().await?;
     let db = &mut *conn;
-    let is_admin = SpaceMember::get(db, &session.user_id, &id)
-        .await?
-        .map(|space_member| space_member.is_admin)
-        .unwrap_or(false);
-    if !is_admin {
-        return Err(AppError::NoPermission(format!(
-            "A non-admin tries to refresh join token"
-        )));
-    }
-    Space::refresh_token(db, &id).await.map_err(Into::into)
+    space_permission(db, &session.user_id, &id, Permissions::INVITE).await?;
+    let token = Space::refresh_token(db, &id).await?;
+    let payload = serde_json::json!({});
+    AuditLog::record(
+        db,
+        &id,
+        &session.user_id,
+        AuditAction::InviteTokenRefreshed,
+        None,
+        None,
+        payload,
+    )
+    .await?;
+    Ok(token)
 }
 
 async fn my_spaces(req: Request<Body>) -> Result<Vec<SpaceWithMember>, AppError> {
@@ -89,6 +106,37 @@ async fn my_spaces(req: Request<Body>) -> Result<Vec<SpaceWithMember>, AppError>
     Space::get_by_user(db, &session.user_id).await.map_err(Into::into)
 }
 
+async fn explore(req: Request<Body>) -> Result<ExplorePage, AppError> {
+    let Explore {
+        language,
+        dice_type,
+        sort,
+        cursor,
+        limit,
+    } = parse_query(req.uri())?;
+    let after = match cursor {
+        Some(cursor) => {
+            let bad_cursor = || AppError::BadRequest("Invalid cursor".to_string());
+            let (key, id) = cursor.split_once('_').ok_or_else(bad_cursor)?;
+            let key: i64 = key.parse().map_err(|_| bad_cursor())?;
+            let id: Uuid = id.parse().map_err(|_| bad_cursor())?;
+            Some((key, id))
+        }
+        None => None,
+    };
+    let limit = limit.unwrap_or(32).clamp(1, 128);
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let rows = Space::explore(db, language.as_deref(), dice_type.as_deref(), sort, after, limit).await?;
+    let next = if rows.len() as i64 == limit {
+        rows.last().map(|(space, key)| format!("{}_{}", key, space.space.id))
+    } else {
+        None
+    };
+    let spaces = rows.into_iter().map(|(space, _)| space).collect();
+    Ok(ExplorePage { spaces, next })
+}
+
 async fn search(req: Request<Body>) -> Result<Vec<Space>, AppError> {
     let SearchParams { search } = parse_query(req.uri()).unwrap();
     let mut conn = database::get().await?;
@@ -120,6 +168,131 @@ async fn create(req: Request<Body>) -> Result<SpaceWithMember, AppError> {
     Ok(SpaceWithMember { space, member, user })
 }
 
+/// Create a space owned by the user with the settings, roles and channels of the source space.
+async fn clone_space<T: Querist>(
+    db: &mut T,
+    source: &Space,
+    user: &User,
+    name: String,
+    include_documents: bool,
+) -> Result<(Space, SpaceMember), AppError> {
+    let description = source.description.clone();
+    let default_dice_type = Some(&*source.default_dice_type);
+    let space = Space::create(db, name, &user.id, description, None, default_dice_type).await?;
+    let space = Space::edit(
+        db,
+        space.id,
+        None,
+        None,
+        None,
+        Some(source.explorable),
+        Some(source.is_public),
+        Some(source.allow_spectator),
+        Some(source.language.clone()),
+    )
+    .await?
+    .ok_or_else(|| unexpected!("The new space not found."))?;
+    let member = SpaceMember::add_admin(db, &user.id, &space.id).await?;
+    SpaceRole::clone_space(db, &source.id, &space.id).await?;
+    for channel in Channel::clone_space(db, &source.id, &space.id, &user.id).await? {
+        ChannelMember::add_user(db, &user.id, &channel.id, "", true).await?;
+    }
+    ChannelRoleOverride::clone_space(db, &source.id, &space.id).await?;
+    if include_documents {
+        Message::copy_documents(db, &source.id, &space.id, &user.id).await?;
+    }
+    Ok((space, member))
+}
+
+async fn clone(req: Request<Body>) -> Result<SpaceWithMember, AppError> {
+    let session = authenticate(&req).await?;
+    let CloneSpace {
+        space_id,
+        name,
+        include_documents,
+    } = interface::parse_body(req).await?;
+
+    let mut conn = database::get().await?;
+    let mut trans = conn.transaction().await?;
+    let db = &mut trans;
+    let source = Space::get_by_id(db, &space_id).await.or_not_found()?;
+    space_permission(db, &session.user_id, &space_id, Permissions::MANAGE_SPACE).await?;
+    let user = User::get_by_id(db, &session.user_id).await.or_not_found()?;
+    let (space, member) = clone_space(db, &source, &user, name, include_documents).await?;
+    trans.commit().await?;
+    log::info!("a space ({}) was cloned from {}", space.id, source.id);
+    Ok(SpaceWithMember { space, member, user })
+}
+
+async fn templates(req: Request<Body>) -> Result<Vec<SpaceTemplate>, AppError> {
+    let session = authenticate(&req).await?;
+    let mut conn = database::get().await?;
+    SpaceTemplate::get_available(&mut *conn, &session.user_id)
+        .await
+        .map_err(Into::into)
+}
+
+async fn save_template(req: Request<Body>) -> Result<SpaceTemplate, AppError> {
+    let session = authenticate(&req).await?;
+    let SaveTemplate {
+        space_id,
+        name,
+        description,
+        is_public,
+        include_documents,
+    } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    space_permission(db, &session.user_id, &space_id, Permissions::MANAGE_SPACE).await?;
+    let template = SpaceTemplate::create(
+        db,
+        &space_id,
+        &session.user_id,
+        &*name,
+        &*description,
+        is_public,
+        include_documents,
+    )
+    .await?;
+    Ok(template)
+}
+
+async fn delete_template(req: Request<Body>) -> Result<bool, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let template = SpaceTemplate::get(db, &id).await.or_not_found()?;
+    if template.creator_id != session.user_id {
+        space_permission(db, &session.user_id, &template.space_id, Permissions::MANAGE_SPACE).await?;
+    }
+    SpaceTemplate::delete(db, &id).await?;
+    Ok(true)
+}
+
+async fn instantiate_template(req: Request<Body>) -> Result<SpaceWithMember, AppError> {
+    let session = authenticate(&req).await?;
+    let InstantiateTemplate { template_id, name } = interface::parse_body(req).await?;
+
+    let mut conn = database::get().await?;
+    let mut trans = conn.transaction().await?;
+    let db = &mut trans;
+    let template = SpaceTemplate::get(db, &template_id).await.or_not_found()?;
+    if !template.is_public && template.creator_id != session.user_id {
+        return Err(AppError::NoPermission(format!("A private template")));
+    }
+    let source = Space::get_by_id(db, &template.space_id).await.or_not_found()?;
+    // A template instantiates the current state of its source, which stays private unless the space is public.
+    if template.creator_id != session.user_id && !source.is_public {
+        return Err(AppError::NoPermission(format!("The source space is not public")));
+    }
+    let user = User::get_by_id(db, &session.user_id).await.or_not_found()?;
+    let (space, member) = clone_space(db, &source, &user, name, template.include_documents).await?;
+    trans.commit().await?;
+    log::info!("a space ({}) was created from template {}", space.id, template.id);
+    Ok(SpaceWithMember { space, member, user })
+}
+
 async fn edit(req: Request<Body>) -> Result<Space, AppError> {
     let session = authenticate(&req).await?;
     let Edit {
@@ -130,6 +303,8 @@ async fn edit(req: Request<Body>) -> Result<Space, AppError> {
         explorable,
         is_public,
         allow_spectator,
+        language,
+        password,
         grant_admins,
         remove_admins,
     }: Edit = interface::parse_body(req).await?;
@@ -138,11 +313,10 @@ async fn edit(req: Request<Body>) -> Result<Space, AppError> {
     let mut trans = conn.transaction().await?;
     let db = &mut trans;
 
-    let space_member = SpaceMember::get(db, &session.user_id, &space_id)
-        .await
-        .or_no_permission()?;
-    if !space_member.is_admin {
-        return Err(AppError::NoPermission(format!("A non-admin tries to edit space")));
+    space_permission(db, &session.user_id, &space_id, Permissions::MANAGE_SPACE).await?;
+    let before = Space::get_by_id(db, &space_id).await.or_not_found()?;
+    if before.archived && !(grant_admins.is_empty() && remove_admins.is_empty()) {
+        return Err(AppError::Archived("space"));
     }
     let space = Space::edit(
         db,
@@ -153,17 +327,33 @@ async fn edit(req: Request<Body>) -> Result<Space, AppError> {
         explorable,
         is_public,
         allow_spectator,
+        language,
     )
     .await?
     .ok_or_else(|| unexpected!("No such space found."))?;
+    let mut changed = audit::diff(&before, &space);
+    if let Some(password) = password {
+        Space::set_password(db, &space_id, &*password).await?;
+        changed["password"] = serde_json::json!({ "removed": password.is_empty() });
+    }
+    if changed.as_object().map_or(false, |changed| !changed.is_empty()) {
+        let action = AuditAction::SpaceEdited;
+        AuditLog::record(db, &space_id, &session.user_id, action, None, None, changed).await?;
+    }
 
     if space.owner_id == session.user_id {
         for user_id in grant_admins.iter() {
-            SpaceMember::set_admin(db, &space_id, user_id, true).await?;
+            if SpaceMember::set_admin(db, user_id, &space_id, true).await?.is_some() {
+                let action = AuditAction::AdminGranted;
+                let payload = serde_json::json!({});
+                AuditLog::record(db, &space_id, &session.user_id, action, None, Some(user_id), payload).await?;
+            }
         }
         for user_id in remove_admins.iter() {
-            if user_id != &space.owner_id {
-                SpaceMember::set_admin(db, &space_id, user_id, false).await?;
+            if user_id != &space.owner_id && SpaceMember::set_admin(db, user_id, &space_id, false).await?.is_some() {
+                let action = AuditAction::AdminRevoked;
+                let payload = serde_json::json!({});
+                AuditLog::record(db, &space_id, &session.user_id, action, None, Some(user_id), payload).await?;
             }
         }
     }
@@ -173,30 +363,279 @@ async fn edit(req: Request<Body>) -> Result<Space, AppError> {
     Ok(space)
 }
 
+async fn archive(req: Request<Body>, archived: bool) -> Result<Space, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    space_permission(db, &session.user_id, &id, Permissions::MANAGE_SPACE).await?;
+    let space = Space::set_archived(db, &id, archived).await?.or_not_found()?;
+    let action = if archived {
+        AuditAction::SpaceArchived
+    } else {
+        AuditAction::SpaceUnarchived
+    };
+    let payload = serde_json::json!({});
+    AuditLog::record(db, &id, &session.user_id, action, None, None, payload).await?;
+    log::info!("space {} was {}", id, if archived { "archived" } else { "unarchived" });
+    Event::space_updated(id);
+    Ok(space)
+}
+
+/// Limit password attempts of a user on a space, 5 attempts per 10 minutes.
+async fn password_limit(space_id: &Uuid, user_id: &Uuid) -> Result<(), AppError> {
+    let mut cache = crate::cache::conn().await;
+    let mut key = make_key(b"space", space_id, b"password_attempts:");
+    key.extend_from_slice(user_id.as_bytes());
+    let counter: i32 = cache.inner.incr(&key, 1).await?;
+    if counter == 1 {
+        cache.inner.expire::<_, ()>(&key, 60 * 10).await?;
+    }
+    if counter > 5 {
+        return Err(AppError::LimitExceeded("password attempts"));
+    }
+    Ok(())
+}
+
 async fn join(req: Request<Body>) -> Result<SpaceWithMember, AppError> {
     let session = authenticate(&req).await?;
     let Join { space_id, token } = parse_query(req.uri())?;
+    let is_json = req
+        .headers()
+        .get(hyper::header::CONTENT_TYPE)
+        .map_or(false, |value| value.as_bytes().starts_with(b"application/json"));
+    let password = if is_json {
+        let JoinWithPassword { password } = interface::parse_body(req).await?;
+        Some(password)
+    } else {
+        None
+    };
 
-    let mut db = database::get().await?;
-    let db = &mut *db;
+    let mut conn = database::get().await?;
+    let mut trans = conn.transaction().await?;
+    let db = &mut trans;
 
     let space = Space::get_by_id(db, &space_id).await?.or_not_found()?;
-    if !space.is_public && token != Some(space.invite_token) && space.owner_id != session.user_id {
+    if space.archived {
+        return Err(AppError::Archived("space"));
+    }
+    let user_id = &session.user_id;
+    let is_member = SpaceMember::get(db, user_id, &space_id).await?.is_some();
+    let is_legacy_token = token == Some(space.invite_token);
+    // Joining again doesn't use up the invite.
+    let invite = match token {
+        Some(token) if !is_member && !is_legacy_token => SpaceInvite::consume(db, &token, &space_id).await?,
+        _ => None,
+    };
+    let mut allowed = space.is_public || is_member || is_legacy_token || invite.is_some() || &space.owner_id == user_id;
+    if let (false, Some(password)) = (allowed, password) {
+        password_limit(&space_id, user_id).await?;
+        allowed = Space::check_password(db, &space_id, &*password).await?;
+    }
+    if !allowed {
         return Err(AppError::NoPermission(format!(
-            "A user tries to join group without token"
+            "A user tries to join group without a valid invite or password"
         )));
     }
-    let user_id = &session.user_id;
     let user = User::get_by_id(db, user_id).await?.ok_or_else(|| unexpected!("No such user found."))?;
     let member = if &space.owner_id == user_id {
         SpaceMember::add_admin(db, user_id, &space_id).await?
     } else {
         SpaceMember::add_user(db, user_id, &space_id).await?
     };
+    let mut joined_channels = Vec::new();
+    if let Some(invite) = invite {
+        for channel_id in invite.channel_ids.iter() {
+            let channel = Channel::get_by_id(db, channel_id).await?;
+            if channel.map_or(false, |channel| channel.space_id == space_id) {
+                ChannelMember::add_user(db, user_id, channel_id, "", false).await?;
+                joined_channels.push(*channel_id);
+            }
+        }
+    }
+    trans.commit().await?;
     Event::space_updated(space_id);
+    for channel_id in joined_channels {
+        Event::push_members(channel_id);
+    }
     Ok(SpaceWithMember { space, member, user })
 }
 
+async fn request_join(req: Request<Body>) -> Result<JoinRequest, AppError> {
+    let session = authenticate(&req).await?;
+    let RequestJoin { space_id, message } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let space = Space::get_by_id(db, &space_id).await.or_not_found()?;
+    if space.archived {
+        return Err(AppError::Archived("space"));
+    }
+    if space.is_public {
+        return Err(AppError::BadRequest(
+            "The space is public, join it directly".to_string(),
+        ));
+    }
+    if SpaceMember::get(db, &session.user_id, &space_id).await?.is_some() {
+        return Err(AppError::BadRequest(
+            "You are already a member of the space".to_string(),
+        ));
+    }
+    let request = JoinRequest::create(db, &space_id, &session.user_id, &*message).await?;
+    let user = User::get_by_id(db, &session.user_id)
+        .await?
+        .ok_or_else(|| unexpected!("No such user found."))?;
+    let recipients = Permissions::members_with(db, &space_id, Permissions::MANAGE_MEMBERS).await?;
+    let payload = serde_json::json!({
+        "requestId": request.id,
+        "userId": user.id,
+        "nickname": user.nickname,
+        "message": request.message,
+        "spaceName": space.name,
+    });
+    let mut notifications = Vec::new();
+    for recipient in &recipients {
+        let kind = DbEventType::JoinRequested;
+        notifications.push(DbEvent::create(db, kind, recipient, Some(&space_id), None, payload.clone()).await?);
+    }
+    notifications.into_iter().for_each(Event::notify);
+    Ok(request)
+}
+
+async fn join_requests(req: Request<Body>) -> Result<Vec<JoinRequestWithUser>, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    space_permission(db, &session.user_id, &id, Permissions::MANAGE_MEMBERS).await?;
+    let requests = JoinRequest::get_pending(db, &id).await?;
+    Ok(requests
+        .into_iter()
+        .map(|(request, user)| JoinRequestWithUser { request, user })
+        .collect())
+}
+
+async fn my_join_requests(req: Request<Body>) -> Result<Vec<JoinRequest>, AppError> {
+    let session = authenticate(&req).await?;
+    let mut conn = database::get().await?;
+    JoinRequest::get_by_user(&mut *conn, &session.user_id)
+        .await
+        .map_err(Into::into)
+}
+
+async fn decide_join_request(req: Request<Body>) -> Result<JoinRequest, AppError> {
+    let session = authenticate(&req).await?;
+    let DecideJoinRequest { request_id, approve } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let mut trans = conn.transaction().await?;
+    let db = &mut trans;
+    let request = JoinRequest::get(db, &request_id).await.or_not_found()?;
+    let space_id = request.space_id;
+    space_permission(db, &session.user_id, &space_id, Permissions::MANAGE_MEMBERS).await?;
+    Space::ensure_active(db, &space_id).await?;
+    let request = JoinRequest::decide(db, &request_id, approve, &session.user_id)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("The request has already been decided".to_string()))?;
+    let action = if approve {
+        SpaceMember::add_user(db, &request.user_id, &space_id).await?;
+        AuditAction::JoinRequestApproved
+    } else {
+        AuditAction::JoinRequestRejected
+    };
+    let payload = serde_json::json!({ "message": request.message });
+    AuditLog::record(
+        db,
+        &space_id,
+        &session.user_id,
+        action,
+        None,
+        Some(&request.user_id),
+        payload,
+    )
+    .await?;
+    let space = Space::get_by_id(db, &space_id).await.or_not_found()?;
+    let payload = serde_json::json!({ "requestId": request.id, "approved": approve, "spaceName": space.name });
+    let kind = DbEventType::JoinRequestDecided;
+    let notification = DbEvent::create(db, kind, &request.user_id, Some(&space_id), None, payload).await?;
+    trans.commit().await?;
+    if approve {
+        Event::space_updated(space_id);
+    }
+    Event::notify(notification);
+    Ok(request)
+}
+
+async fn cancel_join_request(req: Request<Body>) -> Result<bool, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let request = JoinRequest::get(db, &id).await.or_not_found()?;
+    if request.user_id != session.user_id {
+        return Err(AppError::NoPermission(format!("The request is not yours")));
+    }
+    Ok(JoinRequest::remove(db, &id).await? > 0)
+}
+
+async fn invites(req: Request<Body>) -> Result<Vec<SpaceInvite>, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    space_permission(db, &session.user_id, &id, Permissions::INVITE).await?;
+    SpaceInvite::get_by_space(db, &id).await.map_err(Into::into)
+}
+
+async fn create_invite(req: Request<Body>) -> Result<SpaceInvite, AppError> {
+    let session = authenticate(&req).await?;
+    let CreateInvite {
+        space_id,
+        label,
+        max_uses,
+        channel_ids,
+        expires_at,
+    } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    space_permission(db, &session.user_id, &space_id, Permissions::INVITE).await?;
+    for channel_id in channel_ids.iter() {
+        let channel = Channel::get_by_id(db, channel_id).await?;
+        if !channel.map_or(false, |channel| channel.space_id == space_id) {
+            return Err(AppError::BadRequest(format!(
+                "Channel {} is not in the space",
+                channel_id
+            )));
+        }
+    }
+    let invite = SpaceInvite::create(
+        db,
+        &space_id,
+        &session.user_id,
+        &*label,
+        max_uses,
+        &*channel_ids,
+        expires_at,
+    )
+    .await?;
+    let payload = serde_json::json!({ "label": invite.label, "maxUses": invite.max_uses });
+    let action = AuditAction::InviteCreated;
+    AuditLog::record(db, &space_id, &session.user_id, action, None, Some(&invite.id), payload).await?;
+    Ok(invite)
+}
+
+async fn revoke_invite(req: Request<Body>) -> Result<SpaceInvite, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let invite = SpaceInvite::get(db, &id).await.or_not_found()?;
+    space_permission(db, &session.user_id, &invite.space_id, Permissions::INVITE).await?;
+    let invite = SpaceInvite::revoke(db, &id).await.or_not_found()?;
+    let payload = serde_json::json!({ "label": invite.label, "uses": invite.uses });
+    let action = AuditAction::InviteRevoked;
+    AuditLog::record(db, &invite.space_id, &session.user_id, action, None, Some(&id), payload).await?;
+    Ok(invite)
+}
+
 async fn leave(req: Request<Body>) -> Result<bool, AppError> {
     let session = authenticate(&req).await?;
     let IdQuery { id } = parse_query(req.uri())?;
@@ -205,6 +644,7 @@ async fn leave(req: Request<Body>) -> Result<bool, AppError> {
     let mut trans = conn.transaction().await?;
     let db = &mut trans;
 
+    Space::ensure_active(db, &id).await?;
     let channels = SpaceMember::remove_user(db, &session.user_id, &id).await?;
     trans.commit().await?;
     Event::space_updated(id);
@@ -221,22 +661,22 @@ async fn kick(req: Request<Body>) -> Result<bool, AppError> {
     let mut conn = database::get().await?;
     let mut trans = conn.transaction().await?;
     let db = &mut trans;
-    let my_member = SpaceMember::get(db, &session.user_id, &space_id).await.or_not_found()?;
+    space_permission(db, &session.user_id, &space_id, Permissions::MANAGE_MEMBERS).await?;
+    Space::ensure_active(db, &space_id).await?;
     let kick_member = SpaceMember::get(db, &user_id, &space_id).await.or_not_found()?;
     if kick_member.is_admin {
         return Err(AppError::BadRequest("Can't kick admin".to_string()));
     }
-    if my_member.is_admin {
-        let channels = SpaceMember::remove_user(db, &user_id, &space_id).await?;
-        trans.commit().await?;
-        Event::space_updated(space_id);
-        for channel_id in channels {
-            Event::push_members(channel_id);
-        }
-        Ok(true)
-    } else {
-        Err(AppError::NoPermission(format!("A non-admin tries to kick")))
+    let channels = SpaceMember::remove_user(db, &user_id, &space_id).await?;
+    let payload = serde_json::json!({});
+    let action = AuditAction::MemberKicked;
+    AuditLog::record(db, &space_id, &session.user_id, action, None, Some(&user_id), payload).await?;
+    trans.commit().await?;
+    Event::space_updated(space_id);
+    for channel_id in channels {
+        Event::push_members(channel_id);
     }
+    Ok(true)
 }
 
 async fn members(req: Request<Body>) -> Result<HashMap<Uuid, SpaceMemberWithUser>, AppError> {
@@ -248,6 +688,225 @@ async fn members(req: Request<Body>) -> Result<HashMap<Uuid, SpaceMemberWithUser
         .map_err(Into::into)
 }
 
+async fn transfer_ownership(req: Request<Body>) -> Result<OwnershipTransfer, AppError> {
+    let session = authenticate(&req).await?;
+    let TransferOwnership {
+        space_id,
+        user_id,
+        confirm_name,
+    } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let space = Space::get_by_id(db, &space_id).await.or_not_found()?;
+    if space.owner_id != session.user_id {
+        return Err(AppError::NoPermission(format!("Only the owner can transfer the space")));
+    }
+    if user_id == session.user_id {
+        return Err(AppError::BadRequest("The space is already yours".to_string()));
+    }
+    if confirm_name.trim() != space.name {
+        return Err(AppError::BadRequest("The space name does not match".to_string()));
+    }
+    SpaceMember::get(db, &user_id, &space_id)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("The new owner must be a member of the space".to_string()))?;
+    let transfer = OwnershipTransfer::create(db, &space_id, &session.user_id, &user_id).await?;
+    Event::space_updated(space_id);
+    Ok(transfer)
+}
+
+async fn ownership_transfer(req: Request<Body>) -> Result<Option<OwnershipTransfer>, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    SpaceMember::get(db, &session.user_id, &id).await.or_no_permission()?;
+    OwnershipTransfer::get(db, &id).await.map_err(Into::into)
+}
+
+async fn accept_ownership(req: Request<Body>) -> Result<Space, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let mut trans = conn.transaction().await?;
+    let db = &mut trans;
+    let transfer = OwnershipTransfer::get(db, &id).await.or_not_found()?;
+    if transfer.to_user_id != session.user_id {
+        return Err(AppError::NoPermission(format!("The transfer is not for you")));
+    }
+    let space = Space::get_by_id(db, &id).await.or_not_found()?;
+    SpaceMember::get(db, &session.user_id, &id).await.or_no_permission()?;
+    OwnershipTransfer::remove(db, &id).await?;
+    if space.owner_id != transfer.from_user_id {
+        trans.commit().await?;
+        return Err(AppError::BadRequest("The space has changed hands since".to_string()));
+    }
+    let space = Space::set_owner(db, &id, &session.user_id)
+        .await?
+        .ok_or_else(|| unexpected!("No such space found."))?;
+    // The previous owner stays an admin.
+    SpaceMember::set_admin(db, &session.user_id, &id, true).await?;
+    SpaceMember::set_admin(db, &transfer.from_user_id, &id, true).await?;
+    let payload = serde_json::json!({ "from": transfer.from_user_id });
+    let action = AuditAction::OwnershipTransferred;
+    AuditLog::record(db, &id, &session.user_id, action, None, Some(&session.user_id), payload).await?;
+    trans.commit().await?;
+    log::info!("space {} was transferred to {}", id, session.user_id);
+    Event::space_updated(id);
+    Ok(space)
+}
+
+/// Cancelled by the owner or declined by the target.
+async fn cancel_ownership_transfer(req: Request<Body>) -> Result<bool, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let transfer = OwnershipTransfer::get(db, &id).await.or_not_found()?;
+    if transfer.from_user_id != session.user_id && transfer.to_user_id != session.user_id {
+        return Err(AppError::NoPermission(format!("The transfer is not yours")));
+    }
+    OwnershipTransfer::remove(db, &id).await?;
+    Event::space_updated(id);
+    Ok(true)
+}
+
+async fn roles(req: Request<Body>) -> Result<Vec<SpaceRole>, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    SpaceMember::get(db, &session.user_id, &id).await.or_no_permission()?;
+    SpaceRole::get_by_space(db, &id).await.map_err(Into::into)
+}
+
+async fn my_permissions(req: Request<Body>) -> Result<Permissions, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    Permissions::space(&mut *conn, &session.user_id, &id)
+        .await?
+        .or_no_permission()
+}
+
+/// Members with `MANAGE_ROLES` can only hand out the permissions they hold themselves.
+async fn role_manager<T: Querist>(
+    db: &mut T,
+    user_id: &Uuid,
+    space_id: &Uuid,
+    permissions: Permissions,
+) -> Result<(), AppError> {
+    let mine = space_permission(db, user_id, space_id, Permissions::MANAGE_ROLES).await?;
+    if !mine.contains(permissions) {
+        return Err(AppError::NoPermission(format!(
+            "Can't manage a role with permissions beyond your own"
+        )));
+    }
+    Ok(())
+}
+
+async fn create_role(req: Request<Body>) -> Result<SpaceRole, AppError> {
+    let session = authenticate(&req).await?;
+    let CreateRole {
+        space_id,
+        name,
+        permissions,
+    } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    role_manager(db, &session.user_id, &space_id, permissions).await?;
+    let role = SpaceRole::create(db, &space_id, &*name, permissions).await?;
+    let payload = serde_json::json!({ "name": role.name, "permissions": role.permissions });
+    let action = AuditAction::RoleCreated;
+    AuditLog::record(db, &space_id, &session.user_id, action, None, Some(&role.id), payload).await?;
+    Event::space_updated(space_id);
+    Ok(role)
+}
+
+async fn edit_role(req: Request<Body>) -> Result<SpaceRole, AppError> {
+    let session = authenticate(&req).await?;
+    let EditRole {
+        role_id,
+        name,
+        permissions,
+    } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let before = SpaceRole::get(db, &role_id).await.or_not_found()?;
+    let touched = before.permissions | permissions.unwrap_or_default();
+    role_manager(db, &session.user_id, &before.space_id, touched).await?;
+    let role = SpaceRole::edit(db, &role_id, name.as_deref(), permissions)
+        .await?
+        .or_not_found()?;
+    let changed = audit::diff(&before, &role);
+    let action = AuditAction::RoleEdited;
+    AuditLog::record(
+        db,
+        &role.space_id,
+        &session.user_id,
+        action,
+        None,
+        Some(&role_id),
+        changed,
+    )
+    .await?;
+    Event::space_updated(role.space_id);
+    Ok(role)
+}
+
+async fn delete_role(req: Request<Body>) -> Result<bool, AppError> {
+    let session = authenticate(&req).await?;
+    let IdQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let role = SpaceRole::get(db, &id).await.or_not_found()?;
+    if role.kind != RoleKind::Custom {
+        return Err(AppError::BadRequest("Built-in roles can't be deleted".to_string()));
+    }
+    role_manager(db, &session.user_id, &role.space_id, role.permissions).await?;
+    SpaceRole::delete(db, &id).await?;
+    let payload = serde_json::json!({ "name": role.name, "permissions": role.permissions });
+    let action = AuditAction::RoleDeleted;
+    AuditLog::record(db, &role.space_id, &session.user_id, action, None, Some(&id), payload).await?;
+    Event::space_updated(role.space_id);
+    Ok(true)
+}
+
+async fn assign_role(req: Request<Body>, assign: bool) -> Result<bool, AppError> {
+    let session = authenticate(&req).await?;
+    let AssignRole { role_id, user_id } = interface::parse_body(req).await?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let role = SpaceRole::get(db, &role_id).await.or_not_found()?;
+    if role.kind != RoleKind::Custom {
+        return Err(AppError::BadRequest(
+            "Built-in roles are held through the admin and master flags".to_string(),
+        ));
+    }
+    role_manager(db, &session.user_id, &role.space_id, role.permissions).await?;
+    let action = if assign {
+        SpaceMember::get(db, &user_id, &role.space_id).await.or_not_found()?;
+        SpaceRole::assign(db, &user_id, &role.space_id, &role_id).await?;
+        AuditAction::RoleAssigned
+    } else {
+        SpaceRole::unassign(db, &user_id, &role.space_id, &role_id).await?;
+        AuditAction::RoleUnassigned
+    };
+    let payload = serde_json::json!({ "roleId": role_id, "roleName": role.name });
+    AuditLog::record(
+        db,
+        &role.space_id,
+        &session.user_id,
+        action,
+        None,
+        Some(&user_id),
+        payload,
+    )
+    .await?;
+    Event::space_updated(role.space_id);
+    Ok(true)
+}
+
 async fn delete(req: Request<Body>) -> Result<Space, AppError> {
     let IdQuery { id } = parse_query(req.uri())?;
     let mut conn = database::get().await?;
@@ -274,6 +933,7 @@ pub async fn router(req: Request<Body>, path: &str) -> Result<Response, AppError
         ("/refresh_token", Method::POST) => refresh_token(req).await.map(ok_response),
         ("/my", Method::GET) => my_spaces(req).await.map(ok_response),
         ("/search", Method::GET) => search(req).await.map(ok_response),
+        ("/explore", Method::GET) => explore(req).await.map(ok_response),
         ("/create", Method::POST) => create(req).await.map(ok_response),
         ("/edit", Method::POST) => edit(req).await.map(ok_response),
         ("/join", Method::POST) => join(req).await.map(ok_response),
@@ -281,6 +941,32 @@ pub async fn router(req: Request<Body>, path: &str) -> Result<Response, AppError
         ("/kick", Method::POST) => kick(req).await.map(ok_response),
         ("/members", Method::GET) => members(req).await.map(ok_response),
         ("/delete", Method::POST) => delete(req).await.map(ok_response),
+        ("/archive", Method::POST) => archive(req, true).await.map(ok_response),
+        ("/unarchive", Method::POST) => archive(req, false).await.map(ok_response),
+        ("/clone", Method::POST) => clone(req).await.map(ok_response),
+        ("/templates", Method::GET) => templates(req).await.map(ok_response),
+        ("/save_template", Method::POST) => save_template(req).await.map(ok_response),
+        ("/delete_template", Method::POST) => delete_template(req).await.map(ok_response),
+        ("/instantiate_template", Method::POST) => instantiate_template(req).await.map(ok_response),
+        ("/transfer_ownership", Method::POST) => transfer_ownership(req).await.map(ok_response),
+        ("/ownership_transfer", Method::GET) => ownership_transfer(req).await.map(ok_response),
+        ("/accept_ownership", Method::POST) => accept_ownership(req).await.map(ok_response),
+        ("/cancel_ownership_transfer", Method::POST) => cancel_ownership_transfer(req).await.map(ok_response),
+        ("/request_join", Method::POST) => request_join(req).await.map(ok_response),
+        ("/join_requests", Method::GET) => join_requests(req).await.map(ok_response),
+        ("/my_join_requests", Method::GET) => my_join_requests(req).await.map(ok_response),
+        ("/decide_join_request", Method::POST) => decide_join_request(req).await.map(ok_response),
+        ("/cancel_join_request", Method::POST) => cancel_join_request(req).await.map(ok_response),
+        ("/invites", Method::GET) => invites(req).await.map(ok_response),
+        ("/create_invite", Method::POST) => create_invite(req).await.map(ok_response),
+        ("/revoke_invite", Method::POST) => revoke_invite(req).await.map(ok_response),
+        ("/roles", Method::GET) => roles(req).await.map(ok_response),
+        ("/my_permissions", Method::GET) => my_permissions(req).await.map(ok_response),
+        ("/create_role", Method::POST) => create_role(req).await.map(ok_response),
+        ("/edit_role", Method::POST) => edit_role(req).await.map(ok_response),
+        ("/delete_role", Method::POST) => delete_role(req).await.map(ok_response),
+        ("/assign_role", Method::POST) => assign_role(req, true).await.map(ok_response),
+        ("/unassign_role", Method::POST) => assign_role(req, false).await.map(ok_response),
         _ => missing(),
     }
 }
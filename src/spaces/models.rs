@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use std::convert::TryInto;
 
 use chrono::naive::NaiveDateTime;
-use postgres_types::FromSql;
+use postgres_types::{FromSql, ToSql};
 use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -10,8 +10,9 @@ use uuid::Uuid;
 use crate::cache::make_key;
 use crate::channels::ChannelMember;
 use crate::database::Querist;
-use crate::error::{AppError, DbError, ModelError};
-use crate::spaces::api::SpaceWithMember;
+use crate::error::{AppError, DbError, ModelError, ValidationFailed};
+use crate::spaces::api::{ExploreSort, SpaceWithMember, SpaceWithStats};
+use crate::spaces::Permissions;
 use crate::users::User;
 use crate::utils::{inner_result_map, merge_blank};
 
@@ -77,6 +78,7 @@ pub struct Space {
     pub deleted: bool,
     pub explorable: bool,
     pub allow_spectator: bool,
+    pub archived: bool,
 }
 
 impl Space {
@@ -88,9 +90,12 @@ impl Space {
         password: Option<String>,
         default_dice_type: Option<&str>,
     ) -> Result<Space, ModelError> {
-        use crate::validators::{DESCRIPTION, DICE, DISPLAY_NAME};
+        use crate::validators::{DESCRIPTION, DICE, DISPLAY_NAME, SPACE_PASSWORD};
         let name = merge_blank(&*name);
         DISPLAY_NAME.run(&name)?;
+        if let Some(password) = password.as_ref() {
+            SPACE_PASSWORD.run(password)?;
+        }
         if let Some(default_dice_type) = default_dice_type {
             DICE.run(default_dice_type)?;
         }
@@ -101,7 +106,9 @@ impl Space {
                 &[&name, owner_id, &password, &default_dice_type, &description],
             )
             .await?;
-        Ok(row.try_get(0)?)
+        let space: Space = row.try_get(0)?;
+        SpaceRole::create_builtin(db, &space.id).await?;
+        Ok(space)
     }
 
     pub async fn delete<T: Querist>(db: &mut T, id: &Uuid) -> Result<(), DbError> {
@@ -148,11 +155,49 @@ impl Space {
         row.try_get(0)
     }
 
+    pub async fn set_owner<T: Querist>(db: &mut T, id: &Uuid, owner_id: &Uuid) -> Result<Option<Space>, DbError> {
+        let result = db.query_one(include_str!("sql/set_owner.sql"), &[id, owner_id]).await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+
+    /// Set the password of the space, an empty password removes it.
+    pub async fn set_password<T: Querist>(db: &mut T, id: &Uuid, password: &str) -> Result<(), ModelError> {
+        use crate::validators::SPACE_PASSWORD;
+        SPACE_PASSWORD.run(password)?;
+        db.execute(include_str!("sql/set_password.sql"), &[id, &password])
+            .await?;
+        Ok(())
+    }
+
+    /// Always `false` for spaces without a password.
+    pub async fn check_password<T: Querist>(db: &mut T, id: &Uuid, password: &str) -> Result<bool, DbError> {
+        let row = db
+            .query_one(include_str!("sql/check_password.sql"), &[id, &password])
+            .await?;
+        Ok(row.map_or(false, |row| row.get(0)))
+    }
+
     pub async fn is_public<T: Querist>(db: &mut T, id: &Uuid) -> Result<Option<bool>, DbError> {
         let row = db.query_one(include_str!("sql/is_public.sql"), &[id]).await?;
         Ok(row.map(|row| row.get(0)))
     }
 
+    pub async fn set_archived<T: Querist>(db: &mut T, id: &Uuid, archived: bool) -> Result<Option<Space>, DbError> {
+        let result = db
+            .query_one(include_str!("sql/set_archived.sql"), &[id, &archived])
+            .await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+
+    /// Rejects changes to an archived space.
+    pub async fn ensure_active<T: Querist>(db: &mut T, id: &Uuid) -> Result<(), AppError> {
+        let row = db.query_one(include_str!("sql/is_archived.sql"), &[id]).await?;
+        match row.map(|row| row.get(0)) {
+            Some(true) => Err(AppError::Archived("space")),
+            _ => Ok(()),
+        }
+    }
+
     pub async fn edit<T: Querist>(
         db: &mut T,
         space_id: Uuid,
@@ -162,6 +207,7 @@ impl Space {
         explorable: Option<bool>,
         is_public: Option<bool>,
         allow_spectator: Option<bool>,
+        language: Option<String>,
     ) -> Result<Option<Space>, ModelError> {
         use crate::validators;
         let name = name.as_ref().map(|s| s.trim());
@@ -175,6 +221,9 @@ impl Space {
         if let Some(dice) = default_dice_type.as_ref() {
             validators::DICE.run(dice)?;
         }
+        if let Some(language) = language.as_ref() {
+            validators::LANGUAGE.run(language)?;
+        }
         let result = db
             .query_one(
                 include_str!("sql/edit.sql"),
@@ -186,12 +235,48 @@ impl Space {
                     &explorable,
                     &is_public,
                     &allow_spectator,
+                    &language,
                 ],
             )
             .await?;
         Ok(result.map(|row| row.get(0)))
     }
 
+    /// Explorable public spaces with their stats and sort keys, as of the last `refresh_stats`.
+    pub async fn explore<T: Querist>(
+        db: &mut T,
+        language: Option<&str>,
+        dice_type: Option<&str>,
+        sort: ExploreSort,
+        after: Option<(i64, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<(SpaceWithStats, i64)>, DbError> {
+        let query = match sort {
+            ExploreSort::Activity => include_str!("sql/explore_by_activity.sql"),
+            ExploreSort::Members => include_str!("sql/explore_by_members.sql"),
+        };
+        let after_key = after.map(|(key, _)| key);
+        let after_id = after.map(|(_, id)| id);
+        let rows = db
+            .query(query, &[&language, &dice_type, &after_key, &after_id, &limit])
+            .await?;
+        rows.into_iter()
+            .map(|row| {
+                let space = SpaceWithStats {
+                    space: row.try_get(0)?,
+                    member_count: row.try_get(1)?,
+                    channel_count: row.try_get(2)?,
+                    last_message: row.try_get(3)?,
+                };
+                Ok((space, row.try_get(4)?))
+            })
+            .collect()
+    }
+
+    pub async fn refresh_stats<T: Querist>(db: &mut T) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/refresh_stats.sql"), &[]).await
+    }
+
     pub async fn search<T: Querist>(db: &mut T, search: String) -> Result<Vec<Space>, DbError> {
         // https://www.postgresql.org/docs/9.3/functions-matching.html
         let patterns: Vec<String> = search
@@ -331,6 +416,391 @@ impl SpaceMemberWithUser {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, FromSql, ToSql, Clone, Copy, PartialEq, Eq)]
+#[postgres(name = "space_role_kind")]
+pub enum RoleKind {
+    Custom,
+    /// Held by every member of the space.
+    Everyone,
+    /// Held by members whose `is_admin` is set.
+    Admin,
+    /// Held by channel members whose `is_master` is set, only applies inside that channel.
+    Master,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromSql, Clone)]
+#[serde(rename_all = "camelCase")]
+#[postgres(name = "space_roles")]
+pub struct SpaceRole {
+    pub id: Uuid,
+    pub space_id: Uuid,
+    pub name: String,
+    pub kind: RoleKind,
+    pub permissions: Permissions,
+    #[serde(with = "crate::date_format")]
+    pub created: NaiveDateTime,
+}
+
+impl SpaceRole {
+    pub async fn create_builtin<T: Querist>(db: &mut T, space_id: &Uuid) -> Result<Vec<SpaceRole>, DbError> {
+        let rows = db
+            .query(
+                include_str!("sql/create_builtin_roles.sql"),
+                &[
+                    space_id,
+                    &Permissions::DEFAULT_EVERYONE,
+                    &Permissions::DEFAULT_ADMIN,
+                    &Permissions::DEFAULT_MASTER,
+                ],
+            )
+            .await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+
+    pub async fn create<T: Querist>(
+        db: &mut T,
+        space_id: &Uuid,
+        name: &str,
+        permissions: Permissions,
+    ) -> Result<SpaceRole, ModelError> {
+        use crate::validators::DISPLAY_NAME;
+        let name = merge_blank(name);
+        DISPLAY_NAME.run(&name)?;
+        let row = db
+            .query_exactly_one(include_str!("sql/create_role.sql"), &[space_id, &name, &permissions])
+            .await?;
+        Ok(row.try_get(0)?)
+    }
+
+    pub async fn get<T: Querist>(db: &mut T, id: &Uuid) -> Result<Option<SpaceRole>, DbError> {
+        let result = db.query_one(include_str!("sql/get_role.sql"), &[id]).await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+
+    pub async fn get_by_space<T: Querist>(db: &mut T, space_id: &Uuid) -> Result<Vec<SpaceRole>, DbError> {
+        let rows = db
+            .query(include_str!("sql/get_roles_by_space.sql"), &[space_id])
+            .await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+
+    pub async fn edit<T: Querist>(
+        db: &mut T,
+        id: &Uuid,
+        name: Option<&str>,
+        permissions: Option<Permissions>,
+    ) -> Result<Option<SpaceRole>, ModelError> {
+        use crate::validators::DISPLAY_NAME;
+        let name = name.map(merge_blank);
+        if let Some(name) = &name {
+            DISPLAY_NAME.run(name)?;
+        }
+        let result = db
+            .query_one(include_str!("sql/edit_role.sql"), &[id, &name, &permissions])
+            .await;
+        Ok(inner_result_map(result, |row| row.try_get(0))?)
+    }
+
+    /// Built-in roles can't be deleted.
+    pub async fn delete<T: Querist>(db: &mut T, id: &Uuid) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/delete_role.sql"), &[id]).await
+    }
+
+    pub async fn assign<T: Querist>(
+        db: &mut T,
+        user_id: &Uuid,
+        space_id: &Uuid,
+        role_id: &Uuid,
+    ) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/assign_role.sql"), &[user_id, space_id, role_id])
+            .await
+    }
+
+    pub async fn unassign<T: Querist>(
+        db: &mut T,
+        user_id: &Uuid,
+        space_id: &Uuid,
+        role_id: &Uuid,
+    ) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/unassign_role.sql"), &[user_id, space_id, role_id])
+            .await
+    }
+
+    /// Copy custom roles and the permissions of built-in roles, but not who holds them.
+    pub async fn clone_space<T: Querist>(db: &mut T, from_space: &Uuid, to_space: &Uuid) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/clone_roles.sql"), &[from_space, to_space])
+            .await
+    }
+
+    /// Custom roles held by each member of the space.
+    pub async fn assignments<T: Querist>(db: &mut T, space_id: &Uuid) -> Result<HashMap<Uuid, Vec<Uuid>>, DbError> {
+        let rows = db
+            .query(include_str!("sql/get_role_assignments.sql"), &[space_id])
+            .await?;
+        Ok(rows.into_iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromSql, Clone)]
+#[serde(rename_all = "camelCase")]
+#[postgres(name = "space_invites")]
+pub struct SpaceInvite {
+    pub id: Uuid,
+    pub space_id: Uuid,
+    pub creator_id: Uuid,
+    pub label: String,
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    /// Channels the invited user joins along with the space.
+    pub channel_ids: Vec<Uuid>,
+    #[serde(with = "crate::date_format::option")]
+    pub expires_at: Option<NaiveDateTime>,
+    pub revoked: bool,
+    #[serde(with = "crate::date_format")]
+    pub created: NaiveDateTime,
+}
+
+impl SpaceInvite {
+    pub async fn create<T: Querist>(
+        db: &mut T,
+        space_id: &Uuid,
+        creator_id: &Uuid,
+        label: &str,
+        max_uses: Option<i32>,
+        channel_ids: &[Uuid],
+        expires_at: Option<NaiveDateTime>,
+    ) -> Result<SpaceInvite, ModelError> {
+        let label = merge_blank(label);
+        if label.chars().count() > 64 {
+            return Err(ValidationFailed("Label too long").into());
+        }
+        if max_uses.map_or(false, |max_uses| max_uses < 1) {
+            return Err(ValidationFailed("The maximum number of uses must be positive").into());
+        }
+        let row = db
+            .query_exactly_one(
+                include_str!("sql/create_invite.sql"),
+                &[space_id, creator_id, &label, &max_uses, &channel_ids, &expires_at],
+            )
+            .await?;
+        Ok(row.try_get(0)?)
+    }
+
+    pub async fn get<T: Querist>(db: &mut T, id: &Uuid) -> Result<Option<SpaceInvite>, DbError> {
+        let result = db.query_one(include_str!("sql/get_invite.sql"), &[id]).await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+
+    pub async fn get_by_space<T: Querist>(db: &mut T, space_id: &Uuid) -> Result<Vec<SpaceInvite>, DbError> {
+        let rows = db
+            .query(include_str!("sql/get_invites_by_space.sql"), &[space_id])
+            .await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+
+    pub async fn revoke<T: Querist>(db: &mut T, id: &Uuid) -> Result<Option<SpaceInvite>, DbError> {
+        let result = db.query_one(include_str!("sql/revoke_invite.sql"), &[id]).await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+
+    /// Count a use of the invite, `None` if it is revoked, expired or used up.
+    pub async fn consume<T: Querist>(db: &mut T, id: &Uuid, space_id: &Uuid) -> Result<Option<SpaceInvite>, DbError> {
+        let result = db.query_one(include_str!("sql/use_invite.sql"), &[id, space_id]).await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+}
+
+/// A pointer to a source space, others can instantiate a public template only while the space is public.
+#[derive(Debug, Serialize, Deserialize, FromSql, Clone)]
+#[serde(rename_all = "camelCase")]
+#[postgres(name = "space_templates")]
+pub struct SpaceTemplate {
+    pub id: Uuid,
+    pub space_id: Uuid,
+    pub creator_id: Uuid,
+    pub name: String,
+    pub description: String,
+    pub is_public: bool,
+    pub include_documents: bool,
+    #[serde(with = "crate::date_format")]
+    pub created: NaiveDateTime,
+}
+
+impl SpaceTemplate {
+    pub async fn create<T: Querist>(
+        db: &mut T,
+        space_id: &Uuid,
+        creator_id: &Uuid,
+        name: &str,
+        description: &str,
+        is_public: bool,
+        include_documents: bool,
+    ) -> Result<SpaceTemplate, ModelError> {
+        use crate::validators::{DESCRIPTION, DISPLAY_NAME};
+        let name = merge_blank(name);
+        DISPLAY_NAME.run(&name)?;
+        let description = description.trim();
+        DESCRIPTION.run(description)?;
+        let row = db
+            .query_exactly_one(
+                include_str!("sql/create_template.sql"),
+                &[
+                    space_id,
+                    creator_id,
+                    &name,
+                    &description,
+                    &is_public,
+                    &include_documents,
+                ],
+            )
+            .await?;
+        Ok(row.try_get(0)?)
+    }
+
+    pub async fn get<T: Querist>(db: &mut T, id: &Uuid) -> Result<Option<SpaceTemplate>, DbError> {
+        let result = db.query_one(include_str!("sql/get_template.sql"), &[id]).await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+
+    /// Public templates and templates created by the user.
+    pub async fn get_available<T: Querist>(db: &mut T, user_id: &Uuid) -> Result<Vec<SpaceTemplate>, DbError> {
+        let rows = db.query(include_str!("sql/get_templates.sql"), &[user_id]).await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+
+    pub async fn delete<T: Querist>(db: &mut T, id: &Uuid) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/delete_template.sql"), &[id]).await
+    }
+}
+
+/// A pending ownership transfer, the space changes hands once the target accepts it.
+#[derive(Debug, Serialize, Deserialize, FromSql, Clone)]
+#[serde(rename_all = "camelCase")]
+#[postgres(name = "space_ownership_transfers")]
+pub struct OwnershipTransfer {
+    pub space_id: Uuid,
+    pub from_user_id: Uuid,
+    pub to_user_id: Uuid,
+    #[serde(with = "crate::date_format")]
+    pub created: NaiveDateTime,
+}
+
+impl OwnershipTransfer {
+    /// Replaces the pending transfer of the space, if any.
+    pub async fn create<T: Querist>(
+        db: &mut T,
+        space_id: &Uuid,
+        from_user_id: &Uuid,
+        to_user_id: &Uuid,
+    ) -> Result<OwnershipTransfer, DbError> {
+        let row = db
+            .query_exactly_one(
+                include_str!("sql/create_transfer.sql"),
+                &[space_id, from_user_id, to_user_id],
+            )
+            .await?;
+        row.try_get(0)
+    }
+
+    pub async fn get<T: Querist>(db: &mut T, space_id: &Uuid) -> Result<Option<OwnershipTransfer>, DbError> {
+        let result = db.query_one(include_str!("sql/get_transfer.sql"), &[space_id]).await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+
+    pub async fn remove<T: Querist>(db: &mut T, space_id: &Uuid) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/remove_transfer.sql"), &[space_id]).await
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromSql, ToSql, Clone, Copy, PartialEq, Eq)]
+#[postgres(name = "join_request_status")]
+pub enum JoinRequestStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// A request to join a non-public space, decided by a member who can manage members.
+#[derive(Debug, Serialize, Deserialize, FromSql, Clone)]
+#[serde(rename_all = "camelCase")]
+#[postgres(name = "space_join_requests")]
+pub struct JoinRequest {
+    pub id: Uuid,
+    pub space_id: Uuid,
+    pub user_id: Uuid,
+    pub message: String,
+    pub status: JoinRequestStatus,
+    pub decided_by: Option<Uuid>,
+    #[serde(with = "crate::date_format")]
+    pub created: NaiveDateTime,
+    #[serde(with = "crate::date_format::option")]
+    pub decided: Option<NaiveDateTime>,
+}
+
+impl JoinRequest {
+    /// Updates the message if the user already has a pending request to the space.
+    pub async fn create<T: Querist>(
+        db: &mut T,
+        space_id: &Uuid,
+        user_id: &Uuid,
+        message: &str,
+    ) -> Result<JoinRequest, ModelError> {
+        use crate::validators::JOIN_REQUEST;
+        let message = message.trim();
+        JOIN_REQUEST.run(message)?;
+        let row = db
+            .query_exactly_one(
+                include_str!("sql/create_join_request.sql"),
+                &[space_id, user_id, &message],
+            )
+            .await?;
+        row.try_get(0).map_err(Into::into)
+    }
+
+    pub async fn get<T: Querist>(db: &mut T, id: &Uuid) -> Result<Option<JoinRequest>, DbError> {
+        let result = db.query_one(include_str!("sql/get_join_request.sql"), &[id]).await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+
+    pub async fn get_pending<T: Querist>(db: &mut T, space_id: &Uuid) -> Result<Vec<(JoinRequest, User)>, DbError> {
+        let rows = db
+            .query(include_str!("sql/get_pending_join_requests.sql"), &[space_id])
+            .await?;
+        rows.into_iter()
+            .map(|row| Ok((row.try_get(0)?, row.try_get(1)?)))
+            .collect()
+    }
+
+    pub async fn get_by_user<T: Querist>(db: &mut T, user_id: &Uuid) -> Result<Vec<JoinRequest>, DbError> {
+        let rows = db
+            .query(include_str!("sql/get_join_requests_by_user.sql"), &[user_id])
+            .await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+
+    /// Returns `None` if the request has already been decided.
+    pub async fn decide<T: Querist>(
+        db: &mut T,
+        id: &Uuid,
+        approve: bool,
+        decided_by: &Uuid,
+    ) -> Result<Option<JoinRequest>, DbError> {
+        let status = if approve {
+            JoinRequestStatus::Approved
+        } else {
+            JoinRequestStatus::Rejected
+        };
+        let result = db
+            .query_one(include_str!("sql/decide_join_request.sql"), &[id, &status, decided_by])
+            .await;
+        inner_result_map(result, |row| row.try_get(0))
+    }
+
+    pub async fn remove<T: Querist>(db: &mut T, id: &Uuid) -> Result<u64, DbError> {
+        db.execute(include_str!("sql/remove_join_request.sql"), &[id]).await
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, FromSql)]
 #[serde(rename_all = "camelCase")]
 #[postgres(name = "restrained_members")]
@@ -359,7 +829,7 @@ async fn space_test() -> Result<(), crate::error::AppError> {
     let space_name = "Pure Illusion";
     let user = User::register(db, email, username, nickname, password).await.unwrap();
     let space = Space::create(db, space_name.to_string(), &user.id, String::new(), None, None).await?;
-    Space::edit(db, space.id, None, None, None, Some(true), None, None)
+    Space::edit(db, space.id, None, None, None, Some(true), None, None, None)
         .await?
         .unwrap();
     let space = Space::get_by_id(db, &space.id).await?.unwrap();
@@ -383,10 +853,28 @@ async fn space_test() -> Result<(), crate::error::AppError> {
         None,
         None,
         None,
+        Some("ja".to_string()),
     )
     .await?
     .unwrap();
     assert_eq!(space_edited.name, new_name);
+    assert_eq!(space_edited.language, "ja");
+    Space::edit(db, space.id, None, None, None, Some(true), Some(true), None, None)
+        .await?
+        .unwrap();
+    Space::refresh_stats(db).await?;
+    let explored = Space::explore(db, Some("ja"), None, ExploreSort::Members, None, 10).await?;
+    assert!(explored.iter().any(|(s, _)| s.space.id == space.id));
+    assert!(!Space::check_password(db, &space.id, "").await?);
+    Space::set_password(db, &space.id, "open sesame").await?;
+    assert!(Space::check_password(db, &space.id, "open sesame").await?);
+    assert!(!Space::check_password(db, &space.id, "open barley").await?);
+    Space::set_password(db, &space.id, "").await?;
+    assert!(!Space::check_password(db, &space.id, "").await?);
+    assert!(Space::set_archived(db, &space.id, true).await?.unwrap().archived);
+    assert!(Space::ensure_active(db, &space.id).await.is_err());
+    Space::set_archived(db, &space.id, false).await?;
+    Space::ensure_active(db, &space.id).await?;
 
     let _space_2 = Space::create(db, "学园都市".to_string(), &user.id, String::new(), None, None).await?;
     // let result = Space::edit(db, _space_2.id, Some(new_name.to_string())).await;
@@ -406,9 +894,65 @@ async fn space_test() -> Result<(), crate::error::AppError> {
     assert_eq!(joined.len(), 1);
     assert_eq!(joined[0].member.space_id, space.id);
 
+    // roles
+    let roles = SpaceRole::get_by_space(db, &space.id).await?;
+    assert_eq!(roles.len(), 3);
+    let role = SpaceRole::create(db, &space.id, "Player", Permissions::SEND_MESSAGES).await?;
+    SpaceRole::assign(db, &user.id, &space.id, &role.id).await?;
+    let assignments = SpaceRole::assignments(db, &space.id).await?;
+    assert_eq!(assignments.get(&user.id), Some(&vec![role.id]));
+    let role = SpaceRole::edit(db, &role.id, None, Some(Permissions::WHISPER))
+        .await?
+        .unwrap();
+    assert_eq!(role.permissions, Permissions::WHISPER);
+    let permissions = Permissions::space(db, &user.id, &space.id).await?.unwrap();
+    assert_eq!(permissions, Permissions::ALL);
+    let managers = Permissions::members_with(db, &space.id, Permissions::MANAGE_MEMBERS).await?;
+    assert_eq!(managers, vec![user.id]);
+    SpaceRole::unassign(db, &user.id, &space.id, &role.id).await?;
+    SpaceRole::delete(db, &role.id).await?;
+    assert!(SpaceRole::get(db, &role.id).await?.is_none());
+
+    // templates
+    let template = SpaceTemplate::create(db, &space.id, &user.id, "One Shot", "", false, true).await?;
+    let templates = SpaceTemplate::get_available(db, &user.id).await?;
+    assert!(templates.iter().any(|t| t.id == template.id));
+    SpaceTemplate::delete(db, &template.id).await?;
+    assert!(SpaceTemplate::get(db, &template.id).await?.is_none());
+
+    // ownership transfer
+    let transfer = OwnershipTransfer::create(db, &space.id, &user.id, &user.id).await?;
+    assert_eq!(transfer.to_user_id, user.id);
+    assert!(OwnershipTransfer::get(db, &space.id).await?.is_some());
+    OwnershipTransfer::remove(db, &space.id).await?;
+    assert!(OwnershipTransfer::get(db, &space.id).await?.is_none());
+    let owned = Space::set_owner(db, &space.id, &user.id).await?.unwrap();
+    assert_eq!(owned.owner_id, user.id);
+
+    // invites
+    let invite = SpaceInvite::create(db, &space.id, &user.id, "friends", Some(1), &[], None).await?;
+    let used = SpaceInvite::consume(db, &invite.id, &space.id).await?.unwrap();
+    assert_eq!(used.uses, 1);
+    assert!(SpaceInvite::consume(db, &invite.id, &space.id).await?.is_none());
+    let invite = SpaceInvite::create(db, &space.id, &user.id, "", None, &[], None).await?;
+    SpaceInvite::revoke(db, &invite.id).await?.unwrap();
+    assert!(SpaceInvite::consume(db, &invite.id, &space.id).await?.is_none());
+    assert_eq!(SpaceInvite::get_by_space(db, &space.id).await?.len(), 2);
+
     SpaceMember::remove_user(db, &user.id, &space.id).await?;
     assert!(SpaceMember::get(db, &user.id, &space.id).await?.is_none());
 
+    // join requests
+    let request = JoinRequest::create(db, &space.id, &user.id, "hello").await?;
+    let again = JoinRequest::create(db, &space.id, &user.id, "hello again").await?;
+    assert_eq!(request.id, again.id);
+    assert_eq!(JoinRequest::get_pending(db, &space.id).await?.len(), 1);
+    let decided = JoinRequest::decide(db, &request.id, false, &user.id).await?.unwrap();
+    assert_eq!(decided.status, JoinRequestStatus::Rejected);
+    assert!(JoinRequest::decide(db, &request.id, true, &user.id).await?.is_none());
+    assert!(JoinRequest::get_pending(db, &space.id).await?.is_empty());
+    assert_eq!(JoinRequest::get_by_user(db, &user.id).await?.len(), 1);
+
     // delete
     Space::delete(db, &space.id).await?;
     Ok(())
@@ -0,0 +1,239 @@
+use std::error::Error;
+use std::ops::{BitAnd, BitOr, Not};
+
+use bytes::BytesMut;
+use postgres_types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::database::Querist;
+use crate::error::{AppError, DbError};
+
+/// A set of permission bits, stored as `bigint` in `space_roles` and `channel_role_overrides`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub struct Permissions(pub i64);
+
+impl Permissions {
+    pub const NONE: Permissions = Permissions(0);
+    /// Implies every other permission.
+    pub const ADMINISTRATOR: Permissions = Permissions(1 << 0);
+    pub const MANAGE_SPACE: Permissions = Permissions(1 << 1);
+    pub const MANAGE_ROLES: Permissions = Permissions(1 << 2);
+    /// Edit and delete channels, grant masters.
+    pub const MANAGE_CHANNELS: Permissions = Permissions(1 << 3);
+    pub const MANAGE_MEMBERS: Permissions = Permissions(1 << 4);
+    pub const INVITE: Permissions = Permissions(1 << 5);
+    pub const CREATE_CHANNELS: Permissions = Permissions(1 << 6);
+    pub const SEND_MESSAGES: Permissions = Permissions(1 << 7);
+    pub const WHISPER: Permissions = Permissions(1 << 8);
+    pub const UPLOAD_MEDIA: Permissions = Permissions(1 << 9);
+    /// Move and fold messages of other members.
+    pub const MASTER: Permissions = Permissions(1 << 10);
+    /// Delete messages of other members.
+    pub const MODERATE_MESSAGES: Permissions = Permissions(1 << 11);
+    pub const VIEW_AUDIT_LOG: Permissions = Permissions(1 << 12);
+
+    pub const ALL: Permissions = Permissions((1 << 13) - 1);
+
+    pub const DEFAULT_EVERYONE: Permissions = Permissions(
+        Permissions::CREATE_CHANNELS.0
+            | Permissions::SEND_MESSAGES.0
+            | Permissions::WHISPER.0
+            | Permissions::UPLOAD_MEDIA.0,
+    );
+    pub const DEFAULT_ADMIN: Permissions = Permissions::ADMINISTRATOR;
+    pub const DEFAULT_MASTER: Permissions = Permissions::MASTER;
+
+    pub fn contains(self, other: Permissions) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Fold the permissions of roles and their channel overrides into the final permission set.
+    ///
+    /// Each item is `(role permissions, override allow, override deny)`.
+    pub fn compute<I>(is_owner: bool, roles: I) -> Permissions
+    where
+        I: IntoIterator<Item = (Permissions, Permissions, Permissions)>,
+    {
+        if is_owner {
+            return Permissions::ALL;
+        }
+        let mut base = Permissions::NONE;
+        let mut allow = Permissions::NONE;
+        let mut deny = Permissions::NONE;
+        for (permissions, role_allow, role_deny) in roles {
+            base = base | permissions;
+            allow = allow | role_allow;
+            deny = deny | role_deny;
+        }
+        if base.contains(Permissions::ADMINISTRATOR) {
+            return Permissions::ALL;
+        }
+        (base & !deny) | allow
+    }
+
+    /// Permissions of the user in the space, `None` if the user is not a member.
+    pub async fn space<T: Querist>(
+        db: &mut T,
+        user_id: &Uuid,
+        space_id: &Uuid,
+    ) -> Result<Option<Permissions>, DbError> {
+        let rows = db
+            .query(include_str!("sql/space_permissions.sql"), &[user_id, space_id])
+            .await?;
+        Ok(Permissions::from_rows(rows))
+    }
+
+    /// Permissions of the user in the channel with channel overrides applied,
+    /// `None` if the user is not a member of the space.
+    pub async fn channel<T: Querist>(
+        db: &mut T,
+        user_id: &Uuid,
+        channel_id: &Uuid,
+    ) -> Result<Option<Permissions>, DbError> {
+        let rows = db
+            .query(include_str!("sql/channel_permissions.sql"), &[user_id, channel_id])
+            .await?;
+        Ok(Permissions::from_rows(rows))
+    }
+
+    /// Members of the space having all the `needed` permissions, the owner included.
+    pub async fn members_with<T: Querist>(
+        db: &mut T,
+        space_id: &Uuid,
+        needed: Permissions,
+    ) -> Result<Vec<Uuid>, DbError> {
+        let rows = db
+            .query(
+                include_str!("sql/members_with_permissions.sql"),
+                &[space_id, &needed, &Permissions::ADMINISTRATOR],
+            )
+            .await?;
+        rows.into_iter().map(|row| row.try_get(0)).collect()
+    }
+
+    fn from_rows(rows: Vec<tokio_postgres::Row>) -> Option<Permissions> {
+        if rows.is_empty() {
+            return None;
+        }
+        let is_owner = rows.iter().any(|row| row.get(0));
+        let roles = rows.into_iter().map(|row| {
+            (
+                Permissions(row.get(1)),
+                Permissions(row.get::<_, Option<i64>>(2).unwrap_or(0)),
+                Permissions(row.get::<_, Option<i64>>(3).unwrap_or(0)),
+            )
+        });
+        Some(Permissions::compute(is_owner, roles))
+    }
+
+    pub fn require(self, needed: Permissions) -> Result<(), AppError> {
+        if self.contains(needed) {
+            Ok(())
+        } else {
+            Err(AppError::NoPermission(format!("missing permission {:#x}", needed.0)))
+        }
+    }
+}
+
+impl BitOr for Permissions {
+    type Output = Permissions;
+
+    fn bitor(self, rhs: Permissions) -> Permissions {
+        Permissions(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for Permissions {
+    type Output = Permissions;
+
+    fn bitand(self, rhs: Permissions) -> Permissions {
+        Permissions(self.0 & rhs.0)
+    }
+}
+
+impl Not for Permissions {
+    type Output = Permissions;
+
+    fn not(self) -> Permissions {
+        Permissions(!self.0 & Permissions::ALL.0)
+    }
+}
+
+impl<'a> FromSql<'a> for Permissions {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Permissions, Box<dyn Error + Sync + Send>> {
+        i64::from_sql(ty, raw).map(Permissions)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <i64 as FromSql>::accepts(ty)
+    }
+}
+
+impl ToSql for Permissions {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        self.0.to_sql(ty, out)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <i64 as ToSql>::accepts(ty)
+    }
+
+    to_sql_checked!();
+}
+
+/// Check the permissions of a user in a space; non-members have no permission at all.
+pub async fn space_permission<T: Querist>(
+    db: &mut T,
+    user_id: &Uuid,
+    space_id: &Uuid,
+    needed: Permissions,
+) -> Result<Permissions, AppError> {
+    let permissions = Permissions::space(db, user_id, space_id)
+        .await?
+        .ok_or_else(|| AppError::NoPermission(format!("user is not a member of the space")))?;
+    permissions.require(needed)?;
+    Ok(permissions)
+}
+
+/// Check the permissions of a user in a channel; non-members of the space have no permission at all.
+pub async fn channel_permission<T: Querist>(
+    db: &mut T,
+    user_id: &Uuid,
+    channel_id: &Uuid,
+    needed: Permissions,
+) -> Result<Permissions, AppError> {
+    let permissions = Permissions::channel(db, user_id, channel_id)
+        .await?
+        .ok_or_else(|| AppError::NoPermission(format!("user is not a member of the space")))?;
+    permissions.require(needed)?;
+    Ok(permissions)
+}
+
+#[test]
+fn permissions_test() {
+    let none = Permissions::NONE;
+    let everyone = Permissions::DEFAULT_EVERYONE;
+    assert_eq!(Permissions::compute(true, vec![]), Permissions::ALL);
+    assert_eq!(Permissions::compute(false, vec![(everyone, none, none)]), everyone);
+    let admin = Permissions::compute(
+        false,
+        vec![(everyone, none, none), (Permissions::ADMINISTRATOR, none, none)],
+    );
+    assert_eq!(admin, Permissions::ALL);
+    // a read-only channel
+    let muted = Permissions::compute(false, vec![(everyone, none, Permissions::SEND_MESSAGES)]);
+    assert!(!muted.contains(Permissions::SEND_MESSAGES));
+    assert!(muted.contains(Permissions::WHISPER));
+    // an allow override wins over a deny override
+    let co_master = Permissions::compute(
+        false,
+        vec![
+            (everyone, none, Permissions::SEND_MESSAGES),
+            (none, Permissions::SEND_MESSAGES | Permissions::MASTER, none),
+        ],
+    );
+    assert!(co_master.contains(Permissions::SEND_MESSAGES | Permissions::MASTER));
+    assert!(co_master.require(Permissions::MANAGE_SPACE).is_err());
+}
@@ -0,0 +1,3 @@
+INSERT INTO space_member_roles (user_id, space_id, role_id)
+VALUES ($1, $2, $3)
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,13 @@
+SELECT s.owner_id = $1 AS is_owner, r.permissions, o.allow, o.deny
+FROM channels ch
+    INNER JOIN spaces s ON s.id = ch.space_id AND s.deleted = false
+    INNER JOIN space_members sm ON sm.space_id = s.id AND sm.user_id = $1
+    LEFT JOIN channel_members cm ON cm.channel_id = ch.id AND cm.user_id = $1 AND cm.is_joined
+    INNER JOIN space_roles r ON r.space_id = s.id
+    LEFT JOIN channel_role_overrides o ON o.channel_id = ch.id AND o.role_id = r.id
+WHERE ch.id = $2
+  AND ch.deleted = false
+  AND (r.kind = 'Everyone'
+    OR (r.kind = 'Admin' AND sm.is_admin)
+    OR (r.kind = 'Master' AND cm.is_master IS true)
+    OR EXISTS(SELECT 1 FROM space_member_roles mr WHERE mr.role_id = r.id AND mr.user_id = $1));
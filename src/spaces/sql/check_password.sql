@@ -0,0 +1,3 @@
+SELECT password <> '' AND password = crypt($2, password)
+FROM spaces
+WHERE id = $1;
@@ -0,0 +1,14 @@
+WITH custom AS (
+    INSERT INTO space_roles (space_id, name, kind, permissions)
+        SELECT $2, name, kind, permissions
+        FROM space_roles
+        WHERE space_id = $1
+          AND kind = 'Custom'
+)
+UPDATE space_roles new_role
+SET permissions = old_role.permissions
+FROM space_roles old_role
+WHERE old_role.space_id = $1
+  AND new_role.space_id = $2
+  AND old_role.kind = new_role.kind
+  AND new_role.kind <> 'Custom';
@@ -1,3 +1,3 @@
 INSERT INTO spaces (name, owner_id, password, default_dice_type, description)
-VALUES ($1, $2, COALESCE($3, ''), COALESCE($4, 'd20'), $5)
+VALUES ($1, $2, CASE WHEN COALESCE($3, '') = '' THEN '' ELSE crypt($3, gen_salt('bf')) END, COALESCE($4, 'd20'), $5)
 RETURNING spaces;
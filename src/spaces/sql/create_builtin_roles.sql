@@ -0,0 +1,5 @@
+INSERT INTO space_roles (space_id, name, kind, permissions)
+VALUES ($1, 'Everyone', 'Everyone', $2),
+       ($1, 'Admin', 'Admin', $3),
+       ($1, 'Master', 'Master', $4)
+RETURNING space_roles;
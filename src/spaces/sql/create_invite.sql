@@ -0,0 +1,3 @@
+INSERT INTO space_invites (space_id, creator_id, label, max_uses, channel_ids, expires_at)
+VALUES ($1, $2, $3, $4, $5, $6)
+RETURNING space_invites;
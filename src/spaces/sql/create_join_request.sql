@@ -0,0 +1,4 @@
+INSERT INTO space_join_requests (space_id, user_id, message)
+VALUES ($1, $2, $3)
+ON CONFLICT (space_id, user_id) WHERE status = 'Pending' DO UPDATE SET message = $3
+RETURNING space_join_requests;
@@ -0,0 +1,3 @@
+INSERT INTO space_roles (space_id, name, permissions)
+VALUES ($1, $2, $3)
+RETURNING space_roles;
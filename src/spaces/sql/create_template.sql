@@ -0,0 +1,3 @@
+INSERT INTO space_templates (space_id, creator_id, name, description, is_public, include_documents)
+VALUES ($1, $2, $3, $4, $5, $6)
+RETURNING space_templates;
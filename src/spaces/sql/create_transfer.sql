@@ -0,0 +1,6 @@
+INSERT INTO space_ownership_transfers (space_id, from_user_id, to_user_id)
+VALUES ($1, $2, $3)
+ON CONFLICT (space_id) DO UPDATE SET from_user_id = $2,
+                                     to_user_id   = $3,
+                                     created      = (now() at time zone 'utc')
+RETURNING space_ownership_transfers;
@@ -0,0 +1,7 @@
+UPDATE space_join_requests
+SET status     = $2,
+    decided_by = $3,
+    decided    = (now() at time zone 'utc')
+WHERE id = $1
+  AND status = 'Pending'
+RETURNING space_join_requests;
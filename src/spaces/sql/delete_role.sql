@@ -0,0 +1,3 @@
+DELETE
+FROM space_roles
+WHERE id = $1 AND kind = 'Custom';
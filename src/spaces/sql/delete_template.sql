@@ -0,0 +1,3 @@
+DELETE
+FROM space_templates
+WHERE id = $1;
@@ -4,6 +4,7 @@ SET name              = COALESCE($2, name),
     default_dice_type = COALESCE($4, default_dice_type),
     explorable        = COALESCE($5, explorable),
     is_public         = COALESCE($6, is_public),
-    allow_spectator   = COALESCE($7, allow_spectator)
+    allow_spectator   = COALESCE($7, allow_spectator),
+    language          = COALESCE($8, language)
 WHERE id = $1
 RETURNING spaces;
@@ -0,0 +1,12 @@
+SELECT s, st.member_count, st.channel_count, st.last_message, st.member_count
+FROM space_stats st
+         INNER JOIN spaces s ON st.space_id = s.id
+WHERE s.deleted = false
+  AND s.explorable = true
+  AND s.is_public = true
+  AND s.archived = false
+  AND ($1::text IS NULL OR s.language = $1)
+  AND ($2::text IS NULL OR s.default_dice_type = $2)
+  AND ($3::bigint IS NULL OR (st.member_count, st.space_id) < ($3, $4::uuid))
+ORDER BY st.member_count DESC, st.space_id DESC
+LIMIT $5;
@@ -0,0 +1,3 @@
+SELECT space_join_requests
+FROM space_join_requests
+WHERE id = $1;
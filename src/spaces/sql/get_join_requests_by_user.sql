@@ -0,0 +1,5 @@
+SELECT space_join_requests
+FROM space_join_requests
+WHERE user_id = $1
+ORDER BY created DESC
+LIMIT 64;
@@ -0,0 +1,6 @@
+SELECT r, u
+FROM space_join_requests r
+         INNER JOIN users u ON r.user_id = u.id
+WHERE r.space_id = $1
+  AND r.status = 'Pending'
+ORDER BY r.created;
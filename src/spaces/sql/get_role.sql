@@ -0,0 +1,4 @@
+SELECT r
+FROM space_roles r
+WHERE r.id = $1
+LIMIT 1;
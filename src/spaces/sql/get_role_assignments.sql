@@ -0,0 +1,4 @@
+SELECT user_id, array_agg(role_id)
+FROM space_member_roles
+WHERE space_id = $1
+GROUP BY user_id;
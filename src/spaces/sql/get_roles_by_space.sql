@@ -0,0 +1,4 @@
+SELECT r
+FROM space_roles r
+WHERE r.space_id = $1
+ORDER BY r.created, r.kind;
@@ -0,0 +1,3 @@
+SELECT space_templates
+FROM space_templates
+WHERE id = $1;
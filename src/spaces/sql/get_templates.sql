@@ -0,0 +1,7 @@
+-- Public templates are only listed while their source space is public.
+SELECT t
+FROM space_templates t
+         INNER JOIN spaces s ON t.space_id = s.id
+WHERE ((t.is_public = true AND s.is_public = true) OR t.creator_id = $1)
+  AND s.deleted = false
+ORDER BY t.created DESC;
@@ -0,0 +1,3 @@
+SELECT space_ownership_transfers
+FROM space_ownership_transfers
+WHERE space_id = $1;
@@ -0,0 +1,4 @@
+SELECT archived
+FROM spaces
+WHERE id = $1 AND deleted = false
+LIMIT 1;
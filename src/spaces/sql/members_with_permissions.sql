@@ -0,0 +1,14 @@
+-- Members of space $1 whose roles grant all of $2, or $3 (administrator), and the owner.
+SELECT sm.user_id
+FROM spaces s
+    INNER JOIN space_members sm ON sm.space_id = s.id
+    LEFT JOIN space_roles r ON r.space_id = s.id
+        AND (r.kind = 'Everyone'
+            OR (r.kind = 'Admin' AND sm.is_admin)
+            OR EXISTS(SELECT 1 FROM space_member_roles mr WHERE mr.role_id = r.id AND mr.user_id = sm.user_id))
+WHERE s.id = $1
+  AND s.deleted = false
+GROUP BY sm.user_id, s.owner_id
+HAVING s.owner_id = sm.user_id
+    OR bit_or(r.permissions) & $2::bigint = $2::bigint
+    OR bit_or(r.permissions) & $3::bigint <> 0;
@@ -0,0 +1,36 @@
+-- Recounts the stats of explorable spaces, sorting keys of `explore_by_*.sql`.
+-- Only what visitors can see is counted: public channels and messages that are not whispered.
+INSERT INTO space_stats (space_id, member_count, channel_count, last_message, activity, refreshed)
+SELECT s.id,
+       members.count,
+       channels.count,
+       messages.last_message,
+       (extract(EPOCH FROM COALESCE(messages.last_message, s.created)) * 1000)::bigint,
+       (now() at time zone 'utc')
+FROM spaces s
+         CROSS JOIN LATERAL (SELECT count(*) AS count FROM space_members sm WHERE sm.space_id = s.id) members
+         CROSS JOIN LATERAL (SELECT count(*) AS count
+                             FROM channels c
+                             WHERE c.space_id = s.id
+                               AND c.deleted = false
+                               AND c.is_public = true) channels
+         CROSS JOIN LATERAL (SELECT max(last.created) AS last_message
+                             FROM channels c
+                                      CROSS JOIN LATERAL (SELECT m.created
+                                                          FROM messages m
+                                                          WHERE m.channel_id = c.id
+                                                            AND m.deleted = false
+                                                            AND m.whisper_to_users IS NULL
+                                                          ORDER BY m.created DESC
+                                                          LIMIT 1) last
+                             WHERE c.space_id = s.id
+                               AND c.deleted = false
+                               AND c.is_public = true) messages
+WHERE s.deleted = false
+  AND s.explorable = true
+  AND s.is_public = true
+ON CONFLICT (space_id) DO UPDATE SET member_count  = excluded.member_count,
+                                     channel_count = excluded.channel_count,
+                                     last_message  = excluded.last_message,
+                                     activity      = excluded.activity,
+                                     refreshed     = excluded.refreshed;
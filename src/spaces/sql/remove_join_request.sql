@@ -0,0 +1,4 @@
+DELETE
+FROM space_join_requests
+WHERE id = $1
+  AND status = 'Pending';
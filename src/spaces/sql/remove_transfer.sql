@@ -0,0 +1,3 @@
+DELETE
+FROM space_ownership_transfers
+WHERE space_id = $1;
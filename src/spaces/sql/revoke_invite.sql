@@ -0,0 +1,4 @@
+UPDATE space_invites
+SET revoked = true
+WHERE id = $1
+RETURNING space_invites;
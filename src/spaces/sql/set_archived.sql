@@ -0,0 +1,6 @@
+UPDATE spaces
+SET archived = $2,
+    modified = (now() at time zone 'utc')
+WHERE id = $1
+  AND deleted = false
+RETURNING spaces;
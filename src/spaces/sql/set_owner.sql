@@ -0,0 +1,5 @@
+UPDATE spaces
+SET owner_id = $2,
+    modified = (now() at time zone 'utc')
+WHERE id = $1
+RETURNING spaces;
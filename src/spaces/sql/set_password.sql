@@ -0,0 +1,3 @@
+UPDATE spaces
+SET password = CASE WHEN $2 = '' THEN '' ELSE crypt($2, gen_salt('bf')) END
+WHERE id = $1;
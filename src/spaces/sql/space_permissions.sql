@@ -0,0 +1,9 @@
+SELECT s.owner_id = $1 AS is_owner, r.permissions, NULL::bigint AS allow, NULL::bigint AS deny
+FROM spaces s
+    INNER JOIN space_members sm ON sm.space_id = s.id AND sm.user_id = $1
+    INNER JOIN space_roles r ON r.space_id = s.id
+WHERE s.id = $2
+  AND s.deleted = false
+  AND (r.kind = 'Everyone'
+    OR (r.kind = 'Admin' AND sm.is_admin)
+    OR EXISTS(SELECT 1 FROM space_member_roles mr WHERE mr.role_id = r.id AND mr.user_id = $1));
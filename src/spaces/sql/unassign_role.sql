@@ -0,0 +1,5 @@
+DELETE
+FROM space_member_roles
+WHERE user_id = $1
+  AND space_id = $2
+  AND role_id = $3;
@@ -0,0 +1,8 @@
+UPDATE space_invites
+SET uses = uses + 1
+WHERE id = $1
+  AND space_id = $2
+  AND NOT revoked
+  AND (expires_at IS NULL OR expires_at > (now() at time zone 'utc'))
+  AND (max_uses IS NULL OR uses < max_uses)
+RETURNING space_invites;
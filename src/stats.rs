@@ -0,0 +1,6 @@
+mod api;
+mod handlers;
+mod models;
+
+pub use handlers::router;
+pub use models::Stats;
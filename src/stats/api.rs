@@ -0,0 +1,8 @@
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsQuery {
+    pub id: Uuid,
+}
@@ -0,0 +1,39 @@
+use super::api::StatsQuery;
+use super::Stats;
+use crate::channels::Channel;
+use crate::csrf::authenticate;
+use crate::database;
+use crate::error::{AppError, Find};
+use crate::interface::{missing, ok_response, parse_query, Response};
+use crate::spaces::SpaceMember;
+use hyper::{Body, Request};
+
+async fn channel(req: Request<Body>) -> Result<Stats, AppError> {
+    let StatsQuery { id } = parse_query(req.uri())?;
+    let session = authenticate(&req).await.ok();
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    let channel = Channel::get_by_id(db, &id).await.or_not_found()?;
+    let user_id = session.as_ref().map(|session| &session.user_id);
+    channel.ensure_readable(db, user_id).await?;
+    Stats::channel(db, &id).await
+}
+
+async fn space(req: Request<Body>) -> Result<Stats, AppError> {
+    let session = authenticate(&req).await?;
+    let StatsQuery { id } = parse_query(req.uri())?;
+    let mut conn = database::get().await?;
+    let db = &mut *conn;
+    SpaceMember::get(db, &session.user_id, &id).await.or_no_permission()?;
+    Stats::space(db, &id).await
+}
+
+pub async fn router(req: Request<Body>, path: &str) -> Result<Response, AppError> {
+    use hyper::Method;
+
+    match (path, req.method().clone()) {
+        ("/channel", Method::GET) => channel(req).await.map(ok_response),
+        ("/space", Method::GET) => space(req).await.map(ok_response),
+        _ => missing(),
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::cache::{self, make_key};
+use crate::database::Querist;
+use crate::error::{AppError, DbError};
+
+/// Messages further apart than this are considered to be in different sessions.
+pub const SESSION_GAP_SECONDS: f64 = 60.0 * 60.0 * 2.0;
+
+const CACHE_SECONDS: usize = 60 * 10;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MemberStats {
+    pub sender_id: Uuid,
+    pub character_id: Option<Uuid>,
+    pub name: String,
+    pub messages: i64,
+    pub in_game: i64,
+    pub rolls: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyStats {
+    pub day: NaiveDate,
+    pub messages: i64,
+    pub in_game: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStats {
+    #[serde(with = "crate::date_format")]
+    pub start: NaiveDateTime,
+    #[serde(with = "crate::date_format")]
+    pub end: NaiveDateTime,
+    pub messages: i64,
+}
+
+/// Activity of a channel, or of all public channels of a space.
+///
+/// Whispers and system messages are not counted.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    pub messages: i64,
+    pub in_game: i64,
+    pub out_of_game: i64,
+    pub rolls: i64,
+    /// Grouped by sender and character, most active first.
+    pub members: Vec<MemberStats>,
+    pub daily: Vec<DailyStats>,
+    pub sessions: Vec<SessionStats>,
+}
+
+impl Stats {
+    async fn compute<T: Querist>(
+        db: &mut T,
+        channel_id: Option<&Uuid>,
+        space_id: Option<&Uuid>,
+    ) -> Result<Stats, DbError> {
+        let members: Vec<MemberStats> = db
+            .query(include_str!("sql/member_stats.sql"), &[&channel_id, &space_id])
+            .await?
+            .into_iter()
+            .map(|row| MemberStats {
+                sender_id: row.get(0),
+                character_id: row.get(1),
+                name: row.get(2),
+                messages: row.get(3),
+                in_game: row.get(4),
+                rolls: row.get(5),
+            })
+            .collect();
+        let daily = db
+            .query(include_str!("sql/daily_stats.sql"), &[&channel_id, &space_id])
+            .await?
+            .into_iter()
+            .map(|row| DailyStats {
+                day: row.get(0),
+                messages: row.get(1),
+                in_game: row.get(2),
+            })
+            .collect();
+        let sessions = db
+            .query(
+                include_str!("sql/session_stats.sql"),
+                &[&channel_id, &space_id, &SESSION_GAP_SECONDS],
+            )
+            .await?
+            .into_iter()
+            .map(|row| SessionStats {
+                start: row.get(0),
+                end: row.get(1),
+                messages: row.get(2),
+            })
+            .collect();
+        let messages = members.iter().map(|member| member.messages).sum();
+        let in_game = members.iter().map(|member| member.in_game).sum();
+        Ok(Stats {
+            messages,
+            in_game,
+            out_of_game: messages - in_game,
+            rolls: members.iter().map(|member| member.rolls).sum(),
+            members,
+            daily,
+            sessions,
+        })
+    }
+
+    async fn cached<T: Querist>(
+        db: &mut T,
+        key: Vec<u8>,
+        channel_id: Option<&Uuid>,
+        space_id: Option<&Uuid>,
+    ) -> Result<Stats, AppError> {
+        let mut cache = cache::conn().await;
+        if let Some(bytes) = cache.get(&*key).await? {
+            match serde_json::from_slice(&*bytes) {
+                Ok(stats) => return Ok(stats),
+                Err(e) => log::warn!("Failed to deserialize cached stats: {}", e),
+            }
+        }
+        let stats = Stats::compute(db, channel_id, space_id).await?;
+        let bytes = serde_json::to_vec(&stats).map_err(AppError::Serialize)?;
+        cache.set_with_expiration(&*key, &*bytes, CACHE_SECONDS).await?;
+        Ok(stats)
+    }
+
+    pub async fn channel<T: Querist>(db: &mut T, channel_id: &Uuid) -> Result<Stats, AppError> {
+        let key = make_key(b"channel", channel_id, b"stats");
+        Stats::cached(db, key, Some(channel_id), None).await
+    }
+
+    pub async fn space<T: Querist>(db: &mut T, space_id: &Uuid) -> Result<Stats, AppError> {
+        let key = make_key(b"space", space_id, b"stats");
+        Stats::cached(db, key, None, Some(space_id)).await
+    }
+
+    /// Drops the cached statistics a new message in the channel affects.
+    pub async fn invalidate(cache: &mut cache::Connection, space_id: &Uuid, channel_id: &Uuid) -> Result<(), AppError> {
+        cache.remove(&*make_key(b"channel", channel_id, b"stats")).await?;
+        cache.remove(&*make_key(b"space", space_id, b"stats")).await?;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn stats_test() -> Result<(), AppError> {
+    use crate::channels::{Channel, ChannelMember};
+    use crate::database::Client;
+    use crate::messages::Message;
+    use crate::spaces::{Space, SpaceMember};
+    use crate::users::User;
+
+    let mut client = Client::new().await?;
+    let mut trans = client.transaction().await?;
+    let db = &mut trans;
+    let mut cache = cache::conn().await;
+    let user = User::register(db, "stats@mythal.net", "stats_test", "Stats", "no password").await?;
+    let space = Space::create(db, "Stats Space".to_string(), &user.id, String::new(), None, None).await?;
+    SpaceMember::add_admin(db, &user.id, &space.id).await?;
+    let channel = Channel::create(db, &space.id, "Stats Channel", true, None).await?;
+    ChannelMember::add_user(db, &user.id, &channel.id, "", false).await?;
+    let roll = serde_json::json!({ "type": "Expr", "start": 0, "len": 3, "node": { "type": "Roll", "face": 20 } });
+    for (in_game, entities) in [(true, vec![roll]), (false, vec![])] {
+        Message::create(
+            db,
+            &mut cache,
+            None,
+            &channel.id,
+            &user.id,
+            "",
+            "Stats",
+            "1d20",
+            entities,
+            in_game,
+            false,
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+            vec![],
+        )
+        .await?;
+    }
+    let stats = Stats::channel(db, &channel.id).await?;
+    assert_eq!(stats.messages, 2);
+    assert_eq!(stats.in_game, 1);
+    assert_eq!(stats.rolls, 1);
+    assert_eq!(stats.members.len(), 1);
+    assert_eq!(stats.sessions.len(), 1);
+    Stats::invalidate(&mut cache, &space.id, &channel.id).await?;
+    Ok(())
+}
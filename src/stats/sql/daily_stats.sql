@@ -0,0 +1,27 @@
+-- Either $1: channel id or $2: space id, only public channels are counted for a space.
+WITH scoped AS (
+    SELECT m.*
+    FROM messages m
+             JOIN channels c ON c.id = m.channel_id
+    WHERE m.channel_id = $1
+      AND c.deleted = false
+      AND m.deleted = false
+      AND m.whisper_to_users IS NULL
+      AND m.system_event IS NULL
+    UNION ALL
+    SELECT m.*
+    FROM messages m
+             JOIN channels c ON c.id = m.channel_id
+    WHERE c.space_id = $2
+      AND c.is_public
+      AND c.deleted = false
+      AND m.deleted = false
+      AND m.whisper_to_users IS NULL
+      AND m.system_event IS NULL
+)
+SELECT s.created::date                     AS day,
+       count(*)                            AS messages,
+       count(*) FILTER (WHERE s.in_game)   AS in_game
+FROM scoped s
+GROUP BY day
+ORDER BY day;
@@ -0,0 +1,34 @@
+-- Either $1: channel id or $2: space id, only public channels are counted for a space.
+WITH scoped AS (
+    SELECT m.*
+    FROM messages m
+             JOIN channels c ON c.id = m.channel_id
+    WHERE m.channel_id = $1
+      AND c.deleted = false
+      AND m.deleted = false
+      AND m.whisper_to_users IS NULL
+      AND m.system_event IS NULL
+    UNION ALL
+    SELECT m.*
+    FROM messages m
+             JOIN channels c ON c.id = m.channel_id
+    WHERE c.space_id = $2
+      AND c.is_public
+      AND c.deleted = false
+      AND m.deleted = false
+      AND m.whisper_to_users IS NULL
+      AND m.system_event IS NULL
+)
+SELECT s.sender_id,
+       s.character_id,
+       s.name,
+       count(*)                       AS messages,
+       count(*) FILTER (WHERE s.in_game) AS in_game,
+       sum(r.rolls)::bigint           AS rolls
+FROM scoped s
+         CROSS JOIN LATERAL (
+    SELECT count(*) AS rolls
+    FROM jsonb_path_query(s.entities, 'strict $.** ? (@.type == "Roll")')
+    ) r
+GROUP BY s.sender_id, s.character_id, s.name
+ORDER BY messages DESC;
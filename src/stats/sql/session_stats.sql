@@ -0,0 +1,37 @@
+-- Either $1: channel id or $2: space id, only public channels are counted for a space.
+-- A gap longer than $3 seconds between two messages starts a new session.
+WITH scoped AS (
+    SELECT m.*
+    FROM messages m
+             JOIN channels c ON c.id = m.channel_id
+    WHERE m.channel_id = $1
+      AND c.deleted = false
+      AND m.deleted = false
+      AND m.whisper_to_users IS NULL
+      AND m.system_event IS NULL
+    UNION ALL
+    SELECT m.*
+    FROM messages m
+             JOIN channels c ON c.id = m.channel_id
+    WHERE c.space_id = $2
+      AND c.is_public
+      AND c.deleted = false
+      AND m.deleted = false
+      AND m.whisper_to_users IS NULL
+      AND m.system_event IS NULL
+),
+     marked AS (
+         SELECT s.created,
+                (lag(s.created) OVER w IS NULL OR s.created - lag(s.created) OVER w > make_interval(secs => $3))::int
+                    AS starts
+         FROM scoped s
+         WINDOW w AS (ORDER BY s.created)
+     ),
+     numbered AS (
+         SELECT created, sum(starts) OVER (ORDER BY created) AS session
+         FROM marked
+     )
+SELECT min(created) AS start, max(created) AS "end", count(*) AS messages
+FROM numbered
+GROUP BY session
+ORDER BY session;
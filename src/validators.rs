@@ -37,6 +37,8 @@ pub static PASSWORD: Validator<str> = Validator(&[
     ("Password length shall not be more than 128.", &max!(128)),
 ]);
 
+pub static SPACE_PASSWORD: Validator<str> = Validator(&[("Password length shall not be more than 128.", &max!(128))]);
+
 pub static NAME: Validator<str> = Validator(&[
     ("Name length shall not be less than 3.", &min!(3)),
     ("Name length shall not be more than 32.", &max!(32)),
@@ -72,6 +74,26 @@ pub static TOPIC: Validator<str> = Validator(&[("Topic shall not be more than 12
 
 pub static DESCRIPTION: Validator<str> = Validator(&[("Description shall not be more than 512.", &max!(512))]);
 
+pub static JOIN_REQUEST: Validator<str> =
+    Validator(&[("Join request message shall not be more than 512.", &max!(512))]);
+
+pub static LANGUAGE: Validator<str> =
+    Validator(&[("Language shall be an ISO 639-1 code.", &is_match!(r"^([a-z]{2})?$"))]);
+
+/// An emoji, or a shortcode such as `:tada:`.
+pub static EMOJI: Validator<str> = Validator(&[
+    ("Reaction shall not be empty.", &min!(1)),
+    ("Reaction shall not be more than 32.", &max!(32)),
+    ("Reaction shall not contain spaces.", &is_match!(r"^\S+$")),
+]);
+
+/// A message tag without the leading `#`, such as `clue` or `npc:Alice`.
+pub static TAG: Validator<str> = Validator(&[
+    ("Tag shall not be empty.", &min!(1)),
+    ("Tag shall not be more than 64.", &max!(64)),
+    ("Tag shall not contain spaces, `#` or `,`.", &is_match!(r"^[^\s#,]+$")),
+]);
+
 pub static DICE: Validator<str> = Validator(&[("Illegal dice format.", &is_match!(r"^d\d{1,3}|FATE$"))]);
 
 #[test]
@@ -89,4 +111,14 @@ fn validator_test() {
 
     assert!(EMAIL.run("").is_err());
     assert!(EMAIL.run("example@example.com").is_ok());
+
+    assert!(EMOJI.run("👍").is_ok());
+    assert!(EMOJI.run("👨‍👩‍👧").is_ok());
+    assert!(EMOJI.run(":tada:").is_ok());
+    assert!(EMOJI.run("").is_err());
+    assert!(EMOJI.run("a b").is_err());
+
+    assert!(TAG.run("npc:Alice").is_ok());
+    assert!(TAG.run("#clue").is_err());
+    assert!(TAG.run("two words").is_err());
 }